@@ -0,0 +1,173 @@
+//! Allergies and intolerances, with one reaction per row of the `reactions`
+//! Nested column.
+
+use fhir_model::r4b::codes::{
+    AllergyIntoleranceCriticality, AllergyIntoleranceSeverity, AllergyIntoleranceType,
+};
+use fhir_model::r4b::resources::{AllergyIntolerance, AllergyIntoleranceReaction};
+
+use super::clinical_time::{ClinicalTimeRef, parse_clinical_time};
+use super::error::{ConversionError, ConversionErrorReason};
+use super::fhir_r4b_schemav1::{
+    ConversionResult, note_absent, parse_optional_datetime, parse_reference,
+    parse_secondary_reference, rejected,
+};
+use super::util::{coded, double_unwrap, first_code};
+use crate::profile::FacilityProfile;
+use crate::schemav1::{self, FieldOutcome, ReactionSeverity};
+use crate::stats::ConversionStats;
+
+#[allow(dead_code)]
+pub fn convert_allergy_intolerance(
+    src: &AllergyIntolerance,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::AllergyIntolerance> {
+    allergy_row(src, profile, stats).map_err(|err| rejected(err, src.id.as_deref(), profile, stats))
+}
+
+fn allergy_row(
+    src: &AllergyIntolerance,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::AllergyIntolerance> {
+    let Some(allergy_id) = src.id.clone() else {
+        return Err(ConversionError::new(
+            ConversionErrorReason::MissingId,
+            "AllergyIntolerance.id",
+        ));
+    };
+
+    let subject = parse_reference(&src.patient, "Patient", "AllergyIntolerance.patient")?;
+    let (_, encounter) = match &src.encounter {
+        Some(reff) => parse_secondary_reference(
+            reff,
+            &["Encounter"],
+            profile,
+            stats,
+            "AllergyIntolerance.encounter",
+        )?,
+        None => Default::default(),
+    };
+
+    let allergy_type = match src.r#type {
+        None => schemav1::AllergyType::Unknown,
+        Some(AllergyIntoleranceType::Allergy) => schemav1::AllergyType::Allergy,
+        Some(AllergyIntoleranceType::Intolerance) => schemav1::AllergyType::Intolerance,
+    };
+    let criticality = match src.criticality {
+        None => schemav1::AllergyCriticality::Unknown,
+        Some(AllergyIntoleranceCriticality::Low) => schemav1::AllergyCriticality::Low,
+        Some(AllergyIntoleranceCriticality::High) => schemav1::AllergyCriticality::High,
+        Some(AllergyIntoleranceCriticality::UnableToAssess) => {
+            schemav1::AllergyCriticality::UnableToAssess
+        }
+    };
+
+    let (code, code_description, code_system) = match &src.code {
+        Some(concept) => coded(concept),
+        None => {
+            note_absent(
+                profile,
+                stats,
+                "AllergyIntolerance.code",
+                FieldOutcome::Missing,
+            )?;
+            Default::default()
+        }
+    };
+
+    let onset = parse_clinical_time(
+        src.onset.as_ref().map(ClinicalTimeRef::from),
+        profile,
+        stats,
+        "AllergyIntolerance.onset",
+    )?;
+    let (recorded_date, recorded_date_resolution) = parse_optional_datetime(
+        src.recorded_date.as_ref(),
+        profile,
+        stats,
+        "AllergyIntolerance.recordedDate",
+    )?;
+
+    let reactions = parse_reactions(&double_unwrap(&src.reaction), profile, stats);
+
+    Ok(schemav1::AllergyIntolerance {
+        id: allergy_id,
+        clinical_status: src
+            .clinical_status
+            .as_ref()
+            .map(first_code)
+            .unwrap_or_default(),
+        verification_status: src
+            .verification_status
+            .as_ref()
+            .map(first_code)
+            .unwrap_or_default(),
+        allergy_type,
+        category: src
+            .category
+            .iter()
+            .flatten()
+            .map(|category| category.to_string())
+            .collect(),
+        criticality,
+        code,
+        code_description,
+        code_system,
+        subject,
+        encounter,
+        onset_type: onset.time_type,
+        onset_start: onset.start,
+        onset_start_resolution: onset.start_resolution,
+        onset_end: onset.end,
+        onset_end_resolution: onset.end_resolution,
+        onset_age_low: onset.age_low,
+        onset_age_high: onset.age_high,
+        onset_age_unit: onset.age_unit,
+        onset_text: onset.text,
+        recorded_date,
+        recorded_date_resolution,
+        reactions_manifestation: reactions.manifestations,
+        reactions_manifestation_description: reactions.descriptions,
+        reactions_severity: reactions.severities,
+    })
+}
+
+/// One Vec per column of the `reactions` Nested column.
+#[derive(Default)]
+struct ReactionColumns {
+    manifestations: Vec<String>,
+    descriptions: Vec<String>,
+    severities: Vec<ReactionSeverity>,
+}
+
+/// Only the first manifestation of a reaction is kept, further ones are
+/// recorded as truncated.
+fn parse_reactions(
+    reactions: &[AllergyIntoleranceReaction],
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ReactionColumns {
+    let mut columns = ReactionColumns::default();
+    for reaction in reactions {
+        let manifestations = double_unwrap(&reaction.manifestation);
+        if manifestations.len() > 1 {
+            stats.record(
+                &profile.name,
+                "AllergyIntolerance.reaction.manifestation",
+                FieldOutcome::Truncated,
+            );
+        }
+        let (manifestation, description, _) = manifestations.first().map(coded).unwrap_or_default();
+        columns.manifestations.push(manifestation);
+        columns.descriptions.push(description);
+        columns.severities.push(match reaction.severity {
+            None => ReactionSeverity::Unknown,
+            Some(AllergyIntoleranceSeverity::Mild) => ReactionSeverity::Mild,
+            Some(AllergyIntoleranceSeverity::Moderate) => ReactionSeverity::Moderate,
+            Some(AllergyIntoleranceSeverity::Severe) => ReactionSeverity::Severe,
+        });
+    }
+    columns
+}
@@ -0,0 +1,179 @@
+//! The dateTime, Age, Period, Range or string choice elements, like
+//! Condition.onset[x], Procedure.performed[x] and AllergyIntolerance.onset[x].
+
+use fhir_model::DateTime;
+use fhir_model::r4b::resources::{
+    AllergyIntoleranceOnset, ConditionAbatement, ConditionOnset, ProcedurePerformed,
+};
+use fhir_model::r4b::types::{Period, Range};
+use time::OffsetDateTime;
+
+use super::fhir_r4b_schemav1::{ConversionResult, parse_datetime, parse_optional_datetime};
+use crate::profile::FacilityProfile;
+use crate::schemav1::{ClinicalTimeType, TimeResolution};
+use crate::stats::ConversionStats;
+
+/// One of the choice elements, which fhir_model keeps in an enum per element.
+pub(super) enum ClinicalTimeRef<'a> {
+    DateTime(&'a DateTime),
+    /// Value and unit of an Age.
+    Age(Option<f64>, String),
+    Period(&'a Period),
+    Range(&'a Range),
+    String(&'a str),
+}
+
+impl<'a> From<&'a ConditionOnset> for ClinicalTimeRef<'a> {
+    fn from(onset: &'a ConditionOnset) -> Self {
+        match onset {
+            ConditionOnset::DateTime(datetime) => ClinicalTimeRef::DateTime(datetime),
+            ConditionOnset::Age(age) => {
+                ClinicalTimeRef::Age(age.value, age_unit(&age.unit, &age.code))
+            }
+            ConditionOnset::Period(period) => ClinicalTimeRef::Period(period),
+            ConditionOnset::Range(range) => ClinicalTimeRef::Range(range),
+            ConditionOnset::String(string) => ClinicalTimeRef::String(string),
+        }
+    }
+}
+
+impl<'a> From<&'a ProcedurePerformed> for ClinicalTimeRef<'a> {
+    fn from(performed: &'a ProcedurePerformed) -> Self {
+        match performed {
+            ProcedurePerformed::DateTime(datetime) => ClinicalTimeRef::DateTime(datetime),
+            ProcedurePerformed::Age(age) => {
+                ClinicalTimeRef::Age(age.value, age_unit(&age.unit, &age.code))
+            }
+            ProcedurePerformed::Period(period) => ClinicalTimeRef::Period(period),
+            ProcedurePerformed::Range(range) => ClinicalTimeRef::Range(range),
+            ProcedurePerformed::String(string) => ClinicalTimeRef::String(string),
+        }
+    }
+}
+
+impl<'a> From<&'a ConditionAbatement> for ClinicalTimeRef<'a> {
+    fn from(abatement: &'a ConditionAbatement) -> Self {
+        match abatement {
+            ConditionAbatement::DateTime(datetime) => ClinicalTimeRef::DateTime(datetime),
+            ConditionAbatement::Age(age) => {
+                ClinicalTimeRef::Age(age.value, age_unit(&age.unit, &age.code))
+            }
+            ConditionAbatement::Period(period) => ClinicalTimeRef::Period(period),
+            ConditionAbatement::Range(range) => ClinicalTimeRef::Range(range),
+            ConditionAbatement::String(string) => ClinicalTimeRef::String(string),
+        }
+    }
+}
+
+impl<'a> From<&'a AllergyIntoleranceOnset> for ClinicalTimeRef<'a> {
+    fn from(onset: &'a AllergyIntoleranceOnset) -> Self {
+        match onset {
+            AllergyIntoleranceOnset::DateTime(datetime) => ClinicalTimeRef::DateTime(datetime),
+            AllergyIntoleranceOnset::Age(age) => {
+                ClinicalTimeRef::Age(age.value, age_unit(&age.unit, &age.code))
+            }
+            AllergyIntoleranceOnset::Period(period) => ClinicalTimeRef::Period(period),
+            AllergyIntoleranceOnset::Range(range) => ClinicalTimeRef::Range(range),
+            AllergyIntoleranceOnset::String(string) => ClinicalTimeRef::String(string),
+        }
+    }
+}
+
+/// The unit of an age, its code if it has no human readable one.
+fn age_unit(unit: &Option<String>, code: &Option<String>) -> String {
+    unit.clone().or_else(|| code.clone()).unwrap_or_default()
+}
+
+/// One element's worth of columns. Only the ones for its `time_type` are
+/// filled in.
+pub(super) struct ClinicalTime {
+    pub(super) time_type: ClinicalTimeType,
+    pub(super) start: Option<OffsetDateTime>,
+    pub(super) start_resolution: TimeResolution,
+    pub(super) end: Option<OffsetDateTime>,
+    pub(super) end_resolution: TimeResolution,
+    pub(super) age_low: Option<f64>,
+    pub(super) age_high: Option<f64>,
+    pub(super) age_unit: String,
+    pub(super) text: String,
+}
+
+impl ClinicalTime {
+    fn empty(time_type: ClinicalTimeType) -> Self {
+        ClinicalTime {
+            time_type,
+            start: None,
+            start_resolution: TimeResolution::Unknown,
+            end: None,
+            end_resolution: TimeResolution::Unknown,
+            age_low: None,
+            age_high: None,
+            age_unit: String::new(),
+            text: String::new(),
+        }
+    }
+}
+
+/// `path` is the path of the element without its type, e.g.
+/// `Condition.onset`.
+pub(super) fn parse_clinical_time(
+    src: Option<ClinicalTimeRef>,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+    path: &str,
+) -> ConversionResult<ClinicalTime> {
+    Ok(match src {
+        None => ClinicalTime::empty(ClinicalTimeType::None),
+        Some(ClinicalTimeRef::DateTime(datetime)) => {
+            let (start, start_resolution) =
+                parse_datetime(datetime, profile, stats, &format!("{}DateTime", path))?;
+            ClinicalTime {
+                start: Some(start),
+                start_resolution,
+                ..ClinicalTime::empty(ClinicalTimeType::DateTime)
+            }
+        }
+        Some(ClinicalTimeRef::Age(value, unit)) => ClinicalTime {
+            age_low: value,
+            age_high: value,
+            age_unit: unit,
+            ..ClinicalTime::empty(ClinicalTimeType::Age)
+        },
+        Some(ClinicalTimeRef::Period(period)) => {
+            let (start, start_resolution) = parse_optional_datetime(
+                period.start.as_ref(),
+                profile,
+                stats,
+                &format!("{}Period.start", path),
+            )?;
+            let (end, end_resolution) = parse_optional_datetime(
+                period.end.as_ref(),
+                profile,
+                stats,
+                &format!("{}Period.end", path),
+            )?;
+            ClinicalTime {
+                start,
+                start_resolution,
+                end,
+                end_resolution,
+                ..ClinicalTime::empty(ClinicalTimeType::Period)
+            }
+        }
+        Some(ClinicalTimeRef::Range(range)) => {
+            let bound = range.low.as_ref().or(range.high.as_ref());
+            ClinicalTime {
+                age_low: range.low.as_ref().and_then(|low| low.value),
+                age_high: range.high.as_ref().and_then(|high| high.value),
+                age_unit: bound
+                    .map(|bound| age_unit(&bound.unit, &bound.code))
+                    .unwrap_or_default(),
+                ..ClinicalTime::empty(ClinicalTimeType::Range)
+            }
+        }
+        Some(ClinicalTimeRef::String(text)) => ClinicalTime {
+            text: text.to_string(),
+            ..ClinicalTime::empty(ClinicalTimeType::String)
+        },
+    })
+}
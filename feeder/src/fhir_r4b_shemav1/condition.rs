@@ -0,0 +1,142 @@
+//! Conditions, for the problem list and encounter diagnoses.
+
+use fhir_model::r4b::resources::Condition;
+
+use super::clinical_time::{ClinicalTimeRef, parse_clinical_time};
+use super::error::{ConversionError, ConversionErrorReason};
+use super::fhir_r4b_schemav1::{
+    ConversionResult, note_absent, parse_optional_datetime, parse_secondary_reference,
+    parse_subject, rejected,
+};
+use super::util::{coded, double_unwrap, first_code};
+use crate::profile::FacilityProfile;
+use crate::schemav1::{self, ClinicalTimeType, FieldOutcome};
+use crate::stats::ConversionStats;
+
+#[allow(dead_code)]
+pub fn convert_condition(
+    src: &Condition,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::Condition> {
+    condition_row(src, profile, stats)
+        .map_err(|err| rejected(err, src.id.as_deref(), profile, stats))
+}
+
+fn condition_row(
+    src: &Condition,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::Condition> {
+    let Some(condition_id) = src.id.clone() else {
+        return Err(ConversionError::new(
+            ConversionErrorReason::MissingId,
+            "Condition.id",
+        ));
+    };
+
+    let subject = parse_subject(
+        &src.subject,
+        &["Group"],
+        profile,
+        stats,
+        "Condition.subject",
+    )?;
+    let (_, encounter) = match &src.encounter {
+        Some(reff) => {
+            parse_secondary_reference(reff, &["Encounter"], profile, stats, "Condition.encounter")?
+        }
+        None => Default::default(),
+    };
+
+    let (code, code_description, code_system) = match &src.code {
+        Some(concept) => coded(concept),
+        None => {
+            note_absent(profile, stats, "Condition.code", FieldOutcome::Missing)?;
+            Default::default()
+        }
+    };
+    let codings = src
+        .code
+        .as_ref()
+        .map(|concept| double_unwrap(&concept.coding))
+        .unwrap_or_default();
+
+    let onset = parse_clinical_time(
+        src.onset.as_ref().map(ClinicalTimeRef::from),
+        profile,
+        stats,
+        "Condition.onset",
+    )?;
+    if onset.time_type == ClinicalTimeType::None {
+        note_absent(profile, stats, "Condition.onset", FieldOutcome::Missing)?;
+    }
+    let abatement = parse_clinical_time(
+        src.abatement.as_ref().map(ClinicalTimeRef::from),
+        profile,
+        stats,
+        "Condition.abatement",
+    )?;
+
+    let (recorded_date, recorded_date_resolution) = parse_optional_datetime(
+        src.recorded_date.as_ref(),
+        profile,
+        stats,
+        "Condition.recordedDate",
+    )?;
+
+    Ok(schemav1::Condition {
+        id: condition_id,
+        clinical_status: src
+            .clinical_status
+            .as_ref()
+            .map(first_code)
+            .unwrap_or_default(),
+        verification_status: src
+            .verification_status
+            .as_ref()
+            .map(first_code)
+            .unwrap_or_default(),
+        category: double_unwrap(&src.category)
+            .iter()
+            .map(first_code)
+            .collect(),
+        code,
+        code_description,
+        code_system,
+        codings_code: codings
+            .iter()
+            .map(|coding| coding.code.clone().unwrap_or_default())
+            .collect(),
+        codings_display: codings
+            .iter()
+            .map(|coding| coding.display.clone().unwrap_or_default())
+            .collect(),
+        codings_system: codings
+            .iter()
+            .map(|coding| coding.system.clone().unwrap_or_default())
+            .collect(),
+        subject,
+        encounter,
+        onset_type: onset.time_type,
+        onset_start: onset.start,
+        onset_start_resolution: onset.start_resolution,
+        onset_end: onset.end,
+        onset_end_resolution: onset.end_resolution,
+        onset_age_low: onset.age_low,
+        onset_age_high: onset.age_high,
+        onset_age_unit: onset.age_unit,
+        onset_text: onset.text,
+        abatement_type: abatement.time_type,
+        abatement_start: abatement.start,
+        abatement_start_resolution: abatement.start_resolution,
+        abatement_end: abatement.end,
+        abatement_end_resolution: abatement.end_resolution,
+        abatement_age_low: abatement.age_low,
+        abatement_age_high: abatement.age_high,
+        abatement_age_unit: abatement.age_unit,
+        abatement_text: abatement.text,
+        recorded_date,
+        recorded_date_resolution,
+    })
+}
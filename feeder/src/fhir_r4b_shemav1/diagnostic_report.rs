@@ -0,0 +1,249 @@
+//! DiagnosticReports. Their result Observations are kept as ids so panels can
+//! be put back together with a join, and presentedForm attachments only as
+//! metadata.
+
+use fhir_model::r4b::codes::DiagnosticReportStatus;
+use fhir_model::r4b::resources::{DiagnosticReport, DiagnosticReportEffective};
+use fhir_model::r4b::types::{Attachment, Reference};
+use fhir_model::{Base64Binary, DateTime};
+
+use super::error::{ConversionError, ConversionErrorReason};
+use super::fhir_r4b_schemav1::{
+    ConversionResult, note_absent, parse_datetime, parse_effective, parse_secondary_reference,
+    parse_subject, rejected,
+};
+use super::util::{coded, double_unwrap, first_code};
+use crate::profile::FacilityProfile;
+use crate::schemav1::{self, FieldOutcome};
+use crate::stats::ConversionStats;
+
+#[allow(dead_code)]
+pub fn convert_diagnostic_report(
+    src: &DiagnosticReport,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::DiagnosticReport> {
+    diagnostic_report_row(src, profile, stats)
+        .map_err(|err| rejected(err, src.id.as_deref(), profile, stats))
+}
+
+fn diagnostic_report_row(
+    src: &DiagnosticReport,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::DiagnosticReport> {
+    let Some(report_id) = src.id.clone() else {
+        return Err(ConversionError::new(
+            ConversionErrorReason::MissingId,
+            "DiagnosticReport.id",
+        ));
+    };
+
+    let status = match src.status {
+        DiagnosticReportStatus::Registered => schemav1::DiagnosticReportStatus::Registered,
+        DiagnosticReportStatus::Partial => schemav1::DiagnosticReportStatus::Partial,
+        DiagnosticReportStatus::Preliminary => schemav1::DiagnosticReportStatus::Preliminary,
+        DiagnosticReportStatus::Final => schemav1::DiagnosticReportStatus::Final,
+        DiagnosticReportStatus::Amended => schemav1::DiagnosticReportStatus::Amended,
+        DiagnosticReportStatus::Corrected => schemav1::DiagnosticReportStatus::Corrected,
+        DiagnosticReportStatus::Appended => schemav1::DiagnosticReportStatus::Appended,
+        DiagnosticReportStatus::Cancelled => schemav1::DiagnosticReportStatus::Cancelled,
+        DiagnosticReportStatus::EnteredInError => schemav1::DiagnosticReportStatus::EnteredInError,
+        DiagnosticReportStatus::Unknown => schemav1::DiagnosticReportStatus::Unknown,
+    };
+
+    let subject = match &src.subject {
+        Some(reff) => parse_subject(
+            reff,
+            &["Group", "Device", "Location"],
+            profile,
+            stats,
+            "DiagnosticReport.subject",
+        )?,
+        None => {
+            note_absent(
+                profile,
+                stats,
+                "DiagnosticReport.subject",
+                FieldOutcome::Missing,
+            )?;
+            String::new()
+        }
+    };
+    let (_, encounter) = match &src.encounter {
+        Some(reff) => parse_secondary_reference(
+            reff,
+            &["Encounter"],
+            profile,
+            stats,
+            "DiagnosticReport.encounter",
+        )?,
+        None => Default::default(),
+    };
+
+    let (code, code_description, code_system) = coded(&src.code);
+
+    let (effective_start, effective_start_resolution, effective_end, effective_end_resolution) =
+        match &src.effective {
+            Some(DiagnosticReportEffective::DateTime(datetime)) => parse_effective(
+                Some(datetime),
+                None,
+                profile,
+                stats,
+                "DiagnosticReport.effective",
+            )?,
+            Some(DiagnosticReportEffective::Period(period)) => parse_effective(
+                None,
+                Some(period),
+                profile,
+                stats,
+                "DiagnosticReport.effective",
+            )?,
+            None => parse_effective(None, None, profile, stats, "DiagnosticReport.effective")?,
+        };
+    let issued = match &src.issued {
+        Some(instant) => Some(
+            parse_datetime(
+                &DateTime::DateTime(instant.clone()),
+                profile,
+                stats,
+                "DiagnosticReport.issued",
+            )?
+            .0,
+        ),
+        None => None,
+    };
+
+    let mut performers_type = vec![];
+    let mut performers_id = vec![];
+    for reff in double_unwrap(&src.performer) {
+        let (performer_type, performer_id) = parse_secondary_reference(
+            &reff,
+            &[
+                "Practitioner",
+                "PractitionerRole",
+                "Organization",
+                "CareTeam",
+            ],
+            profile,
+            stats,
+            "DiagnosticReport.performer",
+        )?;
+        if !performer_id.is_empty() {
+            performers_type.push(performer_type);
+            performers_id.push(performer_id);
+        }
+    }
+
+    let results = parse_results(&double_unwrap(&src.result), profile, stats)?;
+    let forms = parse_attachments(&double_unwrap(&src.presented_form), profile, stats);
+
+    Ok(schemav1::DiagnosticReport {
+        id: report_id,
+        status,
+        category: double_unwrap(&src.category)
+            .iter()
+            .map(first_code)
+            .collect(),
+        code,
+        code_description,
+        code_system,
+        subject,
+        encounter,
+        effective_start,
+        effective_start_resolution,
+        effective_end,
+        effective_end_resolution,
+        issued,
+        performers_type,
+        performers_id,
+        results,
+        presented_forms_content_type: forms.content_types,
+        presented_forms_language: forms.languages,
+        presented_forms_title: forms.titles,
+        presented_forms_url: forms.urls,
+        presented_forms_size: forms.sizes,
+        presented_forms_hash: forms.hashes,
+    })
+}
+
+/// Ids of the result Observations. Results we can't resolve are left out
+/// rather than kept as empty ids.
+fn parse_results(
+    results: &[Reference],
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<Vec<String>> {
+    let mut ids = vec![];
+    for reff in results {
+        let (_, id) = parse_secondary_reference(
+            reff,
+            &["Observation"],
+            profile,
+            stats,
+            "DiagnosticReport.result",
+        )?;
+        if !id.is_empty() {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
+/// One Vec per column of the `presented_forms` Nested column.
+#[derive(Default)]
+struct AttachmentColumns {
+    content_types: Vec<String>,
+    languages: Vec<String>,
+    titles: Vec<String>,
+    urls: Vec<String>,
+    sizes: Vec<Option<u32>>,
+    hashes: Vec<String>,
+}
+
+/// Inline data is dropped, and recorded as truncated, after it's been used
+/// for the size and hash when the attachment doesn't give them.
+fn parse_attachments(
+    attachments: &[Attachment],
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> AttachmentColumns {
+    let mut columns = AttachmentColumns::default();
+    for attachment in attachments {
+        let data = attachment.data.as_ref().map(|Base64Binary(data)| data);
+        if data.is_some() {
+            stats.record(
+                &profile.name,
+                "DiagnosticReport.presentedForm.data",
+                FieldOutcome::Truncated,
+            );
+        }
+        columns
+            .content_types
+            .push(attachment.content_type.clone().unwrap_or_default());
+        columns
+            .languages
+            .push(attachment.language.clone().unwrap_or_default());
+        columns
+            .titles
+            .push(attachment.title.clone().unwrap_or_default());
+        columns
+            .urls
+            .push(attachment.url.clone().unwrap_or_default());
+        columns.sizes.push(
+            attachment
+                .size
+                .or_else(|| data.and_then(|data| u32::try_from(data.len()).ok())),
+        );
+        columns.hashes.push(match (&attachment.hash, data) {
+            (Some(Base64Binary(hash)), _) => to_hex(hash),
+            (None, Some(data)) => to_hex(&sha1_smol::Sha1::from(data).digest().bytes()),
+            (None, None) => String::new(),
+        });
+    }
+    columns
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
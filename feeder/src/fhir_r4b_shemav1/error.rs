@@ -0,0 +1,95 @@
+use std::fmt;
+
+/// Why a resource couldn't be converted. These are meant to be grouped on, so
+/// keep the set small and put the detail in the path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConversionErrorReason {
+    /// The resource has no logical id.
+    MissingId,
+    /// An element the facility profile requires is absent.
+    MissingRequired,
+    /// A reference points at the wrong kind of resource.
+    BadReferenceType,
+    /// A partial date (year or year-month) couldn't be made into a date.
+    InvalidPartialDate,
+    /// The element is present but holds something we don't know how to store.
+    UnsupportedValue,
+}
+
+impl ConversionErrorReason {
+    /// Machine readable code for logs and tables.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConversionErrorReason::MissingId => "missing_id",
+            ConversionErrorReason::MissingRequired => "missing_required",
+            ConversionErrorReason::BadReferenceType => "bad_reference_type",
+            ConversionErrorReason::InvalidPartialDate => "invalid_partial_date",
+            ConversionErrorReason::UnsupportedValue => "unsupported_value",
+        }
+    }
+}
+
+impl fmt::Display for ConversionErrorReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionError {
+    pub reason: ConversionErrorReason,
+    /// FHIR element path of the offending element, e.g.
+    /// `Encounter.subject.reference`.
+    pub path: String,
+    pub resource_type: String,
+    /// None when the resource has no id, or it hasn't been attached yet.
+    pub resource_id: Option<String>,
+    /// Index of the resource's entry in Bundle.entry, None outside of
+    /// [`convert_bundle`](super::convert_bundle).
+    pub entry: Option<usize>,
+}
+
+impl ConversionError {
+    /// The resource type is taken from the first segment of the path.
+    pub fn new(reason: ConversionErrorReason, path: impl Into<String>) -> Self {
+        let path = path.into();
+        let resource_type = path.split('.').next().unwrap_or_default().to_string();
+        Self {
+            reason,
+            path,
+            resource_type,
+            resource_id: None,
+            entry: None,
+        }
+    }
+
+    /// Attaches the id of the resource being converted, if the error doesn't
+    /// have one already.
+    pub fn with_resource_id(mut self, id: Option<&str>) -> Self {
+        if self.resource_id.is_none() {
+            self.resource_id = id.map(str::to_string);
+        }
+        self
+    }
+
+    /// Attaches the index of the bundle entry the resource came from.
+    pub fn in_entry(mut self, index: usize) -> Self {
+        self.entry = Some(index);
+        self
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {} ({}/{})",
+            self.reason,
+            self.path,
+            self.resource_type,
+            self.resource_id.as_deref().unwrap_or("?")
+        )
+    }
+}
+
+impl std::error::Error for ConversionError {}
@@ -0,0 +1,196 @@
+//! Handlers for the extensions we know how to store, keyed by extension URL.
+//! Every [`FacilityProfile`] carries a registry with the US Core demographics
+//! and `patient-birthTime` in it, and local extensions can be added with
+//! [`ExtensionRegistry::register_patient`], e.g. with [`keep_as_text`] to
+//! store them in the generic `extensions` column.
+
+use std::collections::HashMap;
+
+use fhir_model::r4b::types::{Extension, ExtensionValue};
+
+use super::fhir_r4b_schemav1::{ConversionResult, parse_datetime};
+use crate::profile::FacilityProfile;
+use crate::schemav1::{AggregatePatient, FieldOutcome};
+use crate::stats::ConversionStats;
+
+pub const US_CORE_RACE: &str = "http://hl7.org/fhir/us/core/StructureDefinition/us-core-race";
+pub const US_CORE_ETHNICITY: &str =
+    "http://hl7.org/fhir/us/core/StructureDefinition/us-core-ethnicity";
+pub const US_CORE_BIRTH_SEX: &str =
+    "http://hl7.org/fhir/us/core/StructureDefinition/us-core-birthsex";
+pub const PATIENT_BIRTH_TIME: &str = "http://hl7.org/fhir/StructureDefinition/patient-birthTime";
+
+/// What a handler gets to work with besides the extension and the row.
+pub struct ExtensionContext<'a> {
+    pub profile: &'a FacilityProfile,
+    pub stats: &'a mut ConversionStats,
+    /// Path of the extension itself, e.g. `Patient.birthDate.extension`.
+    pub path: &'a str,
+}
+
+impl ExtensionContext<'_> {
+    /// Counts an extension we couldn't store, as one without a handler is.
+    pub fn truncated(&mut self) {
+        self.stats
+            .record(&self.profile.name, self.path, FieldOutcome::Truncated);
+    }
+}
+
+/// Stores what it can of an extension found on a Patient, or on one of its
+/// elements, in the row.
+pub type PatientExtensionHandler =
+    fn(&Extension, &mut AggregatePatient, &mut ExtensionContext) -> ConversionResult<()>;
+
+#[derive(Debug, Clone)]
+pub struct ExtensionRegistry {
+    patient: HashMap<String, PatientExtensionHandler>,
+}
+
+impl Default for ExtensionRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register_patient(US_CORE_RACE, us_core_race);
+        registry.register_patient(US_CORE_ETHNICITY, us_core_ethnicity);
+        registry.register_patient(US_CORE_BIRTH_SEX, us_core_birth_sex);
+        registry.register_patient(PATIENT_BIRTH_TIME, patient_birth_time);
+        registry
+    }
+}
+
+impl ExtensionRegistry {
+    /// A registry without even the built in handlers.
+    pub fn empty() -> Self {
+        Self {
+            patient: HashMap::new(),
+        }
+    }
+
+    /// Replaces any handler already registered for `url`.
+    pub fn register_patient(&mut self, url: impl Into<String>, handler: PatientExtensionHandler) {
+        self.patient.insert(url.into(), handler);
+    }
+
+    /// Runs the handler of each extension. Extensions without one are counted
+    /// as truncated at `path`.
+    pub(super) fn apply_patient(
+        &self,
+        extensions: &[Extension],
+        patient: &mut AggregatePatient,
+        profile: &FacilityProfile,
+        stats: &mut ConversionStats,
+        path: &str,
+    ) -> ConversionResult<()> {
+        for extension in extensions {
+            match self.patient.get(&extension.url) {
+                Some(handler) => {
+                    let mut context = ExtensionContext {
+                        profile,
+                        stats: &mut *stats,
+                        path,
+                    };
+                    handler(extension, patient, &mut context)?;
+                }
+                None => stats.record(&profile.name, path, FieldOutcome::Truncated),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Handler for local extensions, storing the URL and the value as text in the
+/// `extensions` column.
+pub fn keep_as_text(
+    extension: &Extension,
+    patient: &mut AggregatePatient,
+    _context: &mut ExtensionContext,
+) -> ConversionResult<()> {
+    let value = match &extension.value {
+        Some(ExtensionValue::String(value)) | Some(ExtensionValue::Code(value)) => value.clone(),
+        Some(ExtensionValue::Boolean(value)) => value.to_string(),
+        Some(ExtensionValue::Integer(value)) => value.to_string(),
+        Some(ExtensionValue::Decimal(value)) => value.to_string(),
+        Some(ExtensionValue::Coding(coding)) => coding.code.clone().unwrap_or_default(),
+        Some(other) => serde_json::to_string(other).unwrap_or_default(),
+        None => "".to_string(),
+    };
+    patient.extensions_url.push(extension.url.clone());
+    patient.extensions_value.push(value);
+    Ok(())
+}
+
+/// Codes and text of a US Core race or ethnicity extension.
+#[derive(Default)]
+struct OmbCategories {
+    omb: Vec<String>,
+    detailed: Vec<String>,
+    text: String,
+}
+
+fn omb_categories(extension: &Extension) -> OmbCategories {
+    let mut categories = OmbCategories::default();
+    for part in &extension.extension {
+        match (part.url.as_str(), &part.value) {
+            ("ombCategory", Some(ExtensionValue::Coding(coding))) => {
+                categories.omb.extend(coding.code.clone());
+            }
+            ("detailed", Some(ExtensionValue::Coding(coding))) => {
+                categories.detailed.extend(coding.code.clone());
+            }
+            ("text", Some(ExtensionValue::String(text))) => categories.text = text.clone(),
+            _ => {}
+        }
+    }
+    categories
+}
+
+fn us_core_race(
+    extension: &Extension,
+    patient: &mut AggregatePatient,
+    _context: &mut ExtensionContext,
+) -> ConversionResult<()> {
+    let race = omb_categories(extension);
+    patient.race_omb = race.omb;
+    patient.race_detailed = race.detailed;
+    patient.race_text = race.text;
+    Ok(())
+}
+
+fn us_core_ethnicity(
+    extension: &Extension,
+    patient: &mut AggregatePatient,
+    _context: &mut ExtensionContext,
+) -> ConversionResult<()> {
+    let ethnicity = omb_categories(extension);
+    patient.ethnicity_omb = ethnicity.omb;
+    patient.ethnicity_detailed = ethnicity.detailed;
+    patient.ethnicity_text = ethnicity.text;
+    Ok(())
+}
+
+fn us_core_birth_sex(
+    extension: &Extension,
+    patient: &mut AggregatePatient,
+    context: &mut ExtensionContext,
+) -> ConversionResult<()> {
+    match &extension.value {
+        Some(ExtensionValue::Code(code)) => patient.birth_sex = code.clone(),
+        _ => context.truncated(),
+    }
+    Ok(())
+}
+
+fn patient_birth_time(
+    extension: &Extension,
+    patient: &mut AggregatePatient,
+    context: &mut ExtensionContext,
+) -> ConversionResult<()> {
+    let Some(ExtensionValue::DateTime(birth_time)) = &extension.value else {
+        context.truncated();
+        return Ok(());
+    };
+    match parse_datetime(birth_time, context.profile, context.stats, context.path) {
+        Ok((birth_time, _)) => patient.birth_datetime = Some(birth_time),
+        Err(_) => context.truncated(),
+    }
+    Ok(())
+}
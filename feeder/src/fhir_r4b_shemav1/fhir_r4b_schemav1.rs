@@ -1,51 +1,212 @@
-use super::util::{double_unwrap, join_name};
+use super::allergy::convert_allergy_intolerance;
+use super::condition::convert_condition;
+use super::diagnostic_report::convert_diagnostic_report;
+use super::error::{ConversionError, ConversionErrorReason};
+use super::immunization::convert_immunization;
+use super::location::convert_location;
+use super::medication::{
+    BundleMedications, convert_medication_administration, convert_medication_request,
+    convert_medication_statement,
+};
+use super::observation::convert_observation;
+use super::procedure::convert_procedure;
+use super::provider::{convert_organization, convert_practitioner, convert_practitioner_role};
+use super::util::{coded, double_unwrap, first_code, join_name};
+use crate::profile::FacilityProfile;
 use crate::schemav1;
-use crate::schemav1::{AggregatePatient, Deceased, TimeResolution};
+use crate::schemav1::{AggregatePatient, Deceased, FieldOutcome, TimeResolution};
+use crate::stats::ConversionStats;
 use fhir_model::DateTime;
-use fhir_model::r4b::codes::{AddressType, EncounterStatus};
+use fhir_model::r4b::codes::{
+    AddressType, AdministrativeGender, ContactPointSystem, ContactPointUse,
+    EncounterLocationStatus, EncounterStatus, IdentifierUse, NameUse,
+};
 use fhir_model::r4b::resources::{Bundle, Resource};
-use fhir_model::r4b::types::Reference;
+use fhir_model::r4b::types::{
+    CodeableConcept, ContactPoint, HumanName, Identifier, Period, Reference,
+};
 use fhir_model::{
     Date,
     r4b::{
         codes::AddressUse,
-        resources::{Encounter, Patient, PatientDeceased},
+        resources::{
+            Encounter, EncounterDiagnosis, EncounterLocation, EncounterParticipant, Patient,
+            PatientDeceased,
+        },
     },
 };
-use time::{OffsetDateTime, Time, macros::date};
-
-#[derive(Debug)]
-pub struct ConversionError {}
+use serde::Deserialize;
+use time::{OffsetDateTime, Time};
 
 pub type ConversionResult<T> = Result<T, ConversionError>;
 
-const DD: time::Date = date!(2025 - 01 - 01);
+/// What to do with a bundle when some of its entries can't be converted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BundlePolicy {
+    /// Any bad entry fails the whole bundle.
+    #[default]
+    AllOrNothing,
+    /// Keep every entry that converts and report the rest in
+    /// [`ConvertedBundle::failures`].
+    BestEffort,
+}
 
 #[derive(Debug)]
 pub struct ConvertedBundle {
     pub resources: Vec<schemav1::Resource>,
+    /// Entries which couldn't be converted. Always empty under
+    /// [`BundlePolicy::AllOrNothing`].
+    pub failures: Vec<ConversionError>,
 }
 
+/// Resource types [`convert_bundle`] turns into rows, which are the ones a
+/// profile may accept.
+pub const CONVERTED_RESOURCE_TYPES: [&str; 15] = [
+    "Patient",
+    "Encounter",
+    "Observation",
+    "Condition",
+    "MedicationRequest",
+    "MedicationStatement",
+    "MedicationAdministration",
+    "Procedure",
+    "Immunization",
+    "AllergyIntolerance",
+    "DiagnosticReport",
+    "Practitioner",
+    "PractitionerRole",
+    "Organization",
+    "Location",
+];
+
+/// Element paths the converters check with [`FacilityProfile::requires`],
+/// which are the ones a profile may require.
+pub const REQUIRABLE_PATHS: [&str; 24] = [
+    "Patient.birthDate",
+    "Patient.name",
+    "Patient.address",
+    "Patient.gender",
+    "Patient.identifier",
+    "Encounter.subject",
+    "Encounter.period.start",
+    "Encounter.period.end",
+    "Observation.subject",
+    "Observation.effective",
+    "Condition.code",
+    "Condition.onset",
+    "MedicationRequest.medication",
+    "MedicationStatement.medication",
+    "MedicationStatement.effective",
+    "MedicationAdministration.medication",
+    "MedicationAdministration.effective",
+    "Procedure.code",
+    "Procedure.performed",
+    "AllergyIntolerance.code",
+    "DiagnosticReport.subject",
+    "DiagnosticReport.effective",
+    "Practitioner.name",
+    "Organization.name",
+];
+
 #[allow(dead_code)]
-pub fn convert_bundle(src: &Bundle) -> ConversionResult<ConvertedBundle> {
+pub fn convert_bundle(
+    src: &Bundle,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<ConvertedBundle> {
     let mut result = vec![];
+    let mut failures = vec![];
+    let medications = BundleMedications::from_bundle(src);
 
-    for entry in double_unwrap(&src.entry) {
-        if let Some(resource) = &entry.resource {
-            result.push(match resource {
-                Resource::Patient(res) => schemav1::Resource::Patient(convert_patient(res)?),
-                Resource::Encounter(res) => schemav1::Resource::Encounter(convert_encounter(res)?),
+    for (index, entry) in src.entry.iter().enumerate() {
+        if let Some(resource) = entry.as_ref().and_then(|entry| entry.resource.as_ref()) {
+            if !profile.accepts(resource.resource_type().as_ref()) {
+                continue;
+            }
+            let converted = match resource {
+                Resource::Patient(res) => {
+                    convert_patient(res, profile, stats).map(schemav1::Resource::Patient)
+                }
+                Resource::Encounter(res) => {
+                    convert_encounter(res, profile, stats).map(schemav1::Resource::Encounter)
+                }
+                Resource::Observation(res) => {
+                    convert_observation(res, profile, stats).map(schemav1::Resource::Observation)
+                }
+                Resource::Condition(res) => {
+                    convert_condition(res, profile, stats).map(schemav1::Resource::Condition)
+                }
+                Resource::MedicationRequest(res) => {
+                    convert_medication_request(res, &medications, profile, stats)
+                        .map(schemav1::Resource::MedicationRequest)
+                }
+                Resource::MedicationStatement(res) => {
+                    convert_medication_statement(res, &medications, profile, stats)
+                        .map(schemav1::Resource::MedicationStatement)
+                }
+                Resource::MedicationAdministration(res) => {
+                    convert_medication_administration(res, &medications, profile, stats)
+                        .map(schemav1::Resource::MedicationAdministration)
+                }
+                Resource::Procedure(res) => {
+                    convert_procedure(res, profile, stats).map(schemav1::Resource::Procedure)
+                }
+                Resource::Immunization(res) => {
+                    convert_immunization(res, profile, stats).map(schemav1::Resource::Immunization)
+                }
+                Resource::AllergyIntolerance(res) => {
+                    convert_allergy_intolerance(res, profile, stats)
+                        .map(schemav1::Resource::AllergyIntolerance)
+                }
+                Resource::DiagnosticReport(res) => convert_diagnostic_report(res, profile, stats)
+                    .map(schemav1::Resource::DiagnosticReport),
+                Resource::Practitioner(res) => {
+                    convert_practitioner(res, profile, stats).map(schemav1::Resource::Practitioner)
+                }
+                Resource::PractitionerRole(res) => convert_practitioner_role(res, profile, stats)
+                    .map(schemav1::Resource::PractitionerRole),
+                Resource::Organization(res) => {
+                    convert_organization(res, profile, stats).map(schemav1::Resource::Organization)
+                }
+                Resource::Location(res) => {
+                    convert_location(res, profile, stats).map(schemav1::Resource::Location)
+                }
                 _ => continue,
-            })
+            };
+            match (converted, profile.bundle_policy) {
+                (Ok(res), _) => result.push(res),
+                (Err(err), BundlePolicy::AllOrNothing) => return Err(err.in_entry(index)),
+                (Err(err), BundlePolicy::BestEffort) => failures.push(err.in_entry(index)),
+            }
         }
     }
-    Ok(ConvertedBundle { resources: result })
+    Ok(ConvertedBundle {
+        resources: result,
+        failures,
+    })
 }
 
 #[allow(dead_code)]
-pub fn convert_encounter(src: &Encounter) -> ConversionResult<schemav1::Encounter> {
+pub fn convert_encounter(
+    src: &Encounter,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::Encounter> {
+    encounter_row(src, profile, stats)
+        .map_err(|err| rejected(err, src.id.as_deref(), profile, stats))
+}
+
+fn encounter_row(
+    src: &Encounter,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::Encounter> {
     let Some(encounter_id) = src.id.clone() else {
-        return Err(ConversionError {});
+        return Err(ConversionError::new(
+            ConversionErrorReason::MissingId,
+            "Encounter.id",
+        ));
     };
 
     let status = match src.status {
@@ -61,118 +222,559 @@ pub fn convert_encounter(src: &Encounter) -> ConversionResult<schemav1::Encounte
     };
 
     let subject_id = if let Some(rref) = &src.subject {
-        Some(parse_patient_reference(rref)?)
+        Some(parse_reference(rref, "Patient", "Encounter.subject")?)
     } else {
+        note_absent(profile, stats, "Encounter.subject", FieldOutcome::Missing)?;
         None
     };
 
-    let (start, end): (Option<time::OffsetDateTime>, Option<time::OffsetDateTime>) =
-        if let Some(period) = &src.period {
-            (
-                match &period.start {
-                    Some(t) => Some(parse_datetime(t)?),
-                    None => None,
-                },
-                match &period.end {
-                    Some(t) => Some(parse_datetime(t)?),
-                    None => None,
-                },
-            )
-        } else {
-            (None, None)
-        };
+    let period = src.period.as_ref();
+    let (start, start_resolution) = parse_optional_datetime(
+        period.and_then(|period| period.start.as_ref()),
+        profile,
+        stats,
+        "Encounter.period.start",
+    )?;
+    let (end, end_resolution) = parse_optional_datetime(
+        period.and_then(|period| period.end.as_ref()),
+        profile,
+        stats,
+        "Encounter.period.end",
+    )?;
+    if start.is_none() {
+        note_absent(
+            profile,
+            stats,
+            "Encounter.period.start",
+            FieldOutcome::Missing,
+        )?;
+    }
+    if end.is_none() {
+        note_absent(
+            profile,
+            stats,
+            "Encounter.period.end",
+            FieldOutcome::Missing,
+        )?;
+    }
+
+    let types = parse_concepts(&double_unwrap(&src.r#type));
+    let reasons = parse_concepts(&double_unwrap(&src.reason_code));
+    let diagnoses = parse_diagnoses(&double_unwrap(&src.diagnosis), profile, stats)?;
+    let participants = parse_participants(&double_unwrap(&src.participant), profile, stats)?;
+    let locations = parse_locations(&double_unwrap(&src.location), profile, stats)?;
+
+    let hospitalization = src.hospitalization.as_ref();
+    let hospitalization_code =
+        |concept: Option<&CodeableConcept>| concept.map(first_code).unwrap_or_default();
+
+    let (_, service_provider) = match &src.service_provider {
+        Some(reff) => parse_secondary_reference(
+            reff,
+            &["Organization"],
+            profile,
+            stats,
+            "Encounter.serviceProvider",
+        )?,
+        None => Default::default(),
+    };
 
     Ok(schemav1::Encounter {
         id: encounter_id,
         status,
         subject: subject_id.unwrap_or_default(),
-        // TODO: Should we be using UNIX_EPOCH or some other special value?
-        period_start: start.unwrap_or(OffsetDateTime::UNIX_EPOCH),
-        period_end: end.unwrap_or(OffsetDateTime::UNIX_EPOCH),
+        period_start: start,
+        period_start_resolution: start_resolution,
+        period_end: end,
+        period_end_resolution: end_resolution,
         class_code: src.class.code.clone().unwrap_or_default(),
         class_description: src.class.display.clone().unwrap_or_default(),
         class_system: src.class.system.clone().unwrap_or_default(),
+        types_code: types.codes,
+        types_description: types.descriptions,
+        types_system: types.systems,
+        reasons_code: reasons.codes,
+        reasons_description: reasons.descriptions,
+        reasons_system: reasons.systems,
+        diagnoses_condition: diagnoses.conditions,
+        diagnoses_use: diagnoses.uses,
+        diagnoses_rank: diagnoses.ranks,
+        admit_source: hospitalization_code(hospitalization.and_then(|h| h.admit_source.as_ref())),
+        re_admission: hospitalization_code(hospitalization.and_then(|h| h.re_admission.as_ref())),
+        discharge_disposition: hospitalization_code(
+            hospitalization.and_then(|h| h.discharge_disposition.as_ref()),
+        ),
+        participants_type: participants.types,
+        participants_individual_type: participants.individual_types,
+        participants_individual: participants.individuals,
+        participants_period_start: participants.periods.starts,
+        participants_period_start_resolution: participants.periods.start_resolutions,
+        participants_period_end: participants.periods.ends,
+        participants_period_end_resolution: participants.periods.end_resolutions,
+        locations_location: locations.locations,
+        locations_status: locations.statuses,
+        locations_period_start: locations.periods.starts,
+        locations_period_start_resolution: locations.periods.start_resolutions,
+        locations_period_end: locations.periods.ends,
+        locations_period_end_resolution: locations.periods.end_resolutions,
+        service_provider,
     })
 }
 
-fn parse_datetime(src: &DateTime) -> ConversionResult<OffsetDateTime> {
-    match src {
-        fhir_model::DateTime::Date(date) => match &date {
-            Date::Year(year) => {
-                let Ok(d) = DD.replace_year(*year) else {
-                    return Err(ConversionError {});
-                };
-                Ok(d.with_time(Time::MIDNIGHT).assume_utc())
+/// Code, description and system columns for a repeated CodeableConcept,
+/// taken from its first coding with a code.
+#[derive(Default)]
+pub(super) struct ConceptColumns {
+    pub(super) codes: Vec<String>,
+    pub(super) descriptions: Vec<String>,
+    pub(super) systems: Vec<String>,
+}
+
+pub(super) fn parse_concepts(concepts: &[CodeableConcept]) -> ConceptColumns {
+    let mut columns = ConceptColumns::default();
+    for concept in concepts {
+        let (code, description, system) = coded(concept);
+        columns.codes.push(code);
+        columns.descriptions.push(description);
+        columns.systems.push(system);
+    }
+    columns
+}
+
+/// One Vec per column of the `diagnoses` Nested column.
+#[derive(Default)]
+struct DiagnosisColumns {
+    conditions: Vec<String>,
+    uses: Vec<String>,
+    ranks: Vec<Option<u32>>,
+}
+
+fn parse_diagnoses(
+    diagnoses: &[EncounterDiagnosis],
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<DiagnosisColumns> {
+    let mut columns = DiagnosisColumns::default();
+    for diagnosis in diagnoses {
+        let (_, condition) = parse_secondary_reference(
+            &diagnosis.condition,
+            &["Condition"],
+            profile,
+            stats,
+            "Encounter.diagnosis.condition",
+        )?;
+        columns.conditions.push(condition);
+        columns
+            .uses
+            .push(diagnosis.r#use.as_ref().map(first_code).unwrap_or_default());
+        columns.ranks.push(diagnosis.rank.map(|rank| rank.get()));
+    }
+    Ok(columns)
+}
+
+/// One Vec per column of the `participants` Nested column.
+#[derive(Default)]
+struct ParticipantColumns {
+    types: Vec<String>,
+    individual_types: Vec<String>,
+    individuals: Vec<String>,
+    periods: PeriodColumns,
+}
+
+fn parse_participants(
+    participants: &[EncounterParticipant],
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<ParticipantColumns> {
+    let mut columns = ParticipantColumns::default();
+    for participant in participants {
+        columns.types.push(
+            participant
+                .r#type
+                .iter()
+                .flatten()
+                .map(first_code)
+                .find(|code| !code.is_empty())
+                .unwrap_or_default(),
+        );
+        let (individual_type, individual) = match &participant.individual {
+            Some(reff) => parse_secondary_reference(
+                reff,
+                &["Practitioner", "PractitionerRole", "RelatedPerson"],
+                profile,
+                stats,
+                "Encounter.participant.individual",
+            )?,
+            None => Default::default(),
+        };
+        columns.individual_types.push(individual_type);
+        columns.individuals.push(individual);
+        columns.periods.push(
+            participant.period.as_ref(),
+            profile,
+            stats,
+            "Encounter.participant.period",
+        )?;
+    }
+    Ok(columns)
+}
+
+/// One Vec per column of the `locations` Nested column.
+#[derive(Default)]
+struct LocationColumns {
+    locations: Vec<String>,
+    statuses: Vec<schemav1::EncounterLocationStatus>,
+    periods: PeriodColumns,
+}
+
+fn parse_locations(
+    locations: &[EncounterLocation],
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<LocationColumns> {
+    let mut columns = LocationColumns::default();
+    for location in locations {
+        let (_, location_id) = parse_secondary_reference(
+            &location.location,
+            &["Location"],
+            profile,
+            stats,
+            "Encounter.location.location",
+        )?;
+        columns.locations.push(location_id);
+        columns.statuses.push(match location.status {
+            None => schemav1::EncounterLocationStatus::Unknown,
+            Some(EncounterLocationStatus::Planned) => schemav1::EncounterLocationStatus::Planned,
+            Some(EncounterLocationStatus::Active) => schemav1::EncounterLocationStatus::Active,
+            Some(EncounterLocationStatus::Reserved) => schemav1::EncounterLocationStatus::Reserved,
+            Some(EncounterLocationStatus::Completed) => {
+                schemav1::EncounterLocationStatus::Completed
             }
-            Date::YearMonth(year, month) => {
-                let Ok(d) = DD.replace_year(*year) else {
-                    return Err(ConversionError {});
-                };
-                let Ok(d) = d.replace_month(*month) else {
-                    return Err(ConversionError {});
-                };
-                Ok(d.with_time(Time::MIDNIGHT).assume_utc())
+        });
+        columns.periods.push(
+            location.period.as_ref(),
+            profile,
+            stats,
+            "Encounter.location.period",
+        )?;
+    }
+    Ok(columns)
+}
+
+/// Dates without a time are taken as midnight in the facility's timezone, the
+/// resolution says how much of it was really there.
+pub(super) fn parse_datetime(
+    src: &DateTime,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+    path: &str,
+) -> ConversionResult<(OffsetDateTime, TimeResolution)> {
+    match src {
+        fhir_model::DateTime::Date(date) => {
+            let (d, resolution) = parse_date(date, profile, path)?;
+            // Only the time of day is filled in for a full date, which the
+            // resolution already says
+            if resolution != TimeResolution::Day {
+                stats.record(&profile.name, path, FieldOutcome::Defaulted);
             }
-            Date::Date(date) => Ok(date.with_time(Time::MIDNIGHT).assume_utc()),
-        },
+            Ok((
+                d.with_time(Time::MIDNIGHT)
+                    .assume_offset(profile.utc_offset),
+                resolution,
+            ))
+        }
         fhir_model::DateTime::DateTime(instant) => {
             let fhir_model::Instant(offsetdatetime) = instant;
-            Ok(*offsetdatetime)
+            // Our DateTime columns only keep whole seconds
+            if offsetdatetime.nanosecond() != 0 {
+                stats.record(&profile.name, path, FieldOutcome::Truncated);
+            }
+            Ok((*offsetdatetime, TimeResolution::Second))
         }
     }
 }
 
-fn parse_patient_reference(reff: &Reference) -> ConversionResult<String> {
-    if let Some(ty) = &reff.r#type {
-        match ty.as_str() {
-            "Patient" => {}
-            _ => return Err(ConversionError {}),
+/// Like [`parse_datetime`], with NULL and an unknown resolution for absent
+/// values.
+pub(super) fn parse_optional_datetime(
+    src: Option<&DateTime>,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+    path: &str,
+) -> ConversionResult<(Option<OffsetDateTime>, TimeResolution)> {
+    match src {
+        Some(src) => {
+            let (datetime, resolution) = parse_datetime(src, profile, stats, path)?;
+            Ok((Some(datetime), resolution))
         }
+        None => Ok((None, TimeResolution::Unknown)),
+    }
+}
+
+/// An effective[x] which may only be a dateTime or a Period, like those of
+/// MedicationStatement and DiagnosticReport. `path` is the path of the
+/// element without its type, e.g. `MedicationStatement.effective`.
+pub(super) fn parse_effective(
+    datetime: Option<&DateTime>,
+    period: Option<&Period>,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+    path: &str,
+) -> ConversionResult<(
+    Option<OffsetDateTime>,
+    TimeResolution,
+    Option<OffsetDateTime>,
+    TimeResolution,
+)> {
+    if let Some(datetime) = datetime {
+        let (start, resolution) =
+            parse_datetime(datetime, profile, stats, &format!("{}DateTime", path))?;
+        return Ok((Some(start), resolution, None, TimeResolution::Unknown));
+    }
+    let (start, start_resolution) = parse_optional_datetime(
+        period.and_then(|period| period.start.as_ref()),
+        profile,
+        stats,
+        &format!("{}Period.start", path),
+    )?;
+    let (end, end_resolution) = parse_optional_datetime(
+        period.and_then(|period| period.end.as_ref()),
+        profile,
+        stats,
+        &format!("{}Period.end", path),
+    )?;
+    if start.is_none() && end.is_none() {
+        note_absent(profile, stats, path, FieldOutcome::Missing)?;
+    }
+    Ok((start, start_resolution, end, end_resolution))
+}
+
+/// The columns a Period takes up inside a Nested column.
+#[derive(Default)]
+pub(super) struct PeriodColumns {
+    pub(super) starts: Vec<Option<OffsetDateTime>>,
+    pub(super) start_resolutions: Vec<TimeResolution>,
+    pub(super) ends: Vec<Option<OffsetDateTime>>,
+    pub(super) end_resolutions: Vec<TimeResolution>,
+}
+
+impl PeriodColumns {
+    /// `path` is the path of the period itself, e.g. `Patient.name.period`.
+    pub(super) fn push(
+        &mut self,
+        period: Option<&Period>,
+        profile: &FacilityProfile,
+        stats: &mut ConversionStats,
+        path: &str,
+    ) -> ConversionResult<()> {
+        let (start, start_resolution) = parse_optional_datetime(
+            period.and_then(|period| period.start.as_ref()),
+            profile,
+            stats,
+            &format!("{}.start", path),
+        )?;
+        let (end, end_resolution) = parse_optional_datetime(
+            period.and_then(|period| period.end.as_ref()),
+            profile,
+            stats,
+            &format!("{}.end", path),
+        )?;
+        self.starts.push(start);
+        self.start_resolutions.push(start_resolution);
+        self.ends.push(end);
+        self.end_resolutions.push(end_resolution);
+        Ok(())
+    }
+}
+
+/// Partial dates have their missing month and day filled in from the profile.
+fn parse_date(
+    src: &Date,
+    profile: &FacilityProfile,
+    path: &str,
+) -> ConversionResult<(time::Date, TimeResolution)> {
+    let (year, month, resolution) = match src {
+        Date::Year(year) => (*year, profile.fill_month, TimeResolution::Year),
+        Date::YearMonth(year, month) => (*year, *month, TimeResolution::Month),
+        Date::Date(date) => return Ok((*date, TimeResolution::Day)),
     };
+    // fill_day may be past the end of a shorter month
+    let day = profile.fill_day.min(month.length(year));
+    let Ok(d) = time::Date::from_calendar_date(year, month, day) else {
+        return Err(ConversionError::new(
+            ConversionErrorReason::InvalidPartialDate,
+            path,
+        ));
+    };
+    Ok((d, resolution))
+}
+
+/// Fails if the profile requires the absent element, otherwise counts it as
+/// `outcome`, which is what we did about it.
+pub(super) fn note_absent(
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+    path: &str,
+    outcome: FieldOutcome,
+) -> ConversionResult<()> {
+    if profile.requires(path) {
+        return Err(ConversionError::new(
+            ConversionErrorReason::MissingRequired,
+            path,
+        ));
+    }
+    stats.record(&profile.name, path, outcome);
+    Ok(())
+}
+
+/// Finishes off an error for a rejected resource.
+pub(super) fn rejected(
+    err: ConversionError,
+    id: Option<&str>,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionError {
+    stats.record(&profile.name, &err.path, FieldOutcome::Rejected);
+    err.with_resource_id(id)
+}
+
+/// The id of the `resource_type` resource `reff` points at. `path` is the
+/// path of the Reference element itself, e.g. `Encounter.subject`.
+pub(super) fn parse_reference(
+    reff: &Reference,
+    resource_type: &str,
+    path: &str,
+) -> ConversionResult<String> {
+    let (_, id) = parse_any_reference(reff, &[resource_type], path)?;
+    Ok(id)
+}
+
+/// Like [`parse_reference`] for elements which may point at any of
+/// `resource_types`. Gives back the type along with the id.
+pub(super) fn parse_any_reference(
+    reff: &Reference,
+    resource_types: &[&str],
+    path: &str,
+) -> ConversionResult<(String, String)> {
+    if let Some(ty) = &reff.r#type
+        && !resource_types.contains(&ty.as_str())
+    {
+        return Err(ConversionError::new(
+            ConversionErrorReason::BadReferenceType,
+            format!("{}.type", path),
+        ));
+    }
     if let Some(ref_str) = &reff.reference {
+        let ref_path = format!("{}.reference", path);
+        // A conditional reference, e.g. `Practitioner?identifier=...`, has
+        // its type but no id
+        if let Some((ty, _)) = ref_str.split_once("?") {
+            let reason = if resource_types.contains(&ty) {
+                ConversionErrorReason::UnsupportedValue
+            } else {
+                ConversionErrorReason::BadReferenceType
+            };
+            return Err(ConversionError::new(reason, ref_path));
+        }
         // TODO: Here we assume it's a relative url
         let parts: Vec<&str> = ref_str.split("/").collect();
         if parts.len() < 2 {
-            return Err(ConversionError {});
+            return Err(ConversionError::new(
+                ConversionErrorReason::UnsupportedValue,
+                ref_path,
+            ));
         }
-        if parts[0] != "Patient" {
-            return Err(ConversionError {});
+        if !resource_types.contains(&parts[0]) {
+            return Err(ConversionError::new(
+                ConversionErrorReason::BadReferenceType,
+                ref_path,
+            ));
         }
-        return Ok(parts[1].to_string());
+        return Ok((parts[0].to_string(), parts[1].to_string()));
+    }
+
+    // Logical references by identifier alone can't be resolved to a row
+    Err(ConversionError::new(
+        ConversionErrorReason::UnsupportedValue,
+        path,
+    ))
+}
+
+/// The id of the Patient a subject element points at. Our rows only have a
+/// patient column, so subjects of `other_types` FHIR also allows, e.g. a
+/// Group, are left empty and counted as truncated.
+pub(super) fn parse_subject(
+    reff: &Reference,
+    other_types: &[&str],
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+    path: &str,
+) -> ConversionResult<String> {
+    let resource_types: Vec<&str> = ["Patient"].iter().chain(other_types).copied().collect();
+    let (ty, id) = parse_any_reference(reff, &resource_types, path)?;
+    if ty != "Patient" {
+        stats.record(&profile.name, path, FieldOutcome::Truncated);
+        return Ok(String::new());
     }
+    Ok(id)
+}
 
-    Err(ConversionError {})
+/// Like [`parse_any_reference`] for elements we can do without. References
+/// we can't resolve to an id are left empty and counted as truncated, one
+/// to the wrong type of resource still fails.
+pub(super) fn parse_secondary_reference(
+    reff: &Reference,
+    resource_types: &[&str],
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+    path: &str,
+) -> ConversionResult<(String, String)> {
+    match parse_any_reference(reff, resource_types, path) {
+        Err(err) if err.reason == ConversionErrorReason::UnsupportedValue => {
+            stats.record(&profile.name, path, FieldOutcome::Truncated);
+            Ok(Default::default())
+        }
+        parsed => parsed,
+    }
 }
 
 #[allow(dead_code)]
-pub fn convert_patient(src: &Patient) -> ConversionResult<AggregatePatient> {
+pub fn convert_patient(
+    src: &Patient,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<AggregatePatient> {
+    patient_row(src, profile, stats).map_err(|err| rejected(err, src.id.as_deref(), profile, stats))
+}
+
+fn patient_row(
+    src: &Patient,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<AggregatePatient> {
     let names = double_unwrap(&src.name);
-    let first = names.first();
+    if names.is_empty() {
+        note_absent(profile, stats, "Patient.name", FieldOutcome::Missing)?;
+    }
+    let display_name = display_name(&names);
+    let names = parse_names(&names, profile, stats, "Patient.name")?;
 
-    let (birth_time, birth_time_resolution): (Option<time::Date>, Option<TimeResolution>) =
+    let (birth_time, birth_time_resolution): (Option<time::Date>, TimeResolution) =
         match &src.birth_date {
-            Some(birth_date) => match birth_date {
-                Date::Year(year) => {
-                    let Ok(d) = DD.replace_year(*year) else {
-                        return Err(ConversionError {});
-                    };
-                    (Some(d), Some(TimeResolution::Year))
+            Some(birth_date) => {
+                let (d, resolution) = parse_date(birth_date, profile, "Patient.birthDate")?;
+                if !matches!(resolution, TimeResolution::Day) {
+                    stats.record(&profile.name, "Patient.birthDate", FieldOutcome::Defaulted);
                 }
-                Date::YearMonth(year, month) => {
-                    let Ok(d) = DD.replace_year(*year) else {
-                        return Err(ConversionError {});
-                    };
-                    let Ok(d) = d.replace_month(*month) else {
-                        return Err(ConversionError {});
-                    };
-                    (Some(d), Some(TimeResolution::Month))
-                }
-                Date::Date(date) => (Some(*date), Some(TimeResolution::Day)),
-            },
-            None => (None, None),
+                (Some(d), resolution)
+            }
+            None => {
+                note_absent(profile, stats, "Patient.birthDate", FieldOutcome::Missing)?;
+                (None, TimeResolution::Unknown)
+            }
         };
 
-    let (deceased, death_time): (Deceased, Option<OffsetDateTime>) = match &src.deceased {
+    let (deceased, death_time): (Deceased, Option<&DateTime>) = match &src.deceased {
         Some(deceased) => match deceased {
             PatientDeceased::Boolean(died) => (
                 if *died {
@@ -182,55 +784,318 @@ pub fn convert_patient(src: &Patient) -> ConversionResult<AggregatePatient> {
                 },
                 None,
             ),
-            PatientDeceased::DateTime(death_time) => {
-                (Deceased::Dead, Some(parse_datetime(death_time)?))
-            }
+            PatientDeceased::DateTime(death_time) => (Deceased::Dead, Some(death_time)),
         },
         None => (Deceased::Unknown, None),
     };
+    let (death_time, death_time_resolution) =
+        parse_optional_datetime(death_time, profile, stats, "Patient.deceasedDateTime")?;
 
-    let (uses, types, cities, lines, states, countries): AddressList =
-        parse_addresses(double_unwrap(&src.address))?;
+    let addresses = double_unwrap(&src.address);
+    if addresses.is_empty() {
+        note_absent(profile, stats, "Patient.address", FieldOutcome::Missing)?;
+    }
+    let addresses = parse_addresses(addresses, profile, stats, "Patient.address")?;
+
+    let gender = match src.gender {
+        Some(AdministrativeGender::Male) => schemav1::Gender::Male,
+        Some(AdministrativeGender::Female) => schemav1::Gender::Female,
+        Some(AdministrativeGender::Other) => schemav1::Gender::Other,
+        Some(AdministrativeGender::Unknown) => schemav1::Gender::Unknown,
+        None => {
+            note_absent(profile, stats, "Patient.gender", FieldOutcome::Missing)?;
+            schemav1::Gender::Unknown
+        }
+    };
+
+    let identifiers = double_unwrap(&src.identifier);
+    if identifiers.is_empty() {
+        note_absent(profile, stats, "Patient.identifier", FieldOutcome::Missing)?;
+    }
+    let identifiers = parse_identifiers(&identifiers);
+    let telecom = parse_telecom(&double_unwrap(&src.telecom));
+
+    let communication = double_unwrap(&src.communication);
+    let (_, managing_organization) = match &src.managing_organization {
+        Some(reff) => parse_secondary_reference(
+            reff,
+            &["Organization"],
+            profile,
+            stats,
+            "Patient.managingOrganization",
+        )?,
+        None => Default::default(),
+    };
 
     let Some(patient_id) = &src.id else {
-        return Err(ConversionError {});
+        return Err(ConversionError::new(
+            ConversionErrorReason::MissingId,
+            "Patient.id",
+        ));
     };
 
-    Ok(AggregatePatient {
+    let mut patient = AggregatePatient {
         id: patient_id.clone(),
-        name_given: first
-            .map(|first| join_name(&first.given))
+        name_given: display_name
+            .map(|name| join_name(&name.given))
             .unwrap_or("".to_string()),
-        name_family: first
+        name_family: display_name
             .and_then(|name| name.family.clone())
             .unwrap_or("".to_string()),
-        birth_time: birth_time.unwrap(),
-        birth_time_resolution: birth_time_resolution.unwrap(),
+        names_use: names.uses,
+        names_family: names.families,
+        names_given: names.givens,
+        names_prefix: names.prefixes,
+        names_suffix: names.suffixes,
+        names_period_start: names.periods.starts,
+        names_period_start_resolution: names.periods.start_resolutions,
+        names_period_end: names.periods.ends,
+        names_period_end_resolution: names.periods.end_resolutions,
+        birth_time,
+        birth_time_resolution,
         death_time,
+        death_time_resolution,
         deceased,
-        addresses_use: uses,
-        addresses_type: types,
-        addresses_city: cities,
-        addresses_line: lines,
-    })
+        gender,
+        active: src.active,
+        marital_status: src
+            .marital_status
+            .as_ref()
+            .map(first_code)
+            .unwrap_or_default(),
+        managing_organization,
+        identifiers_use: identifiers.uses,
+        identifiers_type: identifiers.types,
+        identifiers_system: identifiers.systems,
+        identifiers_value: identifiers.values,
+        telecom_system: telecom.systems,
+        telecom_use: telecom.uses,
+        telecom_value: telecom.values,
+        telecom_rank: telecom.ranks,
+        communication_language: communication
+            .iter()
+            .map(|communication| first_code(&communication.language))
+            .collect(),
+        communication_preferred: communication
+            .iter()
+            .map(|communication| communication.preferred.unwrap_or(false))
+            .collect(),
+        addresses_use: addresses.uses,
+        addresses_type: addresses.types,
+        addresses_city: addresses.cities,
+        addresses_line: addresses.lines,
+        addresses_district: addresses.districts,
+        addresses_state: addresses.states,
+        addresses_postal_code: addresses.postal_codes,
+        addresses_country: addresses.countries,
+        addresses_text: addresses.texts,
+        addresses_period_start: addresses.periods.starts,
+        addresses_period_start_resolution: addresses.periods.start_resolutions,
+        addresses_period_end: addresses.periods.ends,
+        addresses_period_end_resolution: addresses.periods.end_resolutions,
+        birth_datetime: None,
+        birth_sex: "".to_string(),
+        race_omb: vec![],
+        race_detailed: vec![],
+        race_text: "".to_string(),
+        ethnicity_omb: vec![],
+        ethnicity_detailed: vec![],
+        ethnicity_text: "".to_string(),
+        extensions_url: vec![],
+        extensions_value: vec![],
+    };
+
+    profile.extensions.apply_patient(
+        &src.extension,
+        &mut patient,
+        profile,
+        stats,
+        "Patient.extension",
+    )?;
+    if let Some(birth_date_ext) = &src.birth_date_ext {
+        profile.extensions.apply_patient(
+            &birth_date_ext.extension,
+            &mut patient,
+            profile,
+            stats,
+            "Patient.birthDate.extension",
+        )?;
+    }
+    Ok(patient)
+}
+
+/// The name to show for a patient. Official names win over usual ones, which
+/// win over the rest, while names which have ended (maiden, old or anything
+/// with a period end) come last. Ties go to whichever came first.
+pub(super) fn display_name(names: &[HumanName]) -> Option<&HumanName> {
+    let rank = |name: &HumanName| {
+        let ended = name
+            .period
+            .as_ref()
+            .is_some_and(|period| period.end.is_some());
+        match name.r#use {
+            _ if ended => 6,
+            Some(NameUse::Official) => 0,
+            Some(NameUse::Usual) => 1,
+            None => 2,
+            Some(NameUse::Temp) => 3,
+            Some(NameUse::Nickname) => 4,
+            Some(NameUse::Anonymous) => 5,
+            Some(NameUse::Old) | Some(NameUse::Maiden) => 6,
+        }
+    };
+    names.iter().min_by_key(|name| rank(name))
+}
+
+/// One Vec per column of the `names` Nested column.
+#[derive(Default)]
+pub(super) struct NameColumns {
+    pub(super) uses: Vec<schemav1::NameUse>,
+    pub(super) families: Vec<String>,
+    pub(super) givens: Vec<String>,
+    pub(super) prefixes: Vec<String>,
+    pub(super) suffixes: Vec<String>,
+    pub(super) periods: PeriodColumns,
 }
 
-type AddressList = (
-    Vec<schemav1::AddressUse>,
-    Vec<schemav1::AddressType>,
-    Vec<String>,
-    Vec<String>,
-    Vec<String>,
-    Vec<String>,
-);
+/// `path` is that of the names, e.g. `Patient.name`.
+pub(super) fn parse_names(
+    names: &[HumanName],
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+    path: &str,
+) -> ConversionResult<NameColumns> {
+    let mut columns = NameColumns::default();
+    for name in names {
+        columns.uses.push(match name.r#use {
+            None => schemav1::NameUse::Unknown,
+            Some(NameUse::Usual) => schemav1::NameUse::Usual,
+            Some(NameUse::Official) => schemav1::NameUse::Official,
+            Some(NameUse::Temp) => schemav1::NameUse::Temp,
+            Some(NameUse::Nickname) => schemav1::NameUse::Nickname,
+            Some(NameUse::Anonymous) => schemav1::NameUse::Anonymous,
+            Some(NameUse::Old) => schemav1::NameUse::Old,
+            Some(NameUse::Maiden) => schemav1::NameUse::Maiden,
+        });
+        columns
+            .families
+            .push(name.family.clone().unwrap_or_default());
+        columns.givens.push(join_name(&name.given));
+        columns.prefixes.push(join_name(&name.prefix));
+        columns.suffixes.push(join_name(&name.suffix));
+
+        columns.periods.push(
+            name.period.as_ref(),
+            profile,
+            stats,
+            &format!("{}.period", path),
+        )?;
+    }
+    Ok(columns)
+}
 
-fn parse_addresses(
+/// One Vec per column of the `identifiers` Nested column.
+#[derive(Default)]
+pub(super) struct IdentifierColumns {
+    pub(super) uses: Vec<schemav1::IdentifierUse>,
+    pub(super) types: Vec<String>,
+    pub(super) systems: Vec<String>,
+    pub(super) values: Vec<String>,
+}
+
+pub(super) fn parse_identifiers(identifiers: &[Identifier]) -> IdentifierColumns {
+    let mut columns = IdentifierColumns::default();
+    for identifier in identifiers {
+        columns.uses.push(match identifier.r#use {
+            None => schemav1::IdentifierUse::Unknown,
+            Some(IdentifierUse::Usual) => schemav1::IdentifierUse::Usual,
+            Some(IdentifierUse::Official) => schemav1::IdentifierUse::Official,
+            Some(IdentifierUse::Temp) => schemav1::IdentifierUse::Temp,
+            Some(IdentifierUse::Secondary) => schemav1::IdentifierUse::Secondary,
+            Some(IdentifierUse::Old) => schemav1::IdentifierUse::Old,
+        });
+        columns.types.push(
+            identifier
+                .r#type
+                .as_ref()
+                .map(first_code)
+                .unwrap_or_default(),
+        );
+        columns
+            .systems
+            .push(identifier.system.clone().unwrap_or_default());
+        columns
+            .values
+            .push(identifier.value.clone().unwrap_or_default());
+    }
+    columns
+}
+
+/// One Vec per column of the `telecom` Nested column.
+#[derive(Default)]
+struct TelecomColumns {
+    systems: Vec<schemav1::ContactPointSystem>,
+    uses: Vec<schemav1::ContactPointUse>,
+    values: Vec<String>,
+    ranks: Vec<Option<u32>>,
+}
+
+fn parse_telecom(contact_points: &[ContactPoint]) -> TelecomColumns {
+    let mut columns = TelecomColumns::default();
+    for contact_point in contact_points {
+        columns.systems.push(match contact_point.system {
+            None => schemav1::ContactPointSystem::Unknown,
+            Some(ContactPointSystem::Phone) => schemav1::ContactPointSystem::Phone,
+            Some(ContactPointSystem::Fax) => schemav1::ContactPointSystem::Fax,
+            Some(ContactPointSystem::Email) => schemav1::ContactPointSystem::Email,
+            Some(ContactPointSystem::Pager) => schemav1::ContactPointSystem::Pager,
+            Some(ContactPointSystem::Url) => schemav1::ContactPointSystem::Url,
+            Some(ContactPointSystem::Sms) => schemav1::ContactPointSystem::Sms,
+            Some(ContactPointSystem::Other) => schemav1::ContactPointSystem::Other,
+        });
+        columns.uses.push(match contact_point.r#use {
+            None => schemav1::ContactPointUse::Unknown,
+            Some(ContactPointUse::Home) => schemav1::ContactPointUse::Home,
+            Some(ContactPointUse::Work) => schemav1::ContactPointUse::Work,
+            Some(ContactPointUse::Temp) => schemav1::ContactPointUse::Temp,
+            Some(ContactPointUse::Old) => schemav1::ContactPointUse::Old,
+            Some(ContactPointUse::Mobile) => schemav1::ContactPointUse::Mobile,
+        });
+        columns
+            .values
+            .push(contact_point.value.clone().unwrap_or_default());
+        columns
+            .ranks
+            .push(contact_point.rank.map(|rank| rank.get()));
+    }
+    columns
+}
+
+/// One Vec per column of the `addresses` Nested column.
+#[derive(Default)]
+pub(super) struct AddressColumns {
+    pub(super) uses: Vec<schemav1::AddressUse>,
+    pub(super) types: Vec<schemav1::AddressType>,
+    pub(super) cities: Vec<String>,
+    pub(super) lines: Vec<String>,
+    pub(super) districts: Vec<String>,
+    pub(super) states: Vec<String>,
+    pub(super) postal_codes: Vec<String>,
+    pub(super) countries: Vec<String>,
+    pub(super) texts: Vec<String>,
+    pub(super) periods: PeriodColumns,
+}
+
+/// `path` is that of the addresses, e.g. `Patient.address`.
+pub(super) fn parse_addresses(
     addresses: Vec<fhir_model::r4b::types::Address>,
-) -> ConversionResult<AddressList> {
-    let (mut uses, mut types, mut cities, mut lines, mut states, mut countries): AddressList =
-        (vec![], vec![], vec![], vec![], vec![], vec![]);
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+    path: &str,
+) -> ConversionResult<AddressColumns> {
+    let mut columns = AddressColumns::default();
     for addr in addresses {
-        uses.push(match addr.r#use {
+        columns.uses.push(match addr.r#use {
             None => schemav1::AddressUse::Unknown,
             Some(addr_use) => match addr_use {
                 AddressUse::Old => schemav1::AddressUse::Old,
@@ -240,7 +1105,7 @@ fn parse_addresses(
                 AddressUse::Billing => schemav1::AddressUse::Billing,
             },
         });
-        types.push(match addr.r#type {
+        columns.types.push(match addr.r#type {
             None => schemav1::AddressType::Unknown,
             Some(addr_type) => match addr_type {
                 AddressType::Physical => schemav1::AddressType::Physical,
@@ -248,23 +1113,27 @@ fn parse_addresses(
                 AddressType::Both => schemav1::AddressType::Both,
             },
         });
-        cities.push(match addr.city {
-            None => "".to_string(),
-            Some(ref city) => city.clone(),
-        });
-        lines.push(join_name(&addr.line));
-        // TODO: Handle address text
-        states.push(addr.state.clone().unwrap_or("".to_string()));
-        countries.push(addr.country.clone().unwrap_or("".to_string()));
-        // postal_codes.push(addr.postal_code.clone().unwrap_or("".to_string()));
-        // periods.push
-        // addr.period
+        columns.cities.push(addr.city.clone().unwrap_or_default());
+        columns.lines.push(join_name(&addr.line));
+        columns
+            .districts
+            .push(addr.district.clone().unwrap_or_default());
+        columns.states.push(addr.state.clone().unwrap_or_default());
+        columns
+            .postal_codes
+            .push(addr.postal_code.clone().unwrap_or_default());
+        columns
+            .countries
+            .push(addr.country.clone().unwrap_or_default());
+        columns.texts.push(addr.text.clone().unwrap_or_default());
+
+        columns.periods.push(
+            addr.period.as_ref(),
+            profile,
+            stats,
+            &format!("{}.period", path),
+        )?;
     }
-    assert_eq!(uses.len(), types.len());
-    assert_eq!(uses.len(), cities.len());
-    assert_eq!(uses.len(), lines.len());
-    assert_eq!(uses.len(), states.len());
-    assert_eq!(uses.len(), countries.len());
 
-    Ok((uses, types, cities, lines, states, countries))
+    Ok(columns)
 }
@@ -0,0 +1,90 @@
+//! Immunizations, coded with CVX where the vaccine has it.
+
+use fhir_model::r4b::resources::{Immunization, ImmunizationOccurrence};
+
+use super::error::{ConversionError, ConversionErrorReason};
+use super::fhir_r4b_schemav1::{
+    ConversionResult, parse_datetime, parse_optional_datetime, parse_reference,
+    parse_secondary_reference, rejected,
+};
+use super::util::{coded_preferring, first_code};
+use crate::profile::FacilityProfile;
+use crate::schemav1::{self, TimeResolution};
+use crate::stats::ConversionStats;
+
+pub const CVX: &str = "http://hl7.org/fhir/sid/cvx";
+
+#[allow(dead_code)]
+pub fn convert_immunization(
+    src: &Immunization,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::Immunization> {
+    immunization_row(src, profile, stats)
+        .map_err(|err| rejected(err, src.id.as_deref(), profile, stats))
+}
+
+fn immunization_row(
+    src: &Immunization,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::Immunization> {
+    let Some(immunization_id) = src.id.clone() else {
+        return Err(ConversionError::new(
+            ConversionErrorReason::MissingId,
+            "Immunization.id",
+        ));
+    };
+
+    let subject = parse_reference(&src.patient, "Patient", "Immunization.patient")?;
+    let (_, encounter) = match &src.encounter {
+        Some(reff) => parse_secondary_reference(
+            reff,
+            &["Encounter"],
+            profile,
+            stats,
+            "Immunization.encounter",
+        )?,
+        None => Default::default(),
+    };
+
+    let (vaccine_code, vaccine_description, vaccine_system) =
+        coded_preferring(&src.vaccine_code, CVX);
+
+    let (occurrence, occurrence_resolution, occurrence_text) = match &src.occurrence {
+        ImmunizationOccurrence::DateTime(datetime) => {
+            let (datetime, resolution) =
+                parse_datetime(datetime, profile, stats, "Immunization.occurrenceDateTime")?;
+            (Some(datetime), resolution, String::new())
+        }
+        ImmunizationOccurrence::String(text) => (None, TimeResolution::Unknown, text.clone()),
+    };
+    let (recorded, recorded_resolution) = parse_optional_datetime(
+        src.recorded.as_ref(),
+        profile,
+        stats,
+        "Immunization.recorded",
+    )?;
+
+    Ok(schemav1::Immunization {
+        id: immunization_id,
+        status: src.status.clone(),
+        status_reason: src
+            .status_reason
+            .as_ref()
+            .map(first_code)
+            .unwrap_or_default(),
+        vaccine_code,
+        vaccine_description,
+        vaccine_system,
+        subject,
+        encounter,
+        occurrence,
+        occurrence_resolution,
+        occurrence_text,
+        recorded,
+        recorded_resolution,
+        primary_source: src.primary_source,
+        lot_number: src.lot_number.clone().unwrap_or_default(),
+    })
+}
@@ -0,0 +1,89 @@
+//! Locations, keeping `partOf` so the bed, room, ward, building and site
+//! hierarchy can be rebuilt, see [`LocationClosure`](crate::schemav1::LocationClosure).
+
+use fhir_model::r4b::resources::Location;
+
+use super::error::{ConversionError, ConversionErrorReason};
+use super::fhir_r4b_schemav1::{ConversionResult, parse_secondary_reference, rejected};
+use super::util::{double_unwrap, first_code, join_name};
+use crate::profile::FacilityProfile;
+use crate::schemav1;
+use crate::stats::ConversionStats;
+
+#[allow(dead_code)]
+pub fn convert_location(
+    src: &Location,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::Location> {
+    location_row(src, profile, stats)
+        .map_err(|err| rejected(err, src.id.as_deref(), profile, stats))
+}
+
+fn location_row(
+    src: &Location,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::Location> {
+    let Some(location_id) = src.id.clone() else {
+        return Err(ConversionError::new(
+            ConversionErrorReason::MissingId,
+            "Location.id",
+        ));
+    };
+
+    let (_, managing_organization) = match &src.managing_organization {
+        Some(reff) => parse_secondary_reference(
+            reff,
+            &["Organization"],
+            profile,
+            stats,
+            "Location.managingOrganization",
+        )?,
+        None => Default::default(),
+    };
+    let (_, part_of) = match &src.part_of {
+        Some(reff) => {
+            parse_secondary_reference(reff, &["Location"], profile, stats, "Location.partOf")?
+        }
+        None => Default::default(),
+    };
+
+    let address = src.address.as_ref();
+
+    Ok(schemav1::Location {
+        id: location_id,
+        status: src
+            .status
+            .map(|status| status.to_string())
+            .unwrap_or_default(),
+        name: src.name.clone().unwrap_or_default(),
+        aliases: double_unwrap(&src.alias),
+        mode: src.mode.map(|mode| mode.to_string()).unwrap_or_default(),
+        types: double_unwrap(&src.r#type).iter().map(first_code).collect(),
+        physical_type: src
+            .physical_type
+            .as_ref()
+            .map(first_code)
+            .unwrap_or_default(),
+        address_line: address
+            .map(|address| join_name(&address.line))
+            .unwrap_or_default(),
+        address_city: address
+            .and_then(|address| address.city.clone())
+            .unwrap_or_default(),
+        address_state: address
+            .and_then(|address| address.state.clone())
+            .unwrap_or_default(),
+        address_postal_code: address
+            .and_then(|address| address.postal_code.clone())
+            .unwrap_or_default(),
+        address_country: address
+            .and_then(|address| address.country.clone())
+            .unwrap_or_default(),
+        latitude: src.position.as_ref().map(|position| position.latitude),
+        longitude: src.position.as_ref().map(|position| position.longitude),
+        managing_organization,
+        part_of,
+    })
+}
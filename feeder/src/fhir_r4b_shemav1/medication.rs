@@ -0,0 +1,584 @@
+//! MedicationRequest, MedicationStatement and MedicationAdministration. Their
+//! medication[x] is resolved to the Medication it points at, which has to be
+//! in the same bundle or contained in the resource, so every row can carry
+//! the medication's code.
+
+use std::collections::HashMap;
+
+use fhir_model::r4b::resources::{
+    Bundle, Medication, MedicationAdministration, MedicationAdministrationEffective,
+    MedicationAdministrationMedication, MedicationRequest, MedicationRequestMedication,
+    MedicationStatement, MedicationStatementEffective, MedicationStatementMedication, Resource,
+};
+use fhir_model::r4b::types::{CodeableConcept, Dosage, DosageDoseAndRateDose, Reference};
+
+use super::error::{ConversionError, ConversionErrorReason};
+use super::fhir_r4b_schemav1::{
+    ConversionResult, note_absent, parse_effective, parse_optional_datetime,
+    parse_secondary_reference, parse_subject, rejected,
+};
+use super::util::{coded_preferring, double_unwrap, first_code};
+use crate::profile::FacilityProfile;
+use crate::schemav1::{self, FieldOutcome};
+use crate::stats::ConversionStats;
+
+pub const RXNORM: &str = "http://www.nlm.nih.gov/research/umls/rxnorm";
+
+/// The Medications of a bundle, by every reference that can point at them:
+/// their `fullUrl` and `Medication/<id>`.
+#[derive(Debug, Default)]
+pub struct BundleMedications<'a> {
+    by_reference: HashMap<String, &'a Medication>,
+}
+
+impl<'a> BundleMedications<'a> {
+    pub fn from_bundle(bundle: &'a Bundle) -> Self {
+        let mut by_reference = HashMap::new();
+        for entry in bundle.entry.iter().flatten() {
+            let Some(Resource::Medication(medication)) = &entry.resource else {
+                continue;
+            };
+            if let Some(full_url) = &entry.full_url {
+                by_reference.insert(full_url.clone(), medication);
+            }
+            if let Some(id) = &medication.id {
+                by_reference.insert(format!("Medication/{}", id), medication);
+            }
+        }
+        BundleMedications { by_reference }
+    }
+
+    /// `contained` is the contained resources of the resource `reff` is on.
+    fn find<'b>(&'b self, reff: &Reference, contained: &'b [Resource]) -> Option<&'b Medication>
+    where
+        'a: 'b,
+    {
+        let reference = reff.reference.as_deref()?;
+        if let Some(id) = reference.strip_prefix('#') {
+            return contained.iter().find_map(|resource| match resource {
+                Resource::Medication(medication) if medication.id.as_deref() == Some(id) => {
+                    Some(medication)
+                }
+                _ => None,
+            });
+        }
+        if let Some(medication) = self.by_reference.get(reference) {
+            return Some(medication);
+        }
+        // An absolute url to a Medication whose entry has some other fullUrl
+        let mut parts = reference.rsplit('/');
+        let (id, ty) = (parts.next()?, parts.next()?);
+        self.by_reference.get(&format!("{}/{}", ty, id)).copied()
+    }
+}
+
+/// medication[x], which fhir_model keeps in a different enum per resource.
+enum MedicationRef<'a> {
+    CodeableConcept(&'a CodeableConcept),
+    Reference(&'a Reference),
+}
+
+/// The medication columns of a row.
+#[derive(Default)]
+struct MedicationColumns {
+    id: String,
+    code: String,
+    description: String,
+    system: String,
+}
+
+/// `path` is the path of the element without its type, e.g.
+/// `MedicationRequest.medication`. It's noted as missing when we end up
+/// without a code, so facilities can require one.
+fn parse_medication(
+    src: MedicationRef,
+    medications: &BundleMedications,
+    contained: &[Resource],
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+    path: &str,
+) -> ConversionResult<MedicationColumns> {
+    let mut columns = MedicationColumns::default();
+    let concept = match src {
+        MedicationRef::CodeableConcept(concept) => Some(concept),
+        MedicationRef::Reference(reff) => match medications.find(reff, contained) {
+            Some(medication) => {
+                columns.id = medication.id.clone().unwrap_or_default();
+                medication.code.as_ref()
+            }
+            None => {
+                // Keep the id so the row can still be joined up later
+                let reference_path = format!("{}Reference", path);
+                if reff
+                    .reference
+                    .as_deref()
+                    .is_some_and(|r| r.starts_with('#'))
+                {
+                    stats.record(&profile.name, &reference_path, FieldOutcome::Truncated);
+                } else {
+                    (_, columns.id) = parse_secondary_reference(
+                        reff,
+                        &["Medication"],
+                        profile,
+                        stats,
+                        &reference_path,
+                    )?;
+                }
+                None
+            }
+        },
+    };
+    if let Some(concept) = concept {
+        (columns.code, columns.description, columns.system) = coded_preferring(concept, RXNORM);
+    }
+    if columns.code.is_empty() {
+        note_absent(profile, stats, path, FieldOutcome::Missing)?;
+    }
+    Ok(columns)
+}
+
+/// One Vec per column of the `dosages` Nested column.
+#[derive(Default)]
+struct DosageColumns {
+    sequences: Vec<Option<i32>>,
+    texts: Vec<String>,
+    routes: Vec<String>,
+    dose_values: Vec<Option<f64>>,
+    dose_lows: Vec<Option<f64>>,
+    dose_highs: Vec<Option<f64>>,
+    dose_units: Vec<String>,
+    frequencies: Vec<Option<u32>>,
+    periods: Vec<Option<f64>>,
+    period_units: Vec<String>,
+    timing_codes: Vec<String>,
+}
+
+/// `path` is the path of the dosages, e.g.
+/// `MedicationRequest.dosageInstruction`.
+fn parse_dosages(
+    dosages: &[Dosage],
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+    path: &str,
+) -> DosageColumns {
+    let mut columns = DosageColumns::default();
+    for dosage in dosages {
+        columns.sequences.push(dosage.sequence);
+        columns.texts.push(dosage.text.clone().unwrap_or_default());
+        columns
+            .routes
+            .push(dosage.route.as_ref().map(first_code).unwrap_or_default());
+
+        let doses: Vec<&DosageDoseAndRateDose> = dosage
+            .dose_and_rate
+            .iter()
+            .flatten()
+            .filter_map(|dose_and_rate| dose_and_rate.dose.as_ref())
+            .collect();
+        // Only the first dose fits in the row
+        if doses.len() > 1 {
+            stats.record(
+                &profile.name,
+                &format!("{}.doseAndRate", path),
+                FieldOutcome::Truncated,
+            );
+        }
+        let (value, low, high, unit) = match doses.first() {
+            Some(DosageDoseAndRateDose::Quantity(quantity)) => {
+                (quantity.value, None, None, quantity.unit.clone())
+            }
+            Some(DosageDoseAndRateDose::Range(range)) => (
+                None,
+                range.low.as_ref().and_then(|low| low.value),
+                range.high.as_ref().and_then(|high| high.value),
+                range
+                    .low
+                    .as_ref()
+                    .or(range.high.as_ref())
+                    .and_then(|bound| bound.unit.clone()),
+            ),
+            None => (None, None, None, None),
+        };
+        columns.dose_values.push(value);
+        columns.dose_lows.push(low);
+        columns.dose_highs.push(high);
+        columns.dose_units.push(unit.unwrap_or_default());
+
+        let timing = dosage.timing.as_ref();
+        let repeat = timing.and_then(|timing| timing.repeat.as_ref());
+        columns.frequencies.push(
+            repeat
+                .and_then(|repeat| repeat.frequency)
+                .map(|frequency| frequency.get()),
+        );
+        columns
+            .periods
+            .push(repeat.and_then(|repeat| repeat.period));
+        columns.period_units.push(
+            repeat
+                .and_then(|repeat| repeat.period_unit.clone())
+                .unwrap_or_default(),
+        );
+        columns.timing_codes.push(
+            timing
+                .and_then(|timing| timing.code.as_ref())
+                .map(first_code)
+                .unwrap_or_default(),
+        );
+    }
+    columns
+}
+
+/// MedicationStatement and MedicationAdministration point at their Encounter
+/// with `context`, which may also be an EpisodeOfCare we have nowhere to put.
+fn parse_context(
+    context: Option<&Reference>,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+    path: &str,
+) -> ConversionResult<String> {
+    let Some(reff) = context else {
+        return Ok(String::new());
+    };
+    let (ty, id) =
+        parse_secondary_reference(reff, &["Encounter", "EpisodeOfCare"], profile, stats, path)?;
+    if ty == "EpisodeOfCare" {
+        stats.record(&profile.name, path, FieldOutcome::Truncated);
+        return Ok(String::new());
+    }
+    Ok(id)
+}
+
+#[allow(dead_code)]
+pub fn convert_medication_request(
+    src: &MedicationRequest,
+    medications: &BundleMedications,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::MedicationRequest> {
+    medication_request_row(src, medications, profile, stats)
+        .map_err(|err| rejected(err, src.id.as_deref(), profile, stats))
+}
+
+fn medication_request_row(
+    src: &MedicationRequest,
+    medications: &BundleMedications,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::MedicationRequest> {
+    let Some(request_id) = src.id.clone() else {
+        return Err(ConversionError::new(
+            ConversionErrorReason::MissingId,
+            "MedicationRequest.id",
+        ));
+    };
+
+    let subject = parse_subject(
+        &src.subject,
+        &["Group"],
+        profile,
+        stats,
+        "MedicationRequest.subject",
+    )?;
+    let (_, encounter) = match &src.encounter {
+        Some(reff) => parse_secondary_reference(
+            reff,
+            &["Encounter"],
+            profile,
+            stats,
+            "MedicationRequest.encounter",
+        )?,
+        None => Default::default(),
+    };
+    let medication = parse_medication(
+        match &src.medication {
+            MedicationRequestMedication::CodeableConcept(concept) => {
+                MedicationRef::CodeableConcept(concept)
+            }
+            MedicationRequestMedication::Reference(reff) => MedicationRef::Reference(reff),
+        },
+        medications,
+        &src.contained,
+        profile,
+        stats,
+        "MedicationRequest.medication",
+    )?;
+    let (authored_on, authored_on_resolution) = parse_optional_datetime(
+        src.authored_on.as_ref(),
+        profile,
+        stats,
+        "MedicationRequest.authoredOn",
+    )?;
+    let (requester_type, requester) = match &src.requester {
+        Some(reff) => parse_secondary_reference(
+            reff,
+            &[
+                "Practitioner",
+                "PractitionerRole",
+                "Organization",
+                "Patient",
+                "RelatedPerson",
+                "Device",
+            ],
+            profile,
+            stats,
+            "MedicationRequest.requester",
+        )?,
+        None => Default::default(),
+    };
+    let dosages = parse_dosages(
+        &double_unwrap(&src.dosage_instruction),
+        profile,
+        stats,
+        "MedicationRequest.dosageInstruction",
+    );
+
+    Ok(schemav1::MedicationRequest {
+        id: request_id,
+        status: src.status.clone(),
+        intent: src.intent.clone(),
+        category: double_unwrap(&src.category)
+            .iter()
+            .map(first_code)
+            .collect(),
+        medication: medication.id,
+        medication_code: medication.code,
+        medication_description: medication.description,
+        medication_system: medication.system,
+        subject,
+        encounter,
+        authored_on,
+        authored_on_resolution,
+        requester_type,
+        requester,
+        dosages_sequence: dosages.sequences,
+        dosages_text: dosages.texts,
+        dosages_route: dosages.routes,
+        dosages_dose_value: dosages.dose_values,
+        dosages_dose_low: dosages.dose_lows,
+        dosages_dose_high: dosages.dose_highs,
+        dosages_dose_unit: dosages.dose_units,
+        dosages_frequency: dosages.frequencies,
+        dosages_period: dosages.periods,
+        dosages_period_unit: dosages.period_units,
+        dosages_timing_code: dosages.timing_codes,
+    })
+}
+
+#[allow(dead_code)]
+pub fn convert_medication_statement(
+    src: &MedicationStatement,
+    medications: &BundleMedications,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::MedicationStatement> {
+    medication_statement_row(src, medications, profile, stats)
+        .map_err(|err| rejected(err, src.id.as_deref(), profile, stats))
+}
+
+fn medication_statement_row(
+    src: &MedicationStatement,
+    medications: &BundleMedications,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::MedicationStatement> {
+    let Some(statement_id) = src.id.clone() else {
+        return Err(ConversionError::new(
+            ConversionErrorReason::MissingId,
+            "MedicationStatement.id",
+        ));
+    };
+
+    let subject = parse_subject(
+        &src.subject,
+        &["Group"],
+        profile,
+        stats,
+        "MedicationStatement.subject",
+    )?;
+    let encounter = parse_context(
+        src.context.as_ref(),
+        profile,
+        stats,
+        "MedicationStatement.context",
+    )?;
+    let medication = parse_medication(
+        match &src.medication {
+            MedicationStatementMedication::CodeableConcept(concept) => {
+                MedicationRef::CodeableConcept(concept)
+            }
+            MedicationStatementMedication::Reference(reff) => MedicationRef::Reference(reff),
+        },
+        medications,
+        &src.contained,
+        profile,
+        stats,
+        "MedicationStatement.medication",
+    )?;
+    let (effective_start, effective_start_resolution, effective_end, effective_end_resolution) =
+        match &src.effective {
+            Some(MedicationStatementEffective::DateTime(datetime)) => parse_effective(
+                Some(datetime),
+                None,
+                profile,
+                stats,
+                "MedicationStatement.effective",
+            )?,
+            Some(MedicationStatementEffective::Period(period)) => parse_effective(
+                None,
+                Some(period),
+                profile,
+                stats,
+                "MedicationStatement.effective",
+            )?,
+            None => parse_effective(None, None, profile, stats, "MedicationStatement.effective")?,
+        };
+    let (date_asserted, date_asserted_resolution) = parse_optional_datetime(
+        src.date_asserted.as_ref(),
+        profile,
+        stats,
+        "MedicationStatement.dateAsserted",
+    )?;
+    let dosages = parse_dosages(
+        &double_unwrap(&src.dosage),
+        profile,
+        stats,
+        "MedicationStatement.dosage",
+    );
+
+    Ok(schemav1::MedicationStatement {
+        id: statement_id,
+        status: src.status.clone(),
+        category: src.category.as_ref().map(first_code).unwrap_or_default(),
+        medication: medication.id,
+        medication_code: medication.code,
+        medication_description: medication.description,
+        medication_system: medication.system,
+        subject,
+        encounter,
+        effective_start,
+        effective_start_resolution,
+        effective_end,
+        effective_end_resolution,
+        date_asserted,
+        date_asserted_resolution,
+        dosages_sequence: dosages.sequences,
+        dosages_text: dosages.texts,
+        dosages_route: dosages.routes,
+        dosages_dose_value: dosages.dose_values,
+        dosages_dose_low: dosages.dose_lows,
+        dosages_dose_high: dosages.dose_highs,
+        dosages_dose_unit: dosages.dose_units,
+        dosages_frequency: dosages.frequencies,
+        dosages_period: dosages.periods,
+        dosages_period_unit: dosages.period_units,
+        dosages_timing_code: dosages.timing_codes,
+    })
+}
+
+#[allow(dead_code)]
+pub fn convert_medication_administration(
+    src: &MedicationAdministration,
+    medications: &BundleMedications,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::MedicationAdministration> {
+    medication_administration_row(src, medications, profile, stats)
+        .map_err(|err| rejected(err, src.id.as_deref(), profile, stats))
+}
+
+fn medication_administration_row(
+    src: &MedicationAdministration,
+    medications: &BundleMedications,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::MedicationAdministration> {
+    let Some(administration_id) = src.id.clone() else {
+        return Err(ConversionError::new(
+            ConversionErrorReason::MissingId,
+            "MedicationAdministration.id",
+        ));
+    };
+
+    let subject = parse_subject(
+        &src.subject,
+        &["Group"],
+        profile,
+        stats,
+        "MedicationAdministration.subject",
+    )?;
+    let encounter = parse_context(
+        src.context.as_ref(),
+        profile,
+        stats,
+        "MedicationAdministration.context",
+    )?;
+    let medication = parse_medication(
+        match &src.medication {
+            MedicationAdministrationMedication::CodeableConcept(concept) => {
+                MedicationRef::CodeableConcept(concept)
+            }
+            MedicationAdministrationMedication::Reference(reff) => MedicationRef::Reference(reff),
+        },
+        medications,
+        &src.contained,
+        profile,
+        stats,
+        "MedicationAdministration.medication",
+    )?;
+    let (effective_start, effective_start_resolution, effective_end, effective_end_resolution) =
+        match &src.effective {
+            MedicationAdministrationEffective::DateTime(datetime) => parse_effective(
+                Some(datetime),
+                None,
+                profile,
+                stats,
+                "MedicationAdministration.effective",
+            )?,
+            MedicationAdministrationEffective::Period(period) => parse_effective(
+                None,
+                Some(period),
+                profile,
+                stats,
+                "MedicationAdministration.effective",
+            )?,
+        };
+    let (_, request) = match &src.request {
+        Some(reff) => parse_secondary_reference(
+            reff,
+            &["MedicationRequest"],
+            profile,
+            stats,
+            "MedicationAdministration.request",
+        )?,
+        None => Default::default(),
+    };
+
+    let dosage = src.dosage.as_ref();
+    let dose = dosage.and_then(|dosage| dosage.dose.as_ref());
+    Ok(schemav1::MedicationAdministration {
+        id: administration_id,
+        status: src.status.clone(),
+        category: src.category.as_ref().map(first_code).unwrap_or_default(),
+        medication: medication.id,
+        medication_code: medication.code,
+        medication_description: medication.description,
+        medication_system: medication.system,
+        subject,
+        encounter,
+        effective_start,
+        effective_start_resolution,
+        effective_end,
+        effective_end_resolution,
+        request,
+        dosage_text: dosage
+            .and_then(|dosage| dosage.text.clone())
+            .unwrap_or_default(),
+        route: dosage
+            .and_then(|dosage| dosage.route.as_ref())
+            .map(first_code)
+            .unwrap_or_default(),
+        dose_value: dose.and_then(|dose| dose.value),
+        dose_unit: dose.and_then(|dose| dose.unit.clone()).unwrap_or_default(),
+    })
+}
@@ -1,4 +1,27 @@
+mod allergy;
+mod clinical_time;
+mod condition;
+mod diagnostic_report;
+mod error;
+mod extensions;
 mod fhir_r4b_schemav1;
+mod immunization;
+mod location;
+mod medication;
+mod observation;
+mod procedure;
+mod provider;
 mod util;
 
+pub use allergy::*;
+pub use condition::*;
+pub use diagnostic_report::*;
+pub use error::*;
+pub use extensions::*;
 pub use fhir_r4b_schemav1::*;
+pub use immunization::*;
+pub use location::*;
+pub use medication::*;
+pub use observation::*;
+pub use procedure::*;
+pub use provider::*;
@@ -0,0 +1,500 @@
+//! Observations, with every value[x] we have columns for. Components share
+//! the value columns of the Observation itself.
+
+use fhir_model::DateTime;
+use fhir_model::r4b::codes::ObservationStatus;
+use fhir_model::r4b::resources::{
+    Observation, ObservationComponent, ObservationComponentValue, ObservationEffective,
+    ObservationValue,
+};
+use fhir_model::r4b::types::{CodeableConcept, Period, Quantity, Range, Ratio};
+use time::OffsetDateTime;
+
+use super::error::{ConversionError, ConversionErrorReason};
+use super::fhir_r4b_schemav1::{
+    ConversionResult, PeriodColumns, note_absent, parse_datetime, parse_optional_datetime,
+    parse_secondary_reference, parse_subject, rejected,
+};
+use super::util::{coded, double_unwrap, first_code};
+use crate::profile::FacilityProfile;
+use crate::schemav1::{self, FieldOutcome, TimeResolution, ValueType};
+use crate::stats::ConversionStats;
+
+#[allow(dead_code)]
+pub fn convert_observation(
+    src: &Observation,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::Observation> {
+    observation_row(src, profile, stats)
+        .map_err(|err| rejected(err, src.id.as_deref(), profile, stats))
+}
+
+fn observation_row(
+    src: &Observation,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::Observation> {
+    let Some(observation_id) = src.id.clone() else {
+        return Err(ConversionError::new(
+            ConversionErrorReason::MissingId,
+            "Observation.id",
+        ));
+    };
+
+    let status = match src.status {
+        ObservationStatus::Registered => schemav1::ObservationStatus::Registered,
+        ObservationStatus::Preliminary => schemav1::ObservationStatus::Preliminary,
+        ObservationStatus::Final => schemav1::ObservationStatus::Final,
+        ObservationStatus::Amended => schemav1::ObservationStatus::Amended,
+        ObservationStatus::Corrected => schemav1::ObservationStatus::Corrected,
+        ObservationStatus::Cancelled => schemav1::ObservationStatus::Cancelled,
+        ObservationStatus::EnteredInError => schemav1::ObservationStatus::EnteredInError,
+        ObservationStatus::Unknown => schemav1::ObservationStatus::Unknown,
+    };
+
+    let subject = match &src.subject {
+        Some(reff) => parse_subject(
+            reff,
+            &["Group", "Device", "Location"],
+            profile,
+            stats,
+            "Observation.subject",
+        )?,
+        None => {
+            note_absent(profile, stats, "Observation.subject", FieldOutcome::Missing)?;
+            String::new()
+        }
+    };
+    let (_, encounter) = match &src.encounter {
+        Some(reff) => parse_secondary_reference(
+            reff,
+            &["Encounter"],
+            profile,
+            stats,
+            "Observation.encounter",
+        )?,
+        None => Default::default(),
+    };
+
+    let (effective_start, effective_start_resolution, effective_end, effective_end_resolution) =
+        match &src.effective {
+            Some(ObservationEffective::DateTime(datetime)) => {
+                let (start, resolution) =
+                    parse_datetime(datetime, profile, stats, "Observation.effectiveDateTime")?;
+                (Some(start), resolution, None, TimeResolution::Unknown)
+            }
+            Some(ObservationEffective::Instant(instant)) => {
+                let (start, resolution) = parse_datetime(
+                    &DateTime::DateTime(instant.clone()),
+                    profile,
+                    stats,
+                    "Observation.effectiveInstant",
+                )?;
+                (Some(start), resolution, None, TimeResolution::Unknown)
+            }
+            Some(ObservationEffective::Period(period)) => {
+                let (start, start_resolution) = parse_optional_datetime(
+                    period.start.as_ref(),
+                    profile,
+                    stats,
+                    "Observation.effectivePeriod.start",
+                )?;
+                let (end, end_resolution) = parse_optional_datetime(
+                    period.end.as_ref(),
+                    profile,
+                    stats,
+                    "Observation.effectivePeriod.end",
+                )?;
+                (start, start_resolution, end, end_resolution)
+            }
+            Some(ObservationEffective::Timing(_)) => {
+                stats.record(
+                    &profile.name,
+                    "Observation.effectiveTiming",
+                    FieldOutcome::Truncated,
+                );
+                (None, TimeResolution::Unknown, None, TimeResolution::Unknown)
+            }
+            None => {
+                note_absent(
+                    profile,
+                    stats,
+                    "Observation.effective",
+                    FieldOutcome::Missing,
+                )?;
+                (None, TimeResolution::Unknown, None, TimeResolution::Unknown)
+            }
+        };
+
+    let issued = match &src.issued {
+        Some(instant) => Some(
+            parse_datetime(
+                &DateTime::DateTime(instant.clone()),
+                profile,
+                stats,
+                "Observation.issued",
+            )?
+            .0,
+        ),
+        None => None,
+    };
+
+    let value = parse_value(
+        src.value.as_ref().map(ValueRef::from),
+        src.data_absent_reason.as_ref(),
+        profile,
+        stats,
+        "Observation.value",
+    )?;
+    let components = parse_components(&double_unwrap(&src.component), profile, stats)?;
+    let (code, code_description, code_system) = coded(&src.code);
+
+    Ok(schemav1::Observation {
+        id: observation_id,
+        status,
+        code,
+        code_description,
+        code_system,
+        category: double_unwrap(&src.category)
+            .iter()
+            .map(first_code)
+            .collect(),
+        subject,
+        encounter,
+        effective_start,
+        effective_start_resolution,
+        effective_end,
+        effective_end_resolution,
+        issued,
+        value_type: value.value_type,
+        value_quantity: value.quantity,
+        value_comparator: value.comparator,
+        value_unit: value.unit,
+        value_code: value.code,
+        value_code_description: value.code_description,
+        value_code_system: value.code_system,
+        value_string: value.string,
+        value_boolean: value.boolean,
+        value_integer: value.integer,
+        value_low: value.low,
+        value_high: value.high,
+        value_numerator: value.numerator,
+        value_denominator: value.denominator,
+        value_denominator_unit: value.denominator_unit,
+        value_period_start: value.period_start,
+        value_period_start_resolution: value.period_start_resolution,
+        value_period_end: value.period_end,
+        value_period_end_resolution: value.period_end_resolution,
+        data_absent_reason: value.data_absent_reason,
+        components_code: components.codes,
+        components_code_description: components.code_descriptions,
+        components_code_system: components.code_systems,
+        components_value_type: components.values.value_types,
+        components_value_quantity: components.values.quantities,
+        components_value_comparator: components.values.comparators,
+        components_value_unit: components.values.units,
+        components_value_code: components.values.codes,
+        components_value_code_description: components.values.code_descriptions,
+        components_value_code_system: components.values.code_systems,
+        components_value_string: components.values.strings,
+        components_value_boolean: components.values.booleans,
+        components_value_integer: components.values.integers,
+        components_value_low: components.values.lows,
+        components_value_high: components.values.highs,
+        components_value_numerator: components.values.numerators,
+        components_value_denominator: components.values.denominators,
+        components_value_denominator_unit: components.values.denominator_units,
+        components_value_period_start: components.values.periods.starts,
+        components_value_period_start_resolution: components.values.periods.start_resolutions,
+        components_value_period_end: components.values.periods.ends,
+        components_value_period_end_resolution: components.values.periods.end_resolutions,
+        components_data_absent_reason: components.values.data_absent_reasons,
+    })
+}
+
+/// value[x] of an Observation or of one of its components, which fhir_model
+/// keeps in two enums.
+enum ValueRef<'a> {
+    Quantity(&'a Quantity),
+    CodeableConcept(&'a CodeableConcept),
+    String(&'a str),
+    Boolean(bool),
+    Integer(i32),
+    Range(&'a Range),
+    Ratio(&'a Ratio),
+    Period(&'a Period),
+    /// SampledData, Time and DateTime.
+    Other,
+}
+
+impl<'a> From<&'a ObservationValue> for ValueRef<'a> {
+    fn from(value: &'a ObservationValue) -> Self {
+        match value {
+            ObservationValue::Quantity(quantity) => ValueRef::Quantity(quantity),
+            ObservationValue::CodeableConcept(concept) => ValueRef::CodeableConcept(concept),
+            ObservationValue::String(string) => ValueRef::String(string),
+            ObservationValue::Boolean(boolean) => ValueRef::Boolean(*boolean),
+            ObservationValue::Integer(integer) => ValueRef::Integer(*integer),
+            ObservationValue::Range(range) => ValueRef::Range(range),
+            ObservationValue::Ratio(ratio) => ValueRef::Ratio(ratio),
+            ObservationValue::Period(period) => ValueRef::Period(period),
+            ObservationValue::SampledData(_)
+            | ObservationValue::Time(_)
+            | ObservationValue::DateTime(_) => ValueRef::Other,
+        }
+    }
+}
+
+impl<'a> From<&'a ObservationComponentValue> for ValueRef<'a> {
+    fn from(value: &'a ObservationComponentValue) -> Self {
+        match value {
+            ObservationComponentValue::Quantity(quantity) => ValueRef::Quantity(quantity),
+            ObservationComponentValue::CodeableConcept(concept) => {
+                ValueRef::CodeableConcept(concept)
+            }
+            ObservationComponentValue::String(string) => ValueRef::String(string),
+            ObservationComponentValue::Boolean(boolean) => ValueRef::Boolean(*boolean),
+            ObservationComponentValue::Integer(integer) => ValueRef::Integer(*integer),
+            ObservationComponentValue::Range(range) => ValueRef::Range(range),
+            ObservationComponentValue::Ratio(ratio) => ValueRef::Ratio(ratio),
+            ObservationComponentValue::Period(period) => ValueRef::Period(period),
+            ObservationComponentValue::SampledData(_)
+            | ObservationComponentValue::Time(_)
+            | ObservationComponentValue::DateTime(_) => ValueRef::Other,
+        }
+    }
+}
+
+/// One value's worth of the `value_*` columns. Only the ones for its
+/// `value_type` are filled in.
+struct Value {
+    value_type: ValueType,
+    quantity: Option<f64>,
+    comparator: String,
+    unit: String,
+    code: String,
+    code_description: String,
+    code_system: String,
+    string: String,
+    boolean: Option<bool>,
+    integer: Option<i32>,
+    low: Option<f64>,
+    high: Option<f64>,
+    numerator: Option<f64>,
+    denominator: Option<f64>,
+    denominator_unit: String,
+    period_start: Option<OffsetDateTime>,
+    period_start_resolution: TimeResolution,
+    period_end: Option<OffsetDateTime>,
+    period_end_resolution: TimeResolution,
+    data_absent_reason: String,
+}
+
+impl Value {
+    fn empty(value_type: ValueType) -> Self {
+        Value {
+            value_type,
+            quantity: None,
+            comparator: String::new(),
+            unit: String::new(),
+            code: String::new(),
+            code_description: String::new(),
+            code_system: String::new(),
+            string: String::new(),
+            boolean: None,
+            integer: None,
+            low: None,
+            high: None,
+            numerator: None,
+            denominator: None,
+            denominator_unit: String::new(),
+            period_start: None,
+            period_start_resolution: TimeResolution::Unknown,
+            period_end: None,
+            period_end_resolution: TimeResolution::Unknown,
+            data_absent_reason: String::new(),
+        }
+    }
+}
+
+/// The unit of a quantity, its code if it has no human readable one.
+fn unit(quantity: &Quantity) -> String {
+    quantity
+        .unit
+        .clone()
+        .or_else(|| quantity.code.clone())
+        .unwrap_or_default()
+}
+
+/// `path` is the path of the value itself, e.g. `Observation.value`. Stats
+/// for a Period are kept under its choice name, e.g.
+/// `Observation.valuePeriod.start`.
+fn parse_value(
+    value: Option<ValueRef>,
+    data_absent_reason: Option<&CodeableConcept>,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+    path: &str,
+) -> ConversionResult<Value> {
+    let mut parsed = match value {
+        None => Value::empty(ValueType::None),
+        Some(ValueRef::Quantity(quantity)) => Value {
+            quantity: quantity.value,
+            comparator: quantity
+                .comparator
+                .map(|comparator| comparator.as_ref().to_string())
+                .unwrap_or_default(),
+            unit: unit(quantity),
+            ..Value::empty(ValueType::Quantity)
+        },
+        Some(ValueRef::CodeableConcept(concept)) => {
+            let (code, code_description, code_system) = coded(concept);
+            Value {
+                code,
+                code_description,
+                code_system,
+                ..Value::empty(ValueType::CodeableConcept)
+            }
+        }
+        Some(ValueRef::String(string)) => Value {
+            string: string.to_string(),
+            ..Value::empty(ValueType::String)
+        },
+        Some(ValueRef::Boolean(boolean)) => Value {
+            boolean: Some(boolean),
+            ..Value::empty(ValueType::Boolean)
+        },
+        Some(ValueRef::Integer(integer)) => Value {
+            integer: Some(integer),
+            ..Value::empty(ValueType::Integer)
+        },
+        Some(ValueRef::Range(range)) => Value {
+            low: range.low.as_ref().and_then(|low| low.value),
+            high: range.high.as_ref().and_then(|high| high.value),
+            unit: range
+                .low
+                .as_ref()
+                .or(range.high.as_ref())
+                .map(unit)
+                .unwrap_or_default(),
+            ..Value::empty(ValueType::Range)
+        },
+        Some(ValueRef::Ratio(ratio)) => Value {
+            numerator: ratio.numerator.as_ref().and_then(|q| q.value),
+            unit: ratio.numerator.as_ref().map(unit).unwrap_or_default(),
+            denominator: ratio.denominator.as_ref().and_then(|q| q.value),
+            denominator_unit: ratio.denominator.as_ref().map(unit).unwrap_or_default(),
+            ..Value::empty(ValueType::Ratio)
+        },
+        Some(ValueRef::Period(period)) => {
+            let (period_start, period_start_resolution) = parse_optional_datetime(
+                period.start.as_ref(),
+                profile,
+                stats,
+                &format!("{}Period.start", path),
+            )?;
+            let (period_end, period_end_resolution) = parse_optional_datetime(
+                period.end.as_ref(),
+                profile,
+                stats,
+                &format!("{}Period.end", path),
+            )?;
+            Value {
+                period_start,
+                period_start_resolution,
+                period_end,
+                period_end_resolution,
+                ..Value::empty(ValueType::Period)
+            }
+        }
+        Some(ValueRef::Other) => {
+            stats.record(&profile.name, path, FieldOutcome::Truncated);
+            Value::empty(ValueType::Other)
+        }
+    };
+    parsed.data_absent_reason = data_absent_reason.map(first_code).unwrap_or_default();
+    Ok(parsed)
+}
+
+/// One Vec per `value_*` column inside the `components` Nested column.
+#[derive(Default)]
+struct ValueColumns {
+    value_types: Vec<ValueType>,
+    quantities: Vec<Option<f64>>,
+    comparators: Vec<String>,
+    units: Vec<String>,
+    codes: Vec<String>,
+    code_descriptions: Vec<String>,
+    code_systems: Vec<String>,
+    strings: Vec<String>,
+    booleans: Vec<Option<bool>>,
+    integers: Vec<Option<i32>>,
+    lows: Vec<Option<f64>>,
+    highs: Vec<Option<f64>>,
+    numerators: Vec<Option<f64>>,
+    denominators: Vec<Option<f64>>,
+    denominator_units: Vec<String>,
+    periods: PeriodColumns,
+    data_absent_reasons: Vec<String>,
+}
+
+impl ValueColumns {
+    fn push(&mut self, value: Value) {
+        self.value_types.push(value.value_type);
+        self.quantities.push(value.quantity);
+        self.comparators.push(value.comparator);
+        self.units.push(value.unit);
+        self.codes.push(value.code);
+        self.code_descriptions.push(value.code_description);
+        self.code_systems.push(value.code_system);
+        self.strings.push(value.string);
+        self.booleans.push(value.boolean);
+        self.integers.push(value.integer);
+        self.lows.push(value.low);
+        self.highs.push(value.high);
+        self.numerators.push(value.numerator);
+        self.denominators.push(value.denominator);
+        self.denominator_units.push(value.denominator_unit);
+        self.periods.starts.push(value.period_start);
+        self.periods
+            .start_resolutions
+            .push(value.period_start_resolution);
+        self.periods.ends.push(value.period_end);
+        self.periods
+            .end_resolutions
+            .push(value.period_end_resolution);
+        self.data_absent_reasons.push(value.data_absent_reason);
+    }
+}
+
+/// One Vec per column of the `components` Nested column.
+#[derive(Default)]
+struct ComponentColumns {
+    codes: Vec<String>,
+    code_descriptions: Vec<String>,
+    code_systems: Vec<String>,
+    values: ValueColumns,
+}
+
+fn parse_components(
+    components: &[ObservationComponent],
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<ComponentColumns> {
+    let mut columns = ComponentColumns::default();
+    for component in components {
+        let (code, code_description, code_system) = coded(&component.code);
+        columns.codes.push(code);
+        columns.code_descriptions.push(code_description);
+        columns.code_systems.push(code_system);
+        columns.values.push(parse_value(
+            component.value.as_ref().map(ValueRef::from),
+            component.data_absent_reason.as_ref(),
+            profile,
+            stats,
+            "Observation.component.value",
+        )?);
+    }
+    Ok(columns)
+}
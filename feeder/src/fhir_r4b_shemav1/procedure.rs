@@ -0,0 +1,115 @@
+//! Procedures, with performed[x] kept like Condition.onset[x].
+
+use fhir_model::r4b::codes::EventStatus;
+use fhir_model::r4b::resources::Procedure;
+
+use super::clinical_time::{ClinicalTimeRef, parse_clinical_time};
+use super::error::{ConversionError, ConversionErrorReason};
+use super::fhir_r4b_schemav1::{
+    ConversionResult, note_absent, parse_concepts, parse_secondary_reference, parse_subject,
+    rejected,
+};
+use super::util::{coded, double_unwrap, first_code};
+use crate::profile::FacilityProfile;
+use crate::schemav1::{self, ClinicalTimeType, FieldOutcome};
+use crate::stats::ConversionStats;
+
+#[allow(dead_code)]
+pub fn convert_procedure(
+    src: &Procedure,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::Procedure> {
+    procedure_row(src, profile, stats)
+        .map_err(|err| rejected(err, src.id.as_deref(), profile, stats))
+}
+
+fn procedure_row(
+    src: &Procedure,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::Procedure> {
+    let Some(procedure_id) = src.id.clone() else {
+        return Err(ConversionError::new(
+            ConversionErrorReason::MissingId,
+            "Procedure.id",
+        ));
+    };
+
+    let status = match src.status {
+        EventStatus::Preparation => schemav1::ProcedureStatus::Preparation,
+        EventStatus::InProgress => schemav1::ProcedureStatus::InProgress,
+        EventStatus::NotDone => schemav1::ProcedureStatus::NotDone,
+        EventStatus::OnHold => schemav1::ProcedureStatus::OnHold,
+        EventStatus::Stopped => schemav1::ProcedureStatus::Stopped,
+        EventStatus::Completed => schemav1::ProcedureStatus::Completed,
+        EventStatus::EnteredInError => schemav1::ProcedureStatus::EnteredInError,
+        EventStatus::Unknown => schemav1::ProcedureStatus::Unknown,
+    };
+
+    let subject = parse_subject(
+        &src.subject,
+        &["Group"],
+        profile,
+        stats,
+        "Procedure.subject",
+    )?;
+    let (_, encounter) = match &src.encounter {
+        Some(reff) => {
+            parse_secondary_reference(reff, &["Encounter"], profile, stats, "Procedure.encounter")?
+        }
+        None => Default::default(),
+    };
+
+    let (code, code_description, code_system) = match &src.code {
+        Some(concept) => coded(concept),
+        None => {
+            note_absent(profile, stats, "Procedure.code", FieldOutcome::Missing)?;
+            Default::default()
+        }
+    };
+
+    let performed = parse_clinical_time(
+        src.performed.as_ref().map(ClinicalTimeRef::from),
+        profile,
+        stats,
+        "Procedure.performed",
+    )?;
+    if performed.time_type == ClinicalTimeType::None {
+        note_absent(profile, stats, "Procedure.performed", FieldOutcome::Missing)?;
+    }
+
+    let reasons = parse_concepts(&double_unwrap(&src.reason_code));
+
+    Ok(schemav1::Procedure {
+        id: procedure_id,
+        status,
+        status_reason: src
+            .status_reason
+            .as_ref()
+            .map(first_code)
+            .unwrap_or_default(),
+        category: src.category.as_ref().map(first_code).unwrap_or_default(),
+        code,
+        code_description,
+        code_system,
+        subject,
+        encounter,
+        performed_type: performed.time_type,
+        performed_start: performed.start,
+        performed_start_resolution: performed.start_resolution,
+        performed_end: performed.end,
+        performed_end_resolution: performed.end_resolution,
+        performed_age_low: performed.age_low,
+        performed_age_high: performed.age_high,
+        performed_age_unit: performed.age_unit,
+        performed_text: performed.text,
+        body_site: double_unwrap(&src.body_site)
+            .iter()
+            .map(first_code)
+            .collect(),
+        reasons_code: reasons.codes,
+        reasons_description: reasons.descriptions,
+        reasons_system: reasons.systems,
+    })
+}
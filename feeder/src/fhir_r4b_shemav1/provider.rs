@@ -0,0 +1,288 @@
+//! Practitioners, PractitionerRoles and Organizations, the dimensions behind
+//! the references in encounters, reports and patients.
+
+use fhir_model::r4b::codes::AdministrativeGender;
+use fhir_model::r4b::resources::{Organization, Practitioner, PractitionerRole};
+use fhir_model::r4b::types::Identifier;
+
+use super::error::{ConversionError, ConversionErrorReason};
+use super::fhir_r4b_schemav1::{
+    ConversionResult, display_name, note_absent, parse_addresses, parse_concepts,
+    parse_identifiers, parse_names, parse_optional_datetime, parse_secondary_reference, rejected,
+};
+use super::util::{double_unwrap, first_code, join_name};
+use crate::profile::FacilityProfile;
+use crate::schemav1::{self, FieldOutcome};
+use crate::stats::ConversionStats;
+
+pub const NPI: &str = "http://hl7.org/fhir/sid/us-npi";
+
+/// Value of the first NPI among `identifiers`.
+fn npi(identifiers: &[Identifier]) -> String {
+    identifiers
+        .iter()
+        .find(|identifier| identifier.system.as_deref() == Some(NPI))
+        .and_then(|identifier| identifier.value.clone())
+        .unwrap_or_default()
+}
+
+#[allow(dead_code)]
+pub fn convert_practitioner(
+    src: &Practitioner,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::Practitioner> {
+    practitioner_row(src, profile, stats)
+        .map_err(|err| rejected(err, src.id.as_deref(), profile, stats))
+}
+
+fn practitioner_row(
+    src: &Practitioner,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::Practitioner> {
+    let Some(practitioner_id) = src.id.clone() else {
+        return Err(ConversionError::new(
+            ConversionErrorReason::MissingId,
+            "Practitioner.id",
+        ));
+    };
+
+    let names = double_unwrap(&src.name);
+    if names.is_empty() {
+        note_absent(profile, stats, "Practitioner.name", FieldOutcome::Missing)?;
+    }
+    let display_name = display_name(&names);
+    let names = parse_names(&names, profile, stats, "Practitioner.name")?;
+
+    let identifiers = double_unwrap(&src.identifier);
+    let npi = npi(&identifiers);
+    let identifiers = parse_identifiers(&identifiers);
+    let addresses = parse_addresses(
+        double_unwrap(&src.address),
+        profile,
+        stats,
+        "Practitioner.address",
+    )?;
+
+    let gender = match src.gender {
+        Some(AdministrativeGender::Male) => schemav1::Gender::Male,
+        Some(AdministrativeGender::Female) => schemav1::Gender::Female,
+        Some(AdministrativeGender::Other) => schemav1::Gender::Other,
+        Some(AdministrativeGender::Unknown) | None => schemav1::Gender::Unknown,
+    };
+
+    Ok(schemav1::Practitioner {
+        id: practitioner_id,
+        active: src.active,
+        npi,
+        name_given: display_name
+            .map(|name| join_name(&name.given))
+            .unwrap_or_default(),
+        name_family: display_name
+            .and_then(|name| name.family.clone())
+            .unwrap_or_default(),
+        names_use: names.uses,
+        names_family: names.families,
+        names_given: names.givens,
+        names_prefix: names.prefixes,
+        names_suffix: names.suffixes,
+        names_period_start: names.periods.starts,
+        names_period_start_resolution: names.periods.start_resolutions,
+        names_period_end: names.periods.ends,
+        names_period_end_resolution: names.periods.end_resolutions,
+        gender,
+        qualifications: double_unwrap(&src.qualification)
+            .iter()
+            .map(|qualification| first_code(&qualification.code))
+            .collect(),
+        identifiers_use: identifiers.uses,
+        identifiers_type: identifiers.types,
+        identifiers_system: identifiers.systems,
+        identifiers_value: identifiers.values,
+        addresses_use: addresses.uses,
+        addresses_type: addresses.types,
+        addresses_city: addresses.cities,
+        addresses_line: addresses.lines,
+        addresses_district: addresses.districts,
+        addresses_state: addresses.states,
+        addresses_postal_code: addresses.postal_codes,
+        addresses_country: addresses.countries,
+        addresses_text: addresses.texts,
+        addresses_period_start: addresses.periods.starts,
+        addresses_period_start_resolution: addresses.periods.start_resolutions,
+        addresses_period_end: addresses.periods.ends,
+        addresses_period_end_resolution: addresses.periods.end_resolutions,
+    })
+}
+
+#[allow(dead_code)]
+pub fn convert_practitioner_role(
+    src: &PractitionerRole,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::PractitionerRole> {
+    practitioner_role_row(src, profile, stats)
+        .map_err(|err| rejected(err, src.id.as_deref(), profile, stats))
+}
+
+fn practitioner_role_row(
+    src: &PractitionerRole,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::PractitionerRole> {
+    let Some(role_id) = src.id.clone() else {
+        return Err(ConversionError::new(
+            ConversionErrorReason::MissingId,
+            "PractitionerRole.id",
+        ));
+    };
+
+    let (_, practitioner) = match &src.practitioner {
+        Some(reff) => parse_secondary_reference(
+            reff,
+            &["Practitioner"],
+            profile,
+            stats,
+            "PractitionerRole.practitioner",
+        )?,
+        None => Default::default(),
+    };
+    let (_, organization) = match &src.organization {
+        Some(reff) => parse_secondary_reference(
+            reff,
+            &["Organization"],
+            profile,
+            stats,
+            "PractitionerRole.organization",
+        )?,
+        None => Default::default(),
+    };
+
+    let (period_start, period_start_resolution) = parse_optional_datetime(
+        src.period.as_ref().and_then(|period| period.start.as_ref()),
+        profile,
+        stats,
+        "PractitionerRole.period.start",
+    )?;
+    let (period_end, period_end_resolution) = parse_optional_datetime(
+        src.period.as_ref().and_then(|period| period.end.as_ref()),
+        profile,
+        stats,
+        "PractitionerRole.period.end",
+    )?;
+
+    let specialties = parse_concepts(&double_unwrap(&src.specialty));
+    let mut locations = vec![];
+    for reff in double_unwrap(&src.location) {
+        let (_, location) = parse_secondary_reference(
+            &reff,
+            &["Location"],
+            profile,
+            stats,
+            "PractitionerRole.location",
+        )?;
+        if !location.is_empty() {
+            locations.push(location);
+        }
+    }
+    let identifiers = parse_identifiers(&double_unwrap(&src.identifier));
+
+    Ok(schemav1::PractitionerRole {
+        id: role_id,
+        active: src.active,
+        practitioner,
+        organization,
+        period_start,
+        period_start_resolution,
+        period_end,
+        period_end_resolution,
+        roles: double_unwrap(&src.code).iter().map(first_code).collect(),
+        specialties_code: specialties.codes,
+        specialties_description: specialties.descriptions,
+        specialties_system: specialties.systems,
+        locations,
+        identifiers_use: identifiers.uses,
+        identifiers_type: identifiers.types,
+        identifiers_system: identifiers.systems,
+        identifiers_value: identifiers.values,
+    })
+}
+
+#[allow(dead_code)]
+pub fn convert_organization(
+    src: &Organization,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::Organization> {
+    organization_row(src, profile, stats)
+        .map_err(|err| rejected(err, src.id.as_deref(), profile, stats))
+}
+
+fn organization_row(
+    src: &Organization,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ConversionResult<schemav1::Organization> {
+    let Some(organization_id) = src.id.clone() else {
+        return Err(ConversionError::new(
+            ConversionErrorReason::MissingId,
+            "Organization.id",
+        ));
+    };
+
+    let name = match &src.name {
+        Some(name) => name.clone(),
+        None => {
+            note_absent(profile, stats, "Organization.name", FieldOutcome::Missing)?;
+            String::new()
+        }
+    };
+    let (_, part_of) = match &src.part_of {
+        Some(reff) => parse_secondary_reference(
+            reff,
+            &["Organization"],
+            profile,
+            stats,
+            "Organization.partOf",
+        )?,
+        None => Default::default(),
+    };
+
+    let identifiers = double_unwrap(&src.identifier);
+    let npi = npi(&identifiers);
+    let identifiers = parse_identifiers(&identifiers);
+    let addresses = parse_addresses(
+        double_unwrap(&src.address),
+        profile,
+        stats,
+        "Organization.address",
+    )?;
+
+    Ok(schemav1::Organization {
+        id: organization_id,
+        active: src.active,
+        npi,
+        name,
+        aliases: double_unwrap(&src.alias),
+        types: double_unwrap(&src.r#type).iter().map(first_code).collect(),
+        part_of,
+        identifiers_use: identifiers.uses,
+        identifiers_type: identifiers.types,
+        identifiers_system: identifiers.systems,
+        identifiers_value: identifiers.values,
+        addresses_use: addresses.uses,
+        addresses_type: addresses.types,
+        addresses_city: addresses.cities,
+        addresses_line: addresses.lines,
+        addresses_district: addresses.districts,
+        addresses_state: addresses.states,
+        addresses_postal_code: addresses.postal_codes,
+        addresses_country: addresses.countries,
+        addresses_text: addresses.texts,
+        addresses_period_start: addresses.periods.starts,
+        addresses_period_start_resolution: addresses.periods.start_resolutions,
+        addresses_period_end: addresses.periods.ends,
+        addresses_period_end_resolution: addresses.periods.end_resolutions,
+    })
+}
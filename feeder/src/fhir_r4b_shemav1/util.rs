@@ -1,3 +1,4 @@
+use fhir_model::r4b::types::{CodeableConcept, Coding};
 
 pub fn join_name<T>(name: &T) -> String
 where
@@ -20,6 +21,65 @@ where
     it.iter().filter_map(|it| it.clone()).collect()
 }
 
+/// The first code in a CodeableConcept, empty if none of its codings has one.
+pub fn first_code(concept: &CodeableConcept) -> String {
+    concept
+        .coding
+        .iter()
+        .flatten()
+        .find_map(|coding| coding.code.clone())
+        .unwrap_or_default()
+}
+
+/// The first coding in a CodeableConcept which has a code.
+pub fn first_coding(concept: &CodeableConcept) -> Option<&Coding> {
+    concept
+        .coding
+        .iter()
+        .flatten()
+        .find(|coding| coding.code.is_some())
+}
+
+/// Code, description and system of the first coding with a code. The
+/// description falls back to the concept's text when the coding has no
+/// display.
+pub fn coded(concept: &CodeableConcept) -> (String, String, String) {
+    let coding = first_coding(concept);
+    (
+        coding
+            .and_then(|coding| coding.code.clone())
+            .unwrap_or_default(),
+        coding
+            .and_then(|coding| coding.display.clone())
+            .or_else(|| concept.text.clone())
+            .unwrap_or_default(),
+        coding
+            .and_then(|coding| coding.system.clone())
+            .unwrap_or_default(),
+    )
+}
+
+/// Like [`coded`], preferring a coding from `system`, e.g. RxNorm for
+/// medications.
+pub fn coded_preferring(concept: &CodeableConcept, system: &str) -> (String, String, String) {
+    let preferred = concept
+        .coding
+        .iter()
+        .flatten()
+        .find(|coding| coding.system.as_deref() == Some(system) && coding.code.is_some());
+    match preferred {
+        Some(coding) => (
+            coding.code.clone().unwrap_or_default(),
+            coding
+                .display
+                .clone()
+                .or_else(|| concept.text.clone())
+                .unwrap_or_default(),
+            system.to_string(),
+        ),
+        None => coded(concept),
+    }
+}
 
 #[cfg(test)]
 mod tests {
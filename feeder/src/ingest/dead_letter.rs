@@ -0,0 +1,112 @@
+//! Republishing messages we gave up on, so they stop holding back their
+//! partition's commits and anyone who cares can pick them up.
+
+use std::time::Duration;
+
+use rdkafka::{
+    Message,
+    message::{Header, Headers, OwnedHeaders, OwnedMessage},
+    producer::{FutureProducer, FutureRecord},
+};
+
+use super::IngestResult;
+
+/// Header holding why a message was dead-lettered, a conversion error or
+/// rejection reason code, or `clickhouse` and the like for infrastructure.
+pub const DEAD_LETTER_REASON_HEADER: &str = "dead_letter_reason";
+/// Header holding how many times we tried to process the message.
+pub const DEAD_LETTER_ATTEMPTS_HEADER: &str = "dead_letter_attempts";
+
+/// The message's own headers with the dead letter ones added. Stale dead
+/// letter headers, from a message that was replayed out of the dead-letter
+/// topic, are replaced.
+pub fn dead_letter_headers(msg: &OwnedMessage, reason: &str, attempts: u32) -> OwnedHeaders {
+    let mut headers = OwnedHeaders::new();
+    for header in msg.headers().into_iter().flat_map(|headers| headers.iter()) {
+        if header.key == DEAD_LETTER_REASON_HEADER || header.key == DEAD_LETTER_ATTEMPTS_HEADER {
+            continue;
+        }
+        headers = headers.insert(header);
+    }
+    headers
+        .insert(Header {
+            key: DEAD_LETTER_REASON_HEADER,
+            value: Some(reason),
+        })
+        .insert(Header {
+            key: DEAD_LETTER_ATTEMPTS_HEADER,
+            value: Some(&attempts.to_string()),
+        })
+}
+
+/// Publishes `msg` to `topic` with its original key, payload and headers,
+/// plus the dead letter headers.
+pub async fn dead_letter(
+    producer: &FutureProducer,
+    topic: &str,
+    msg: &OwnedMessage,
+    reason: &str,
+    attempts: u32,
+) -> IngestResult<()> {
+    let mut record =
+        FutureRecord::<[u8], [u8]>::to(topic).headers(dead_letter_headers(msg, reason, attempts));
+    if let Some(key) = msg.key() {
+        record = record.key(key);
+    }
+    if let Some(payload) = msg.payload() {
+        record = record.payload(payload);
+    }
+    producer
+        .send(record, Duration::from_secs(30))
+        .await
+        .map_err(|(err, _)| err)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rdkafka::Timestamp;
+
+    use super::*;
+
+    fn header_value<'a>(headers: &'a OwnedHeaders, key: &str) -> Option<&'a [u8]> {
+        headers
+            .iter()
+            .find(|header| header.key == key)
+            .and_then(|header| header.value)
+    }
+
+    #[test]
+    fn test_headers_are_kept_and_added() {
+        let original = OwnedHeaders::new()
+            .insert(Header {
+                key: "facility",
+                value: Some("olathe"),
+            })
+            .insert(Header {
+                key: DEAD_LETTER_ATTEMPTS_HEADER,
+                value: Some("3"),
+            });
+        let msg = OwnedMessage::new(
+            Some(b"{}".to_vec()),
+            None,
+            "test_bundles".to_string(),
+            Timestamp::NotAvailable,
+            0,
+            12,
+            Some(original),
+        );
+
+        let headers = dead_letter_headers(&msg, "invalid_json", 1);
+        assert_eq!(headers.count(), 3);
+        assert_eq!(header_value(&headers, "facility"), Some(&b"olathe"[..]));
+        assert_eq!(
+            header_value(&headers, DEAD_LETTER_REASON_HEADER),
+            Some(&b"invalid_json"[..])
+        );
+        assert_eq!(
+            header_value(&headers, DEAD_LETTER_ATTEMPTS_HEADER),
+            Some(&b"1"[..])
+        );
+    }
+}
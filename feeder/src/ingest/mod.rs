@@ -0,0 +1,565 @@
+mod dead_letter;
+mod offsets;
+mod reprocess;
+mod retry;
+
+pub use dead_letter::*;
+pub use offsets::OffsetTracker;
+pub use reprocess::*;
+pub use retry::*;
+
+use std::{sync::Mutex, time::Duration};
+
+use clickhouse::Client;
+use fhir_model::r4b::resources::Bundle;
+use futures::{StreamExt, stream::FuturesUnordered};
+use rdkafka::{
+    ClientConfig, Message, Offset, TopicPartitionList,
+    admin::{AdminClient, AdminOptions, NewTopic, TopicReplication},
+    client::DefaultClientContext,
+    consumer::{CommitMode, Consumer, StreamConsumer},
+    error::KafkaError,
+    message::Headers,
+    producer::FutureProducer,
+};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+use crate::fhir_r4b_shemav1::{ConversionError, convert_bundle};
+use crate::profile::{FacilityProfile, FacilityProfiles};
+use crate::rules::{Rejection, ScreenedBundle, apply_rules};
+use crate::schemav1::{self, QuarantineEntry};
+use crate::stats::ConversionStats;
+
+/// Where to read bundles from and where to put them.
+#[derive(Debug, Clone)]
+pub struct IngestConfig {
+    pub brokers: String,
+    pub group_id: String,
+    pub topic: String,
+    /// Bumped whenever the tables change in a way [`install_schema_v1`] can't
+    /// apply to existing ones, see there.
+    ///
+    /// [`install_schema_v1`]: schemav1::db_ops::install_schema_v1
+    pub db_name: String,
+    /// How many messages may be processed concurrently. They can finish in
+    /// any order, so anything they write must be safe to interleave.
+    pub max_in_flight: usize,
+    /// How many messages may be read but not committed yet, which includes
+    /// finished ones waiting on a slower one below them. Reading pauses at
+    /// this many until the slow one is done, e.g. while it's retried.
+    pub max_uncommitted: usize,
+    /// Picked per message by its `facility` header.
+    pub profiles: FacilityProfiles,
+    /// How often conversion statistics are written out.
+    pub stats_interval: Duration,
+    /// Where messages which fail permanently are republished. None keeps the
+    /// old behaviour of stopping on them.
+    pub dead_letter_topic: Option<String>,
+    /// For clickhouse and Kafka failures which may go away on their own.
+    pub retry: RetryPolicy,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            brokers: "localhost:9092".to_string(),
+            group_id: "test-group".to_string(),
+            topic: "test_bundles".to_string(),
+            db_name: "attempt_1_2".to_string(),
+            max_in_flight: 10,
+            max_uncommitted: 1000,
+            profiles: FacilityProfiles::default(),
+            stats_interval: Duration::from_secs(60),
+            dead_letter_topic: Some("test_bundles_dead_letter".to_string()),
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+impl IngestConfig {
+    /// Whether [`run`] may read another message while `running` are being
+    /// processed and `tracker` holds everything read but not committed.
+    fn may_receive(&self, running: usize, tracker: &OffsetTracker) -> bool {
+        running < self.max_in_flight && tracker.in_flight() < self.max_uncommitted
+    }
+}
+
+#[derive(Debug)]
+pub enum IngestError {
+    Kafka(KafkaError),
+    Clickhouse(clickhouse::error::Error),
+    Json(serde_json::Error),
+    Conversion(ConversionError),
+}
+
+impl From<KafkaError> for IngestError {
+    fn from(value: KafkaError) -> Self {
+        Self::Kafka(value)
+    }
+}
+
+impl From<clickhouse::error::Error> for IngestError {
+    fn from(value: clickhouse::error::Error) -> Self {
+        Self::Clickhouse(value)
+    }
+}
+
+impl From<serde_json::Error> for IngestError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+impl From<ConversionError> for IngestError {
+    fn from(value: ConversionError) -> Self {
+        Self::Conversion(value)
+    }
+}
+
+impl IngestError {
+    /// Short code for the dead letter reason header.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            IngestError::Kafka(_) => "kafka",
+            IngestError::Clickhouse(_) => "clickhouse",
+            IngestError::Json(_) => "invalid_json",
+            IngestError::Conversion(err) => err.reason.as_str(),
+        }
+    }
+}
+
+pub type IngestResult<T> = Result<T, IngestError>;
+
+fn kafka_client_config(config: &IngestConfig) -> ClientConfig {
+    let mut kafka_config = ClientConfig::new();
+    kafka_config.set("metadata.broker.list", &config.brokers);
+    kafka_config.set("group.id", &config.group_id);
+    // We commit by hand once messages are persisted.
+    kafka_config.set("enable.auto.commit", "false");
+    kafka_config.set("auto.offset.reset", "earliest");
+    kafka_config
+}
+
+/// Creates the ingress and dead-letter topics if they don't exist yet.
+pub async fn ensure_topic(config: &IngestConfig) -> IngestResult<()> {
+    let admin: AdminClient<DefaultClientContext> = kafka_client_config(config).create()?;
+    let topics: Vec<NewTopic> = std::iter::once(&config.topic)
+        .chain(&config.dead_letter_topic)
+        .map(|topic| NewTopic::new(topic, 1, TopicReplication::Fixed(1)))
+        .collect();
+    // Per topic results are ignored, the topics most likely already exist.
+    admin.create_topics(&topics, &AdminOptions::new()).await?;
+    Ok(())
+}
+
+/// Makes a consumer subscribed to the ingress topic with auto commit off.
+pub fn make_consumer(config: &IngestConfig) -> IngestResult<StreamConsumer> {
+    let consumer: StreamConsumer = kafka_client_config(config).create()?;
+    consumer.subscribe(&[&config.topic])?;
+    Ok(consumer)
+}
+
+/// Makes the producer used for the dead-letter topic.
+pub fn make_producer(config: &IngestConfig) -> IngestResult<FutureProducer> {
+    let producer = ClientConfig::new()
+        .set("metadata.broker.list", &config.brokers)
+        .create()?;
+    Ok(producer)
+}
+
+/// Kafka header naming the facility a bundle came from.
+pub const FACILITY_HEADER: &str = "facility";
+
+/// Where in Kafka a payload was read from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageOrigin {
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+}
+
+/// Parses, converts and screens a bundle without storing anything. A payload
+/// which isn't a bundle, or which the profile's bundle policy refuses, comes
+/// back as a single whole bundle rejection.
+pub fn screen_payload(
+    payload: &[u8],
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ScreenedBundle {
+    let bundle = match serde_json::from_slice::<Bundle>(payload) {
+        Ok(bundle) => bundle,
+        Err(_) => {
+            return ScreenedBundle {
+                resources: vec![],
+                rejected: vec![Rejection::whole_bundle("invalid_json", "Bundle", None)],
+            };
+        }
+    };
+    match convert_bundle(&bundle, profile, stats) {
+        Ok(converted) => apply_rules(&bundle, converted, profile, stats),
+        Err(err) => ScreenedBundle {
+            resources: vec![],
+            rejected: vec![Rejection::whole_bundle(
+                err.reason.as_str(),
+                err.path,
+                bundle.id.as_deref(),
+            )],
+        },
+    }
+}
+
+/// What [`process_payload`] did with a bundle.
+#[derive(Debug, Default)]
+pub struct ProcessedPayload {
+    /// Everything which went to the quarantine table.
+    pub rejected: Vec<Rejection>,
+    /// Whether any Locations were stored, so the location closure is stale.
+    pub stored_locations: bool,
+}
+
+/// Converts and stores a single bundle. Whatever can't be stored goes to the
+/// quarantine table, and is returned. Statistics are only counted once
+/// everything is saved, so retrying a failed call doesn't count twice, and
+/// only for bundles which weren't quarantined whole.
+pub async fn process_payload(
+    client: &Client,
+    db_name: &str,
+    profile: &FacilityProfile,
+    stats: &Mutex<ConversionStats>,
+    origin: &MessageOrigin,
+    payload: &[u8],
+) -> IngestResult<ProcessedPayload> {
+    let mut bundle_stats = ConversionStats::default();
+    let screened = screen_payload(payload, profile, &mut bundle_stats);
+    schemav1::db_ops::insert_bundle(client, db_name, &screened.resources).await?;
+    quarantine(
+        client,
+        db_name,
+        profile,
+        origin,
+        payload,
+        &screened.rejected,
+    )
+    .await?;
+    if !screened.rejected.iter().any(Rejection::is_whole_bundle) {
+        stats.lock().unwrap().merge(bundle_stats);
+    }
+    Ok(ProcessedPayload {
+        stored_locations: screened
+            .resources
+            .iter()
+            .any(|res| matches!(res, schemav1::Resource::Location(_))),
+        rejected: screened.rejected,
+    })
+}
+
+/// Just enough of a bundle to slice each entry out of the payload as it was
+/// sent.
+#[derive(Deserialize)]
+struct RawBundle<'a> {
+    #[serde(borrow, default)]
+    entry: Vec<Option<&'a RawValue>>,
+}
+
+impl RawBundle<'_> {
+    /// Entry `index` if it has a resource.
+    fn entry(&self, index: usize) -> Option<(&str, &str)> {
+        let entry = self.entry.get(index)?.as_ref()?.get();
+        let resource = serde_json::from_str::<RawEntry>(entry)
+            .ok()?
+            .resource?
+            .get();
+        Some((entry, resource))
+    }
+
+    /// Every entry with a Medication in it.
+    fn medication_entries(&self) -> impl Iterator<Item = &str> {
+        (0..self.entry.len())
+            .filter_map(|index| self.entry(index))
+            .filter(|(_, resource)| resource_type(resource).as_deref() == Some("Medication"))
+            .map(|(entry, _)| entry)
+    }
+}
+
+#[derive(Deserialize)]
+struct RawEntry<'a> {
+    #[serde(borrow)]
+    resource: Option<&'a RawValue>,
+}
+
+#[derive(Deserialize)]
+struct RawResource {
+    #[serde(rename = "resourceType")]
+    resource_type: String,
+}
+
+/// The resourceType of a resource's json, if it has one.
+fn resource_type(json: &str) -> Option<String> {
+    serde_json::from_str::<RawResource>(json)
+        .ok()
+        .map(|resource| resource.resource_type)
+}
+
+/// Resources whose medication[x] may point at a Medication elsewhere in the
+/// bundle.
+const MEDICATION_USERS: [&str; 3] = [
+    "MedicationRequest",
+    "MedicationStatement",
+    "MedicationAdministration",
+];
+
+/// Wraps entries as they were sent in a collection bundle.
+fn bundle_of_entries<'a>(entries: impl IntoIterator<Item = &'a str>) -> String {
+    format!(
+        r#"{{"resourceType":"Bundle","type":"collection","entry":[{}]}}"#,
+        entries.into_iter().collect::<Vec<_>>().join(",")
+    )
+}
+
+async fn quarantine(
+    client: &Client,
+    db_name: &str,
+    profile: &FacilityProfile,
+    origin: &MessageOrigin,
+    payload: &[u8],
+    rejected: &[Rejection],
+) -> IngestResult<()> {
+    let now = time::OffsetDateTime::now_utc();
+    let raw_bundle = serde_json::from_slice::<RawBundle>(payload).ok();
+    // Medication resources can't be converted without the Medications they
+    // point at, so they're kept in a bundle along with them
+    let raw_resource = |rejection: &Rejection| {
+        let (entry, resource) = raw_bundle.as_ref()?.entry(rejection.entry?)?;
+        if !MEDICATION_USERS.contains(&rejection.resource_type.as_str()) {
+            return Some(resource.to_string());
+        }
+        let medications = raw_bundle.as_ref()?.medication_entries();
+        Some(bundle_of_entries(std::iter::once(entry).chain(medications)))
+    };
+    let entries: Vec<QuarantineEntry> = rejected
+        .iter()
+        .map(|rejection| {
+            // Whole bundles, and resources which somehow can't be sliced out,
+            // keep the whole message so nothing is lost
+            let (resource_type, payload) = match raw_resource(rejection) {
+                Some(resource) => (rejection.resource_type.clone(), resource),
+                None => (
+                    "Bundle".to_string(),
+                    String::from_utf8_lossy(payload).into_owned(),
+                ),
+            };
+            QuarantineEntry {
+                time: now,
+                kafka_topic: origin.topic.clone(),
+                kafka_partition: origin.partition,
+                kafka_offset: origin.offset,
+                facility: profile.name.clone(),
+                resource_type,
+                resource_id: rejection.resource_id.clone().unwrap_or_default(),
+                reason: rejection.reason.clone(),
+                path: rejection.path.clone(),
+                payload,
+            }
+        })
+        .collect();
+    schemav1::db_ops::insert_quarantine(client, db_name, &entries).await?;
+    Ok(())
+}
+
+/// Writes out everything counted since the last flush.
+pub async fn flush_stats(
+    client: &Client,
+    db_name: &str,
+    stats: &Mutex<ConversionStats>,
+) -> IngestResult<()> {
+    let rows = {
+        let stats = stats.lock().unwrap();
+        if stats.is_empty() {
+            return Ok(());
+        }
+        stats.to_rows(time::OffsetDateTime::now_utc())
+    };
+    schemav1::db_ops::insert_conversion_stats(client, db_name, &rows).await?;
+    // Only clear once they're saved. Nothing is converted while we wait on the
+    // insert since the loop in run isn't polling anything else.
+    stats.lock().unwrap().clear();
+    Ok(())
+}
+
+/// Reads bundles off the queue forever, storing them in clickhouse.
+///
+/// Up to `max_in_flight` messages are processed concurrently and may finish
+/// in any order. An offset is only committed once every message below it has
+/// been persisted, so after a crash we may see a message twice but never lose
+/// one. Reading pauses while `max_uncommitted` messages are waiting on that. Messages we can't convert are quarantined and committed like any
+/// other. Transient clickhouse and Kafka failures are retried according to
+/// `retry`, and stop the loop without committing if they outlast it, so the
+/// message is read again once we're restarted. Whole bundles which were
+/// quarantined, and messages which failed permanently, are also published to
+/// the dead-letter topic and committed. Without a dead-letter topic the
+/// latter stop the loop without committing too. Conversion statistics are
+/// flushed every `stats_interval`, and the location closure is refreshed
+/// along with them if Locations were stored since the last refresh. Either
+/// is tried again on the next tick if it fails transiently. Refreshes never
+/// overlap, as long as only one of these runs against a database.
+pub async fn run(
+    consumer: &StreamConsumer,
+    producer: &FutureProducer,
+    client: &Client,
+    config: &IngestConfig,
+) -> IngestResult<()> {
+    let mut tracker = OffsetTracker::default();
+    let stats = Mutex::new(ConversionStats::default());
+    let stats = &stats;
+    let mut in_flight = FuturesUnordered::new();
+    let mut flush_interval = tokio::time::interval(config.stats_interval);
+    let mut locations_changed = false;
+
+    loop {
+        tokio::select! {
+            msg = consumer.recv(), if config.may_receive(in_flight.len(), &tracker) => {
+                let msg = msg?.detach();
+                tracker.track(msg.partition(), msg.offset());
+                let db_name = config.db_name.as_str();
+                let profile = config.profiles.get(facility_of(&msg));
+                let origin = MessageOrigin {
+                    topic: msg.topic().to_string(),
+                    partition: msg.partition(),
+                    offset: msg.offset(),
+                };
+                in_flight.push(async move {
+                    let (result, attempts) = match msg.payload() {
+                        Some(payload) => {
+                            retry(&config.retry, || {
+                                process_payload(client, db_name, profile, stats, &origin, payload)
+                            })
+                            .await
+                        }
+                        // Tombstones have nothing to store
+                        None => (Ok(ProcessedPayload::default()), 1),
+                    };
+                    (msg, result, attempts)
+                });
+            }
+            Some((msg, result, attempts)) = in_flight.next() => {
+                let (partition, offset) = (msg.partition(), msg.offset());
+                let rejected = match (result, &config.dead_letter_topic) {
+                    (Ok(processed), _) => {
+                        locations_changed |= processed.stored_locations;
+                        processed.rejected
+                    }
+                    // Transient failures which outlasted the retries stop
+                    // us instead, rather than sending everything in flight
+                    // to the dead-letter topic while clickhouse is away
+                    (Err(err), Some(topic)) if !err.is_transient() => {
+                        log::error!(
+                            "Dead-lettering {}/{} after {} attempts: {:?}",
+                            partition, offset, attempts, err
+                        );
+                        retry(&config.retry, || {
+                            dead_letter(producer, topic, &msg, err.reason(), attempts)
+                        })
+                        .await
+                        .0?;
+                        vec![]
+                    }
+                    (Err(err), _) => return Err(err),
+                };
+                for rejection in &rejected {
+                    log::warn!(
+                        "Quarantined {}/{} from {}/{}: {} at {}",
+                        rejection.resource_type,
+                        rejection.resource_id.as_deref().unwrap_or("?"),
+                        partition,
+                        offset,
+                        rejection.reason,
+                        rejection.path,
+                    );
+                }
+                let whole_bundle = rejected.iter().find(|rejection| rejection.is_whole_bundle());
+                if let (Some(rejection), Some(topic)) = (whole_bundle, &config.dead_letter_topic) {
+                    retry(&config.retry, || {
+                        dead_letter(producer, topic, &msg, &rejection.reason, attempts)
+                    })
+                    .await
+                    .0?;
+                }
+                if let Some(next) = tracker.complete(partition, offset) {
+                    let mut tpl = TopicPartitionList::new();
+                    tpl.add_partition_offset(&config.topic, partition, Offset::Offset(next))?;
+                    consumer.commit(&tpl, CommitMode::Async)?;
+                }
+            }
+            _ = flush_interval.tick() => {
+                match flush_stats(client, &config.db_name, stats).await {
+                    Err(err) if err.is_transient() => {
+                        log::warn!("Couldn't flush stats, keeping them for later: {:?}", err);
+                    }
+                    result => result?,
+                }
+                if locations_changed {
+                    let refreshed =
+                        schemav1::db_ops::refresh_location_closure(client, &config.db_name)
+                            .await
+                            .map_err(IngestError::from);
+                    match refreshed {
+                        Ok(()) => locations_changed = false,
+                        Err(err) if err.is_transient() => {
+                            log::warn!("Couldn't refresh the location closure: {:?}", err);
+                        }
+                        Err(err) => return Err(err),
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn facility_of(msg: &impl Message) -> Option<&str> {
+    msg.headers()?
+        .iter()
+        .find(|header| header.key == FACILITY_HEADER)
+        .and_then(|header| header.value)
+        .and_then(|value| std::str::from_utf8(value).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stalled_low_offset_stops_receiving() {
+        let config = IngestConfig {
+            max_in_flight: 10,
+            max_uncommitted: 3,
+            ..Default::default()
+        };
+        let mut tracker = OffsetTracker::default();
+        for offset in 0..3 {
+            tracker.track(0, offset);
+        }
+        assert!(!config.may_receive(3, &tracker));
+
+        // Everything above 0 is done, but 0 is still being retried
+        assert_eq!(tracker.complete(0, 1), None);
+        assert_eq!(tracker.complete(0, 2), None);
+        assert!(!config.may_receive(1, &tracker));
+
+        assert_eq!(tracker.complete(0, 0), Some(3));
+        assert!(config.may_receive(0, &tracker));
+    }
+
+    #[test]
+    fn test_max_in_flight_stops_receiving() {
+        let config = IngestConfig {
+            max_in_flight: 2,
+            ..Default::default()
+        };
+        let mut tracker = OffsetTracker::default();
+        tracker.track(0, 0);
+        tracker.track(0, 1);
+        assert!(!config.may_receive(2, &tracker));
+        assert!(config.may_receive(1, &tracker));
+    }
+}
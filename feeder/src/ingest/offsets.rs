@@ -0,0 +1,99 @@
+use std::collections::{BTreeMap, HashMap};
+
+/// Keeps track of which kafka messages have been read but not yet persisted
+/// so that we only ever commit an offset once every message below it is
+/// safely in clickhouse. Messages can finish out of order, so a finished
+/// message may have to wait for a slower one before it gets committed.
+#[derive(Debug, Default)]
+pub struct OffsetTracker {
+    /// partition -> offset -> whether the message has been persisted
+    partitions: HashMap<i32, BTreeMap<i64, bool>>,
+}
+
+impl OffsetTracker {
+    /// Registers a message which has been read from the queue.
+    pub fn track(&mut self, partition: i32, offset: i64) {
+        self.partitions
+            .entry(partition)
+            .or_default()
+            .insert(offset, false);
+    }
+
+    /// Marks a tracked message as persisted. If that advances the contiguous
+    /// run of persisted messages at the front of the partition, returns the
+    /// offset which should be committed, which is one past the last persisted
+    /// message as kafka expects.
+    pub fn complete(&mut self, partition: i32, offset: i64) -> Option<i64> {
+        let pending = self.partitions.get_mut(&partition)?;
+        match pending.get_mut(&offset) {
+            Some(done) => *done = true,
+            None => return None,
+        }
+
+        let mut committable = None;
+        while let Some(entry) = pending.first_entry() {
+            if !*entry.get() {
+                break;
+            }
+            committable = Some(*entry.key() + 1);
+            entry.remove();
+        }
+        committable
+    }
+
+    /// Number of messages which have been read but not yet committed.
+    pub fn in_flight(&self) -> usize {
+        self.partitions.values().map(|pending| pending.len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_order_completion() {
+        let mut tracker = OffsetTracker::default();
+        tracker.track(0, 5);
+        tracker.track(0, 6);
+
+        assert_eq!(tracker.complete(0, 5), Some(6));
+        assert_eq!(tracker.complete(0, 6), Some(7));
+        assert_eq!(tracker.in_flight(), 0);
+    }
+
+    #[test]
+    fn test_out_of_order_completion_waits_for_gap() {
+        let mut tracker = OffsetTracker::default();
+        tracker.track(0, 1);
+        tracker.track(0, 2);
+        tracker.track(0, 3);
+
+        assert_eq!(tracker.complete(0, 3), None);
+        assert_eq!(tracker.complete(0, 2), None);
+        assert_eq!(tracker.in_flight(), 3);
+        assert_eq!(tracker.complete(0, 1), Some(4));
+        assert_eq!(tracker.in_flight(), 0);
+    }
+
+    #[test]
+    fn test_partitions_are_independent() {
+        let mut tracker = OffsetTracker::default();
+        tracker.track(0, 10);
+        tracker.track(1, 10);
+        tracker.track(1, 11);
+
+        assert_eq!(tracker.complete(1, 11), None);
+        assert_eq!(tracker.complete(0, 10), Some(11));
+        assert_eq!(tracker.complete(1, 10), Some(12));
+    }
+
+    #[test]
+    fn test_untracked_offsets_are_ignored() {
+        let mut tracker = OffsetTracker::default();
+        assert_eq!(tracker.complete(0, 1), None);
+        tracker.track(0, 2);
+        assert_eq!(tracker.complete(0, 1), None);
+        assert_eq!(tracker.in_flight(), 1);
+    }
+}
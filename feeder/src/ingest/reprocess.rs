@@ -0,0 +1,71 @@
+//! Second chances for quarantined items, once a profile or the converter has
+//! been fixed to handle them.
+
+use std::sync::Mutex;
+
+use clickhouse::Client;
+
+use super::{IngestResult, bundle_of_entries, flush_stats, resource_type, screen_payload};
+use crate::profile::FacilityProfiles;
+use crate::schemav1::{self, QuarantineEntry};
+use crate::stats::ConversionStats;
+
+/// What a reprocessing pass did with the quarantine table.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReprocessSummary {
+    /// Converted, stored and removed from the quarantine.
+    pub resolved: usize,
+    /// Still rejected, left where they were.
+    pub remaining: usize,
+}
+
+/// Runs every quarantined item through the current converter and rules with
+/// its facility's profile. Items which now pass in full are stored and removed
+/// from the quarantine, anything else is left alone, so a bundle which only
+/// partly passes stays quarantined as it was. Whole bundles weren't counted in
+/// the conversion statistics when they were quarantined, so they are once
+/// they're stored. The location closure is refreshed if any Locations were.
+pub async fn reprocess_quarantine(
+    client: &Client,
+    db_name: &str,
+    profiles: &FacilityProfiles,
+) -> IngestResult<ReprocessSummary> {
+    let mut summary = ReprocessSummary::default();
+    let stats = Mutex::new(ConversionStats::default());
+    let mut stored_locations = false;
+    for entry in schemav1::db_ops::fetch_quarantine(client, db_name).await? {
+        let profile = profiles.get(Some(&entry.facility));
+        let mut entry_stats = ConversionStats::default();
+        let screened = screen_payload(as_bundle(&entry).as_bytes(), profile, &mut entry_stats);
+        if !screened.rejected.is_empty() {
+            summary.remaining += 1;
+            continue;
+        }
+        schemav1::db_ops::insert_bundle(client, db_name, &screened.resources).await?;
+        schemav1::db_ops::delete_quarantined(client, db_name, &entry).await?;
+        stored_locations |= screened
+            .resources
+            .iter()
+            .any(|res| matches!(res, schemav1::Resource::Location(_)));
+        // Single resources were counted when they were first quarantined
+        if entry.resource_type == "Bundle" {
+            stats.lock().unwrap().merge(entry_stats);
+        }
+        summary.resolved += 1;
+    }
+    flush_stats(client, db_name, &stats).await?;
+    if stored_locations {
+        schemav1::db_ops::refresh_location_closure(client, db_name).await?;
+    }
+    Ok(summary)
+}
+
+/// Single resources are quarantined on their own, wrap them back up.
+/// Medication resources already come in a bundle with their Medications.
+fn as_bundle(entry: &QuarantineEntry) -> String {
+    if entry.resource_type == "Bundle" || resource_type(&entry.payload).as_deref() == Some("Bundle")
+    {
+        return entry.payload.clone();
+    }
+    bundle_of_entries([format!(r#"{{"resource":{}}}"#, entry.payload).as_str()])
+}
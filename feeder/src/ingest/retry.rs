@@ -0,0 +1,214 @@
+//! Telling failures worth another try (a busy or unreachable clickhouse or
+//! broker) apart from ones that will fail the same way forever (a bundle we
+//! can't convert), and retrying the former with exponential backoff.
+
+use std::{future::Future, time::Duration};
+
+use rdkafka::{error::KafkaError, types::RDKafkaErrorCode};
+
+use super::{IngestError, IngestResult};
+
+/// ClickHouse server error codes for overload and connectivity problems,
+/// see `src/Common/ErrorCodes.cpp` in ClickHouse.
+const TRANSIENT_CLICKHOUSE_CODES: &[u32] = &[
+    159, // TIMEOUT_EXCEEDED
+    202, // TOO_MANY_SIMULTANEOUS_QUERIES
+    209, // SOCKET_TIMEOUT
+    210, // NETWORK_ERROR
+    241, // MEMORY_LIMIT_EXCEEDED
+    242, // TABLE_IS_READ_ONLY
+    252, // TOO_MANY_PARTS
+    319, // UNKNOWN_STATUS_OF_INSERT
+];
+
+impl IngestError {
+    /// Whether trying the same thing again later might work.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            IngestError::Kafka(err) => kafka_is_transient(err),
+            IngestError::Clickhouse(err) => clickhouse_is_transient(err),
+            IngestError::Json(_) | IngestError::Conversion(_) => false,
+        }
+    }
+}
+
+fn clickhouse_is_transient(err: &clickhouse::error::Error) -> bool {
+    use clickhouse::error::Error;
+    match err {
+        Error::Network(_) | Error::TimedOut => true,
+        // Either "Code: 241. DB::Exception: ..." from the server, or just the
+        // HTTP status when the body couldn't be read.
+        Error::BadResponse(reason) => {
+            let code = reason
+                .strip_prefix("Code: ")
+                .and_then(|rest| rest.split(|c: char| !c.is_ascii_digit()).next())
+                .and_then(|code| code.parse::<u32>().ok());
+            match code {
+                Some(code) => TRANSIENT_CLICKHOUSE_CODES.contains(&code),
+                None => ["502", "503", "504"]
+                    .iter()
+                    .any(|status| reason.starts_with(status)),
+            }
+        }
+        _ => false,
+    }
+}
+
+fn kafka_is_transient(err: &KafkaError) -> bool {
+    matches!(
+        err.rdkafka_error_code(),
+        Some(
+            RDKafkaErrorCode::BrokerTransportFailure
+                | RDKafkaErrorCode::AllBrokersDown
+                | RDKafkaErrorCode::MessageTimedOut
+                | RDKafkaErrorCode::OperationTimedOut
+                | RDKafkaErrorCode::QueueFull
+                | RDKafkaErrorCode::LeaderNotAvailable
+                | RDKafkaErrorCode::NotLeaderForPartition
+                | RDKafkaErrorCode::RequestTimedOut
+                | RDKafkaErrorCode::BrokerNotAvailable
+                | RDKafkaErrorCode::NetworkException
+                | RDKafkaErrorCode::CoordinatorLoadInProgress
+                | RDKafkaErrorCode::CoordinatorNotAvailable
+                | RDKafkaErrorCode::NotCoordinator
+                | RDKafkaErrorCode::NotEnoughReplicas
+                | RDKafkaErrorCode::NotEnoughReplicasAfterAppend
+        )
+    )
+}
+
+/// How often and how patiently transient failures are retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first one.
+    pub max_attempts: u32,
+    /// Wait before the first retry.
+    pub initial_backoff: Duration,
+    /// Each wait is this many times longer than the last.
+    pub multiplier: f64,
+    /// Waits never grow past this.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            multiplier: 2.0,
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait after the `attempt`th attempt failed, counting from 1.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        self.initial_backoff.mul_f64(factor).min(self.max_backoff)
+    }
+}
+
+/// Runs `op` until it succeeds, fails permanently or runs out of attempts.
+/// Returns its last result along with how many attempts were made.
+pub async fn retry<T, F, Fut>(policy: &RetryPolicy, mut op: F) -> (IngestResult<T>, u32)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = IngestResult<T>>,
+{
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match op().await {
+            Err(err) if err.is_transient() && attempts < policy.max_attempts => {
+                log::warn!("Attempt {} failed, retrying: {:?}", attempts, err);
+                tokio::time::sleep(policy.backoff(attempts)).await;
+            }
+            result => return (result, attempts),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bad_response(reason: &str) -> IngestError {
+        IngestError::Clickhouse(clickhouse::error::Error::BadResponse(reason.to_string()))
+    }
+
+    #[test]
+    fn test_classification() {
+        assert!(IngestError::Clickhouse(clickhouse::error::Error::TimedOut).is_transient());
+        assert!(bad_response("Code: 252. DB::Exception: Too many parts").is_transient());
+        assert!(bad_response("503 Service Unavailable").is_transient());
+        assert!(!bad_response("Code: 62. DB::Exception: Syntax error").is_transient());
+        assert!(
+            IngestError::Kafka(KafkaError::MessageProduction(
+                RDKafkaErrorCode::MessageTimedOut
+            ))
+            .is_transient()
+        );
+        assert!(
+            !IngestError::Kafka(KafkaError::MessageProduction(
+                RDKafkaErrorCode::MessageSizeTooLarge
+            ))
+            .is_transient()
+        );
+        let json = serde_json::from_str::<u8>("x").unwrap_err();
+        assert!(!IngestError::Json(json).is_transient());
+    }
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_secs(1),
+            multiplier: 3.0,
+            max_backoff: Duration::from_secs(20),
+        };
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(3));
+        assert_eq!(policy.backoff(3), Duration::from_secs(9));
+        assert_eq!(policy.backoff(4), Duration::from_secs(20));
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::ZERO,
+            ..Default::default()
+        };
+
+        let (result, attempts) = retry(&policy, || async {
+            Err::<(), _>(IngestError::Clickhouse(clickhouse::error::Error::TimedOut))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+
+        let (result, attempts) = retry(&policy, || async {
+            Err::<(), _>(bad_response("Code: 62. DB::Exception: Syntax error"))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+
+        let mut failures = 1;
+        let (result, attempts) = retry(&policy, || {
+            let fail = failures > 0;
+            failures -= 1;
+            async move {
+                if fail {
+                    Err(IngestError::Clickhouse(clickhouse::error::Error::TimedOut))
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(attempts, 2);
+    }
+}
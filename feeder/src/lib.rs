@@ -1,2 +1,6 @@
 pub mod fhir_r4b_shemav1;
+pub mod ingest;
+pub mod profile;
+pub mod rules;
 pub mod schemav1;
+pub mod stats;
@@ -0,0 +1,252 @@
+//! Per facility knobs for how we interpret what they send us. Every feed has
+//! its own habits (dates without days, missing periods, fields they never
+//! fill in) and a profile is where we write down what to do about them instead
+//! of guessing the same way for everyone.
+
+use std::{collections::HashMap, fmt, path::Path};
+
+use serde::Deserialize;
+use time::{Month, UtcOffset};
+
+use crate::fhir_r4b_shemav1::{
+    BundlePolicy, CONVERTED_RESOURCE_TYPES, ExtensionRegistry, PatientExtensionHandler,
+    REQUIRABLE_PATHS,
+};
+use crate::rules::RejectionRule;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FacilityProfile {
+    /// Filled in from the key in the profiles file.
+    #[serde(skip)]
+    pub name: String,
+    /// Month used when a date only has a year. Given as a number in the file.
+    #[serde(deserialize_with = "month_from_number")]
+    pub fill_month: Month,
+    /// Day used when a date only has a year or a year and month, or the
+    /// month's last day if it is shorter.
+    pub fill_day: u8,
+    /// Offset assumed for dates which come without a time or timezone. This
+    /// is a fixed offset, daylight saving is not accounted for. Given in
+    /// minutes east of UTC in the file.
+    #[serde(
+        rename = "utc_offset_minutes",
+        deserialize_with = "offset_from_minutes"
+    )]
+    pub utc_offset: UtcOffset,
+    /// Element paths which must be present, e.g. `Patient.birthDate`. Only
+    /// those in [`REQUIRABLE_PATHS`] are allowed.
+    pub required: Vec<String>,
+    /// Resource types to convert, anything else in a bundle is skipped. None
+    /// accepts every type we know how to convert, the ones in
+    /// [`CONVERTED_RESOURCE_TYPES`].
+    pub accept: Option<Vec<String>>,
+    pub bundle_policy: BundlePolicy,
+    /// Checked after conversion, see [`crate::rules`].
+    pub rules: Vec<RejectionRule>,
+    /// Handlers for the extensions we store, registered in code.
+    #[serde(skip)]
+    pub extensions: ExtensionRegistry,
+}
+
+impl Default for FacilityProfile {
+    fn default() -> Self {
+        Self {
+            name: "default".to_string(),
+            fill_month: Month::January,
+            fill_day: 1,
+            utc_offset: UtcOffset::UTC,
+            required: vec![],
+            accept: None,
+            bundle_policy: BundlePolicy::default(),
+            rules: vec![],
+            extensions: ExtensionRegistry::default(),
+        }
+    }
+}
+
+impl FacilityProfile {
+    /// Whether the element at `path` must be present. Only the paths in
+    /// [`REQUIRABLE_PATHS`] are ever asked about.
+    pub fn requires(&self, path: &str) -> bool {
+        self.required.iter().any(|required| required == path)
+    }
+
+    pub fn accepts(&self, resource_type: &str) -> bool {
+        match &self.accept {
+            None => true,
+            Some(types) => types.iter().any(|ty| ty == resource_type),
+        }
+    }
+
+    fn validate(&self) -> Result<(), ProfileError> {
+        // 2000 is a leap year so every real day of the month is accepted,
+        // parse_date clamps it in shorter months
+        time::Date::from_calendar_date(2000, self.fill_month, self.fill_day)
+            .map_err(|_| ProfileError::Invalid(format!("fill_day {}", self.fill_day)))?;
+        // A typo would otherwise quietly never be enforced
+        if let Some(path) = self
+            .required
+            .iter()
+            .find(|path| !REQUIRABLE_PATHS.contains(&path.as_str()))
+        {
+            return Err(ProfileError::Invalid(format!("required {}", path)));
+        }
+        if let Some(ty) = self
+            .accept
+            .iter()
+            .flatten()
+            .find(|ty| !CONVERTED_RESOURCE_TYPES.contains(&ty.as_str()))
+        {
+            return Err(ProfileError::Invalid(format!("accept {}", ty)));
+        }
+        Ok(())
+    }
+}
+
+fn month_from_number<'de, D>(deserializer: D) -> Result<Month, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let month = u8::deserialize(deserializer)?;
+    Month::try_from(month).map_err(serde::de::Error::custom)
+}
+
+fn offset_from_minutes<'de, D>(deserializer: D) -> Result<UtcOffset, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let minutes = i32::deserialize(deserializer)?;
+    UtcOffset::from_whole_seconds(minutes * 60).map_err(serde::de::Error::custom)
+}
+
+/// Every known facility's profile, plus the one used for everybody else.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FacilityProfiles {
+    pub default: FacilityProfile,
+    pub facilities: HashMap<String, FacilityProfile>,
+}
+
+impl FacilityProfiles {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ProfileError> {
+        let text = std::fs::read_to_string(path).map_err(ProfileError::Io)?;
+        Self::from_json(&text)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, ProfileError> {
+        let mut profiles: FacilityProfiles =
+            serde_json::from_str(text).map_err(ProfileError::Json)?;
+        profiles.default.name = "default".to_string();
+        for (name, profile) in profiles.facilities.iter_mut() {
+            profile.name = name.clone();
+        }
+
+        profiles.default.validate()?;
+        for profile in profiles.facilities.values() {
+            profile.validate()?;
+        }
+        Ok(profiles)
+    }
+
+    /// Registers a handler for a local Patient extension with every profile.
+    pub fn register_patient_extension(&mut self, url: &str, handler: PatientExtensionHandler) {
+        self.default.extensions.register_patient(url, handler);
+        for profile in self.facilities.values_mut() {
+            profile.extensions.register_patient(url, handler);
+        }
+    }
+
+    /// Profile for the facility a message came from, falling back on the
+    /// default one for unknown or unlabeled facilities.
+    pub fn get(&self, facility: Option<&str>) -> &FacilityProfile {
+        facility
+            .and_then(|name| self.facilities.get(name))
+            .unwrap_or(&self.default)
+    }
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::Io(err) => write!(f, "can't read profiles: {}", err),
+            ProfileError::Json(err) => write!(f, "can't parse profiles: {}", err),
+            ProfileError::Invalid(what) => write!(f, "invalid profile setting: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_facility_gets_default() {
+        let profiles = FacilityProfiles::from_json(
+            r#"{ "facilities": { "olathe": { "utc_offset_minutes": -300 } } }"#,
+        )
+        .unwrap();
+
+        assert_eq!(profiles.get(Some("olathe")).name, "olathe");
+        assert_eq!(
+            profiles.get(Some("olathe")).utc_offset.whole_minutes(),
+            -300
+        );
+        assert_eq!(profiles.get(Some("nowhere")).name, "default");
+        assert_eq!(profiles.get(None).name, "default");
+    }
+
+    #[test]
+    fn test_invalid_settings_are_rejected() {
+        let result =
+            FacilityProfiles::from_json(r#"{ "default": { "fill_day": 31, "fill_month": 2 } }"#);
+        assert!(matches!(result, Err(ProfileError::Invalid(_))));
+
+        let result = FacilityProfiles::from_json(r#"{ "default": { "fill_month": 13 } }"#);
+        assert!(matches!(result, Err(ProfileError::Json(_))));
+
+        let result = FacilityProfiles::from_json(r#"{ "default": { "fill_mnth": 2 } }"#);
+        assert!(matches!(result, Err(ProfileError::Json(_))));
+    }
+
+    #[test]
+    fn test_unknown_paths_and_types_are_rejected() {
+        let result =
+            FacilityProfiles::from_json(r#"{ "default": { "required": ["Patient.birthdate"] } }"#);
+        assert!(
+            matches!(result, Err(ProfileError::Invalid(what)) if what == "required Patient.birthdate")
+        );
+
+        let result = FacilityProfiles::from_json(
+            r#"{ "facilities": { "olathe": { "accept": ["Patient", "Observations"] } } }"#,
+        );
+        assert!(
+            matches!(result, Err(ProfileError::Invalid(what)) if what == "accept Observations")
+        );
+
+        let result = FacilityProfiles::from_json(
+            r#"{ "default": { "required": ["Patient.birthDate"], "accept": ["Patient"] } }"#,
+        );
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_accepts() {
+        let profile = FacilityProfile {
+            accept: Some(vec!["Patient".to_string()]),
+            ..Default::default()
+        };
+        assert!(profile.accepts("Patient"));
+        assert!(!profile.accepts("Encounter"));
+        assert!(FacilityProfile::default().accepts("Encounter"));
+    }
+}
@@ -0,0 +1,227 @@
+//! Facility chosen conditions under which we refuse data that converted fine.
+//! Rules run over a bundle after [`convert_bundle`], and everything they (or
+//! the conversion) turn away comes back as a [`Rejection`] so it can be
+//! stored and looked at later.
+//!
+//! [`convert_bundle`]: crate::fhir_r4b_shemav1::convert_bundle
+
+use std::collections::HashMap;
+
+use fhir_model::r4b::resources::{Bundle, Resource};
+use serde::Deserialize;
+
+use crate::fhir_r4b_shemav1::{BundlePolicy, ConversionError, ConvertedBundle};
+use crate::profile::FacilityProfile;
+use crate::schemav1::{self, FieldOutcome};
+use crate::stats::ConversionStats;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case", deny_unknown_fields)]
+pub enum RejectionRule {
+    /// Reject Patients without a birthDate.
+    PatientWithoutBirthDate,
+    /// Reject Encounters whose subject isn't a reference to a Patient.
+    SubjectNotPatient,
+    /// Reject the whole bundle when more than `percent` of its entries were
+    /// rejected.
+    MaxFailedPercent { percent: f64 },
+}
+
+impl RejectionRule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RejectionRule::PatientWithoutBirthDate => "patient_without_birth_date",
+            RejectionRule::SubjectNotPatient => "subject_not_patient",
+            RejectionRule::MaxFailedPercent { .. } => "max_failed_percent",
+        }
+    }
+
+    /// The path of the offending element if `resource` breaks this rule.
+    fn check(&self, resource: &Resource) -> Option<&'static str> {
+        match (self, resource) {
+            (RejectionRule::PatientWithoutBirthDate, Resource::Patient(patient)) => {
+                patient.birth_date.is_none().then_some("Patient.birthDate")
+            }
+            (RejectionRule::SubjectNotPatient, Resource::Encounter(encounter)) => {
+                let is_patient = encounter.subject.as_ref().is_some_and(|subject| {
+                    let typed_patient = subject.r#type.as_deref().is_none_or(|ty| ty == "Patient");
+                    let points_at_patient = subject
+                        .reference
+                        .as_deref()
+                        .is_some_and(|reference| reference.starts_with("Patient/"));
+                    typed_patient && points_at_patient
+                });
+                (!is_patient).then_some("Encounter.subject")
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A resource we're not going to store, and why.
+#[derive(Debug)]
+pub struct Rejection {
+    /// Rule name or conversion error reason code.
+    pub reason: String,
+    pub path: String,
+    pub resource_type: String,
+    pub resource_id: Option<String>,
+    /// Index of the resource's entry in Bundle.entry, None for whole bundle
+    /// rejections.
+    pub entry: Option<usize>,
+}
+
+impl Rejection {
+    /// Turns away the whole message, for when it isn't a bundle we can parse
+    /// or the profile doesn't store partial bundles.
+    pub fn whole_bundle(
+        reason: impl Into<String>,
+        path: impl Into<String>,
+        bundle_id: Option<&str>,
+    ) -> Self {
+        Rejection {
+            reason: reason.into(),
+            path: path.into(),
+            resource_type: "Bundle".to_string(),
+            resource_id: bundle_id.map(str::to_string),
+            entry: None,
+        }
+    }
+
+    pub fn is_whole_bundle(&self) -> bool {
+        self.resource_type == "Bundle"
+    }
+
+    fn from_conversion_error(err: ConversionError) -> Self {
+        Rejection {
+            reason: err.reason.as_str().to_string(),
+            path: err.path,
+            resource_type: err.resource_type,
+            resource_id: err.resource_id,
+            entry: err.entry,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ScreenedBundle {
+    pub resources: Vec<schemav1::Resource>,
+    pub rejected: Vec<Rejection>,
+}
+
+/// Checks the profile's rules against a converted bundle, moving everything
+/// they reject, along with the entries which failed to convert, into
+/// [`ScreenedBundle::rejected`]. Under [`BundlePolicy::AllOrNothing`] a
+/// resource breaking a rule rejects the whole bundle, as a failed conversion
+/// would have.
+pub fn apply_rules(
+    src: &Bundle,
+    converted: ConvertedBundle,
+    profile: &FacilityProfile,
+    stats: &mut ConversionStats,
+) -> ScreenedBundle {
+    let mut rejected: Vec<Rejection> = converted
+        .failures
+        .into_iter()
+        .map(Rejection::from_conversion_error)
+        .collect();
+    if profile.rules.is_empty() {
+        return ScreenedBundle {
+            resources: converted.resources,
+            rejected,
+        };
+    }
+    let entries = EntryIndex::new(src);
+    let mut resources = vec![];
+
+    for row in converted.resources {
+        let Some((index, resource)) = entries.find(row.resource_type(), row.id()) else {
+            resources.push(row);
+            continue;
+        };
+        let broken = profile
+            .rules
+            .iter()
+            .find_map(|rule| rule.check(resource).map(|path| (rule, path)));
+        match broken {
+            Some((rule, path)) => {
+                stats.record(&profile.name, path, FieldOutcome::Rejected);
+                if profile.bundle_policy == BundlePolicy::AllOrNothing {
+                    return ScreenedBundle {
+                        resources: vec![],
+                        rejected: vec![Rejection::whole_bundle(
+                            rule.name(),
+                            path,
+                            src.id.as_deref(),
+                        )],
+                    };
+                }
+                rejected.push(Rejection {
+                    reason: rule.name().to_string(),
+                    path: path.to_string(),
+                    resource_type: row.resource_type().to_string(),
+                    resource_id: Some(row.id().to_string()),
+                    entry: Some(index),
+                });
+            }
+            None => resources.push(row),
+        }
+    }
+
+    let total = resources.len() + rejected.len();
+    for rule in &profile.rules {
+        let RejectionRule::MaxFailedPercent { percent } = rule else {
+            continue;
+        };
+        if total == 0 || rejected.len() as f64 * 100.0 / total as f64 <= *percent {
+            continue;
+        }
+        for row in resources.drain(..) {
+            stats.record(&profile.name, "Bundle.entry", FieldOutcome::Rejected);
+            rejected.push(Rejection {
+                reason: rule.name().to_string(),
+                path: "Bundle.entry".to_string(),
+                resource_type: row.resource_type().to_string(),
+                resource_id: Some(row.id().to_string()),
+                entry: entries
+                    .find(row.resource_type(), row.id())
+                    .map(|(index, _)| index),
+            });
+        }
+    }
+
+    ScreenedBundle {
+        resources,
+        rejected,
+    }
+}
+
+/// A bundle's entries by resource id, so finding the entry of each converted
+/// row doesn't mean going through the whole bundle again.
+struct EntryIndex<'a> {
+    by_id: HashMap<&'a str, Vec<(usize, &'a Resource)>>,
+}
+
+impl<'a> EntryIndex<'a> {
+    fn new(src: &'a Bundle) -> Self {
+        let mut by_id: HashMap<&str, Vec<(usize, &Resource)>> = HashMap::new();
+        for (index, entry) in src.entry.iter().enumerate() {
+            let Some(res) = entry.as_ref().and_then(|entry| entry.resource.as_ref()) else {
+                continue;
+            };
+            if let Some(id) = res.as_base_resource().id().as_deref() {
+                by_id.entry(id).or_default().push((index, res));
+            }
+        }
+        EntryIndex { by_id }
+    }
+
+    /// Index and resource of the first entry holding `resource_type/id`.
+    fn find(&self, resource_type: &str, id: &str) -> Option<(usize, &'a Resource)> {
+        self.by_id
+            .get(id)?
+            .iter()
+            .find(|(_, res)| res.resource_type().as_ref() == resource_type)
+            .copied()
+    }
+}
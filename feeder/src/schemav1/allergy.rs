@@ -0,0 +1,76 @@
+use clickhouse::Row;
+use serde::Serialize;
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use super::{ClinicalTimeType, TimeResolution};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum AllergyType {
+    Unknown = 0,
+    Allergy = 1,
+    Intolerance = 2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum AllergyCriticality {
+    Unknown = 0,
+    Low = 1,
+    High = 2,
+    UnableToAssess = 3,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum ReactionSeverity {
+    Unknown = 0,
+    Mild = 1,
+    Moderate = 2,
+    Severe = 3,
+}
+
+#[derive(Debug, Row, Serialize)]
+pub struct AllergyIntolerance {
+    pub id: String,
+    /// e.g. `active` or `resolved`.
+    pub clinical_status: String,
+    /// e.g. `confirmed` or `refuted`.
+    pub verification_status: String,
+    pub allergy_type: AllergyType,
+    /// `food`, `medication`, `environment` or `biologic`.
+    pub category: Vec<String>,
+    pub criticality: AllergyCriticality,
+    /// The substance, or a negated code like "no known allergy".
+    pub code: String,
+    pub code_description: String,
+    pub code_system: String,
+    /// Patient id, from AllergyIntolerance.patient.
+    pub subject: String,
+    /// Encounter id
+    pub encounter: String,
+
+    /// Same as the onset columns of [`Condition`](super::Condition).
+    pub onset_type: ClinicalTimeType,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub onset_start: Option<time::OffsetDateTime>,
+    pub onset_start_resolution: TimeResolution,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub onset_end: Option<time::OffsetDateTime>,
+    pub onset_end_resolution: TimeResolution,
+    pub onset_age_low: Option<f64>,
+    pub onset_age_high: Option<f64>,
+    pub onset_age_unit: String,
+    pub onset_text: String,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub recorded_date: Option<time::OffsetDateTime>,
+    pub recorded_date_resolution: TimeResolution,
+
+    /// First coding of the first manifestation of each reaction.
+    #[serde(rename = "reactions.manifestation")]
+    pub reactions_manifestation: Vec<String>,
+    #[serde(rename = "reactions.manifestation_description")]
+    pub reactions_manifestation_description: Vec<String>,
+    #[serde(rename = "reactions.severity")]
+    pub reactions_severity: Vec<ReactionSeverity>,
+}
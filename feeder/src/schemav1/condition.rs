@@ -0,0 +1,79 @@
+use clickhouse::Row;
+use serde::Serialize;
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use super::TimeResolution;
+
+/// Which variant of a dateTime, Age, Period, Range or string choice element
+/// the source used, e.g. for Condition.onset[x] or Procedure.performed[x].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum ClinicalTimeType {
+    None = 0,
+    DateTime = 1,
+    Age = 2,
+    Period = 3,
+    Range = 4,
+    String = 5,
+}
+
+#[derive(Debug, Row, Serialize)]
+pub struct Condition {
+    pub id: String,
+    /// First code of Condition.clinicalStatus, e.g. `active` or `resolved`.
+    pub clinical_status: String,
+    /// First code of Condition.verificationStatus, e.g. `confirmed`.
+    pub verification_status: String,
+    /// First code of each Condition.category, e.g. `problem-list-item` or
+    /// `encounter-diagnosis`.
+    pub category: Vec<String>,
+
+    /// First coding of Condition.code with a code, see `codings` for all of
+    /// them.
+    pub code: String,
+    pub code_description: String,
+    pub code_system: String,
+    #[serde(rename = "codings.code")]
+    pub codings_code: Vec<String>,
+    #[serde(rename = "codings.display")]
+    pub codings_display: Vec<String>,
+    #[serde(rename = "codings.system")]
+    pub codings_system: Vec<String>,
+
+    /// Patient id, empty when the subject is a Group.
+    pub subject: String,
+    /// Encounter id
+    pub encounter: String,
+
+    /// onsetDateTime only has a start. onsetAge and onsetRange go in the
+    /// `age` columns, an age being both the low and the high.
+    pub onset_type: ClinicalTimeType,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub onset_start: Option<time::OffsetDateTime>,
+    pub onset_start_resolution: TimeResolution,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub onset_end: Option<time::OffsetDateTime>,
+    pub onset_end_resolution: TimeResolution,
+    pub onset_age_low: Option<f64>,
+    pub onset_age_high: Option<f64>,
+    /// e.g. `years`.
+    pub onset_age_unit: String,
+    pub onset_text: String,
+
+    /// Same as the onset columns.
+    pub abatement_type: ClinicalTimeType,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub abatement_start: Option<time::OffsetDateTime>,
+    pub abatement_start_resolution: TimeResolution,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub abatement_end: Option<time::OffsetDateTime>,
+    pub abatement_end_resolution: TimeResolution,
+    pub abatement_age_low: Option<f64>,
+    pub abatement_age_high: Option<f64>,
+    pub abatement_age_unit: String,
+    pub abatement_text: String,
+
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub recorded_date: Option<time::OffsetDateTime>,
+    pub recorded_date_resolution: TimeResolution,
+}
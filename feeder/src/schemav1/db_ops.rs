@@ -1,7 +1,38 @@
-use clickhouse::Client;
+use clickhouse::{Client, Row};
+use serde::Serialize;
+
+use super::{
+    AggregatePatient, AllergyIntolerance, Condition, ConversionStatsRow, DiagnosticReport,
+    Encounter, Immunization, Location, LocationClosure, LocationEdge, MedicationAdministration,
+    MedicationRequest, MedicationStatement, Observation, Organization, Practitioner,
+    PractitionerRole, Procedure, QuarantineEntry, Resource, location_closure,
+};
 
 const MAKE_PATIENT_TABLE: &str = include_str!("sql/make_patient.sql");
+const MAKE_ENCOUNTER_TABLE: &str = include_str!("sql/make_encounter.sql");
+const MAKE_OBSERVATION_TABLE: &str = include_str!("sql/make_observation.sql");
+const MAKE_CONDITION_TABLE: &str = include_str!("sql/make_condition.sql");
+const MAKE_MEDICATION_REQUEST_TABLE: &str = include_str!("sql/make_medication_request.sql");
+const MAKE_MEDICATION_STATEMENT_TABLE: &str = include_str!("sql/make_medication_statement.sql");
+const MAKE_MEDICATION_ADMINISTRATION_TABLE: &str =
+    include_str!("sql/make_medication_administration.sql");
+const MAKE_PROCEDURE_TABLE: &str = include_str!("sql/make_procedure.sql");
+const MAKE_IMMUNIZATION_TABLE: &str = include_str!("sql/make_immunization.sql");
+const MAKE_ALLERGY_INTOLERANCE_TABLE: &str = include_str!("sql/make_allergy_intolerance.sql");
+const MAKE_DIAGNOSTIC_REPORT_TABLE: &str = include_str!("sql/make_diagnostic_report.sql");
+const MAKE_PRACTITIONER_TABLE: &str = include_str!("sql/make_practitioner.sql");
+const MAKE_PRACTITIONER_ROLE_TABLE: &str = include_str!("sql/make_practitioner_role.sql");
+const MAKE_ORGANIZATION_TABLE: &str = include_str!("sql/make_organization.sql");
+const MAKE_LOCATION_TABLE: &str = include_str!("sql/make_location.sql");
+const MAKE_LOCATION_CLOSURE_TABLE: &str = include_str!("sql/make_location_closure.sql");
+const MAKE_CONVERSION_STATS_TABLE: &str = include_str!("sql/make_conversion_stats.sql");
+const MAKE_QUARANTINE_TABLE: &str = include_str!("sql/make_quarantine.sql");
 
+/// Creates whichever tables don't exist yet. Existing tables are left as they
+/// are, so a change to their columns or engine goes into a new database
+/// instead. `attempt_1_1` still has the tables from before the patient and
+/// encounter columns were filled out and the resource tables became
+/// ReplacingMergeTrees, and the feeder's rows can't be inserted into it.
 pub async fn install_schema_v1(
     client: &Client,
     db_name: &str,
@@ -12,7 +43,244 @@ pub async fn install_schema_v1(
         .execute()
         .await?;
     client = client.with_database(db_name);
-    client.query(MAKE_PATIENT_TABLE).execute().await.unwrap();
+    client.query(MAKE_PATIENT_TABLE).execute().await?;
+    client.query(MAKE_ENCOUNTER_TABLE).execute().await?;
+    client.query(MAKE_OBSERVATION_TABLE).execute().await?;
+    client.query(MAKE_CONDITION_TABLE).execute().await?;
+    client
+        .query(MAKE_MEDICATION_REQUEST_TABLE)
+        .execute()
+        .await?;
+    client
+        .query(MAKE_MEDICATION_STATEMENT_TABLE)
+        .execute()
+        .await?;
+    client
+        .query(MAKE_MEDICATION_ADMINISTRATION_TABLE)
+        .execute()
+        .await?;
+    client.query(MAKE_PROCEDURE_TABLE).execute().await?;
+    client.query(MAKE_IMMUNIZATION_TABLE).execute().await?;
+    client
+        .query(MAKE_ALLERGY_INTOLERANCE_TABLE)
+        .execute()
+        .await?;
+    client.query(MAKE_DIAGNOSTIC_REPORT_TABLE).execute().await?;
+    client.query(MAKE_PRACTITIONER_TABLE).execute().await?;
+    client.query(MAKE_PRACTITIONER_ROLE_TABLE).execute().await?;
+    client.query(MAKE_ORGANIZATION_TABLE).execute().await?;
+    client.query(MAKE_LOCATION_TABLE).execute().await?;
+    client.query(MAKE_LOCATION_CLOSURE_TABLE).execute().await?;
+    client.query(MAKE_CONVERSION_STATS_TABLE).execute().await?;
+    client.query(MAKE_QUARANTINE_TABLE).execute().await?;
 
     Ok(())
 }
+
+/// Inserts every resource of a converted bundle into its table, one insert
+/// per table. The tables are ReplacingMergeTrees keyed on id, so inserting a
+/// bundle again, on a retry or a redelivery, only leaves duplicates until
+/// they're merged, which happens whenever clickhouse gets round to it.
+/// Readers must query with FINAL, which keeps the last row inserted for each
+/// id, or they count those duplicates. The location closure isn't touched, see
+/// [`refresh_location_closure`].
+pub async fn insert_bundle(
+    client: &Client,
+    db_name: &str,
+    resources: &[Resource],
+) -> Result<(), clickhouse::error::Error> {
+    let mut patients: Vec<&AggregatePatient> = vec![];
+    let mut encounters: Vec<&Encounter> = vec![];
+    let mut observations: Vec<&Observation> = vec![];
+    let mut conditions: Vec<&Condition> = vec![];
+    let mut medication_requests: Vec<&MedicationRequest> = vec![];
+    let mut medication_statements: Vec<&MedicationStatement> = vec![];
+    let mut medication_administrations: Vec<&MedicationAdministration> = vec![];
+    let mut procedures: Vec<&Procedure> = vec![];
+    let mut immunizations: Vec<&Immunization> = vec![];
+    let mut allergies: Vec<&AllergyIntolerance> = vec![];
+    let mut diagnostic_reports: Vec<&DiagnosticReport> = vec![];
+    let mut practitioners: Vec<&Practitioner> = vec![];
+    let mut practitioner_roles: Vec<&PractitionerRole> = vec![];
+    let mut organizations: Vec<&Organization> = vec![];
+    let mut locations: Vec<&Location> = vec![];
+    for res in resources {
+        match res {
+            Resource::Patient(patient) => patients.push(patient),
+            Resource::Encounter(encounter) => encounters.push(encounter),
+            Resource::Observation(observation) => observations.push(observation),
+            Resource::Condition(condition) => conditions.push(condition),
+            Resource::MedicationRequest(request) => medication_requests.push(request),
+            Resource::MedicationStatement(statement) => medication_statements.push(statement),
+            Resource::MedicationAdministration(administration) => {
+                medication_administrations.push(administration)
+            }
+            Resource::Procedure(procedure) => procedures.push(procedure),
+            Resource::Immunization(immunization) => immunizations.push(immunization),
+            Resource::AllergyIntolerance(allergy) => allergies.push(allergy),
+            Resource::DiagnosticReport(report) => diagnostic_reports.push(report),
+            Resource::Practitioner(practitioner) => practitioners.push(practitioner),
+            Resource::PractitionerRole(role) => practitioner_roles.push(role),
+            Resource::Organization(organization) => organizations.push(organization),
+            Resource::Location(location) => locations.push(location),
+        }
+    }
+
+    insert_rows(client, &format!("{}.AggregatePatient", db_name), &patients).await?;
+    insert_rows(client, &format!("{}.Encounter", db_name), &encounters).await?;
+    insert_rows(client, &format!("{}.Observation", db_name), &observations).await?;
+    insert_rows(client, &format!("{}.Condition", db_name), &conditions).await?;
+    insert_rows(
+        client,
+        &format!("{}.MedicationRequest", db_name),
+        &medication_requests,
+    )
+    .await?;
+    insert_rows(
+        client,
+        &format!("{}.MedicationStatement", db_name),
+        &medication_statements,
+    )
+    .await?;
+    insert_rows(
+        client,
+        &format!("{}.MedicationAdministration", db_name),
+        &medication_administrations,
+    )
+    .await?;
+    insert_rows(client, &format!("{}.Procedure", db_name), &procedures).await?;
+    insert_rows(client, &format!("{}.Immunization", db_name), &immunizations).await?;
+    insert_rows(
+        client,
+        &format!("{}.AllergyIntolerance", db_name),
+        &allergies,
+    )
+    .await?;
+    insert_rows(
+        client,
+        &format!("{}.DiagnosticReport", db_name),
+        &diagnostic_reports,
+    )
+    .await?;
+    insert_rows(client, &format!("{}.Practitioner", db_name), &practitioners).await?;
+    insert_rows(
+        client,
+        &format!("{}.PractitionerRole", db_name),
+        &practitioner_roles,
+    )
+    .await?;
+    insert_rows(client, &format!("{}.Organization", db_name), &organizations).await?;
+    insert_rows(client, &format!("{}.Location", db_name), &locations).await?;
+
+    Ok(())
+}
+
+/// Rebuilds LocationClosure from everything in the Location table, since a
+/// Location's parent may have come in an earlier bundle. The new closure is
+/// built next to the old one and swapped in, so queries never see it empty.
+/// Must not run concurrently with itself, [`crate::ingest::run`] does it
+/// every `stats_interval` when Locations were stored in the meantime.
+pub async fn refresh_location_closure(
+    client: &Client,
+    db_name: &str,
+) -> Result<(), clickhouse::error::Error> {
+    let edges: Vec<LocationEdge> = client
+        .query(&format!("SELECT ?fields FROM {}.Location FINAL", db_name))
+        .fetch_all()
+        .await?;
+    let closure = location_closure(&edges);
+    let rows: Vec<&LocationClosure> = closure.iter().collect();
+
+    client
+        .query(&format!(
+            "CREATE TABLE IF NOT EXISTS {0}.LocationClosureNext AS {0}.LocationClosure",
+            db_name
+        ))
+        .execute()
+        .await?;
+    client
+        .query(&format!("TRUNCATE TABLE {}.LocationClosureNext", db_name))
+        .execute()
+        .await?;
+    insert_rows(client, &format!("{}.LocationClosureNext", db_name), &rows).await?;
+    client
+        .query(&format!(
+            "EXCHANGE TABLES {0}.LocationClosure AND {0}.LocationClosureNext",
+            db_name
+        ))
+        .execute()
+        .await
+}
+
+pub async fn insert_conversion_stats(
+    client: &Client,
+    db_name: &str,
+    rows: &[ConversionStatsRow],
+) -> Result<(), clickhouse::error::Error> {
+    let rows: Vec<&ConversionStatsRow> = rows.iter().collect();
+    insert_rows(client, &format!("{}.ConversionStats", db_name), &rows).await
+}
+
+pub async fn insert_quarantine(
+    client: &Client,
+    db_name: &str,
+    entries: &[QuarantineEntry],
+) -> Result<(), clickhouse::error::Error> {
+    let entries: Vec<&QuarantineEntry> = entries.iter().collect();
+    insert_rows(client, &format!("{}.Quarantine", db_name), &entries).await
+}
+
+/// Everything in the quarantine table, oldest first.
+pub async fn fetch_quarantine(
+    client: &Client,
+    db_name: &str,
+) -> Result<Vec<QuarantineEntry>, clickhouse::error::Error> {
+    client
+        .query(&format!(
+            "SELECT ?fields FROM {}.Quarantine ORDER BY time",
+            db_name
+        ))
+        .fetch_all()
+        .await
+}
+
+/// Removes a quarantined item, along with any copies of it quarantined when
+/// its message was delivered again.
+pub async fn delete_quarantined(
+    client: &Client,
+    db_name: &str,
+    entry: &QuarantineEntry,
+) -> Result<(), clickhouse::error::Error> {
+    client
+        .query(&format!(
+            "DELETE FROM {}.Quarantine WHERE kafka_topic = ? AND kafka_partition = ? \
+             AND kafka_offset = ? AND resource_type = ? AND resource_id = ? AND reason = ?",
+            db_name
+        ))
+        .bind(&entry.kafka_topic)
+        .bind(entry.kafka_partition)
+        .bind(entry.kafka_offset)
+        .bind(&entry.resource_type)
+        .bind(&entry.resource_id)
+        .bind(&entry.reason)
+        .execute()
+        .await
+}
+
+async fn insert_rows<T>(
+    client: &Client,
+    table: &str,
+    rows: &[&T],
+) -> Result<(), clickhouse::error::Error>
+where
+    T: Row + Serialize,
+{
+    if rows.is_empty() {
+        return Ok(());
+    }
+    let mut insert = client.insert::<T>(table)?;
+    for row in rows {
+        insert.write(row).await?;
+    }
+    insert.end().await
+}
@@ -0,0 +1,70 @@
+use clickhouse::Row;
+use serde::Serialize;
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use super::TimeResolution;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum DiagnosticReportStatus {
+    Registered = 0,
+    Partial = 1,
+    Preliminary = 2,
+    Final = 3,
+    Amended = 4,
+    Corrected = 5,
+    Appended = 6,
+    Cancelled = 7,
+    EnteredInError = 8,
+    Unknown = 9,
+}
+
+#[derive(Debug, Row, Serialize)]
+pub struct DiagnosticReport {
+    pub id: String,
+    pub status: DiagnosticReportStatus,
+    /// First code of each DiagnosticReport.category, e.g. `LAB`.
+    pub category: Vec<String>,
+    pub code: String,
+    pub code_description: String,
+    pub code_system: String,
+    /// Patient id, empty when the subject is a Group, Device or Location.
+    pub subject: String,
+    /// Encounter id
+    pub encounter: String,
+    /// effectiveDateTime only has a start.
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub effective_start: Option<time::OffsetDateTime>,
+    pub effective_start_resolution: TimeResolution,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub effective_end: Option<time::OffsetDateTime>,
+    pub effective_end_resolution: TimeResolution,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub issued: Option<time::OffsetDateTime>,
+
+    /// Practitioner, PractitionerRole, Organization or CareTeam. Performers
+    /// we can't resolve to an id are left out.
+    #[serde(rename = "performers.type")]
+    pub performers_type: Vec<String>,
+    #[serde(rename = "performers.id")]
+    pub performers_id: Vec<String>,
+    /// Observation ids, in the order the report lists them.
+    pub results: Vec<String>,
+
+    /// One per presentedForm. The attachment's data isn't kept.
+    #[serde(rename = "presented_forms.content_type")]
+    pub presented_forms_content_type: Vec<String>,
+    #[serde(rename = "presented_forms.language")]
+    pub presented_forms_language: Vec<String>,
+    #[serde(rename = "presented_forms.title")]
+    pub presented_forms_title: Vec<String>,
+    #[serde(rename = "presented_forms.url")]
+    pub presented_forms_url: Vec<String>,
+    /// Size in bytes, taken from the data if the attachment has no size.
+    #[serde(rename = "presented_forms.size")]
+    pub presented_forms_size: Vec<Option<u32>>,
+    /// SHA-1 of the data, hex encoded, computed from the data if the
+    /// attachment has no hash.
+    #[serde(rename = "presented_forms.hash")]
+    pub presented_forms_hash: Vec<String>,
+}
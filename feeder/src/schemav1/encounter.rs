@@ -2,6 +2,8 @@ use clickhouse::Row;
 use serde::Serialize;
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
+use super::TimeResolution;
+
 #[derive(Debug, Serialize_repr, Deserialize_repr)]
 #[repr(u8)]
 pub enum EncounterStatus {
@@ -16,6 +18,16 @@ pub enum EncounterStatus {
     Unknown = 8,
 }
 
+#[derive(Debug, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum EncounterLocationStatus {
+    Unknown = 0,
+    Planned = 1,
+    Active = 2,
+    Reserved = 3,
+    Completed = 4,
+}
+
 #[derive(Debug, Row, Serialize)]
 pub struct Encounter {
     pub id: String,
@@ -23,17 +35,90 @@ pub struct Encounter {
     /// Patient id
     pub subject: String,
 
-    #[serde(with = "clickhouse::serde::time::datetime")]
-    pub period_start: time::OffsetDateTime,
-    #[serde(with = "clickhouse::serde::time::datetime")]
-    pub period_end: time::OffsetDateTime,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub period_start: Option<time::OffsetDateTime>,
+    pub period_start_resolution: TimeResolution,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub period_end: Option<time::OffsetDateTime>,
+    pub period_end_resolution: TimeResolution,
 
     pub class_code: String,
     pub class_description: String,
     pub class_system: String,
 
-    // pub type_code: String,
-    // pub type_description: String,
-    // pub type_system: String,
+    /// First coding of each Encounter.type.
+    #[serde(rename = "types.code")]
+    pub types_code: Vec<String>,
+    #[serde(rename = "types.description")]
+    pub types_description: Vec<String>,
+    #[serde(rename = "types.system")]
+    pub types_system: Vec<String>,
+
+    /// First coding of each Encounter.reasonCode.
+    #[serde(rename = "reasons.code")]
+    pub reasons_code: Vec<String>,
+    #[serde(rename = "reasons.description")]
+    pub reasons_description: Vec<String>,
+    #[serde(rename = "reasons.system")]
+    pub reasons_system: Vec<String>,
+
+    /// Condition id
+    #[serde(rename = "diagnoses.condition")]
+    pub diagnoses_condition: Vec<String>,
+    /// First code of EncounterDiagnosis.use, e.g. `AD` or `DD`.
+    #[serde(rename = "diagnoses.use")]
+    pub diagnoses_use: Vec<String>,
+    #[serde(rename = "diagnoses.rank")]
+    pub diagnoses_rank: Vec<Option<u32>>,
+
+    /// First codes of the Encounter.hospitalization elements.
+    pub admit_source: String,
+    pub re_admission: String,
+    pub discharge_disposition: String,
+
+    /// First code of the participant's types, e.g. `ATND`.
+    #[serde(rename = "participants.type")]
+    pub participants_type: Vec<String>,
+    /// Practitioner, PractitionerRole or RelatedPerson.
+    #[serde(rename = "participants.individual_type")]
+    pub participants_individual_type: Vec<String>,
+    #[serde(rename = "participants.individual")]
+    pub participants_individual: Vec<String>,
+    #[serde(
+        rename = "participants.period_start",
+        with = "super::serde_helpers::datetime_option_vec"
+    )]
+    pub participants_period_start: Vec<Option<time::OffsetDateTime>>,
+    #[serde(rename = "participants.period_start_resolution")]
+    pub participants_period_start_resolution: Vec<TimeResolution>,
+    #[serde(
+        rename = "participants.period_end",
+        with = "super::serde_helpers::datetime_option_vec"
+    )]
+    pub participants_period_end: Vec<Option<time::OffsetDateTime>>,
+    #[serde(rename = "participants.period_end_resolution")]
+    pub participants_period_end_resolution: Vec<TimeResolution>,
+
+    /// Location id
+    #[serde(rename = "locations.location")]
+    pub locations_location: Vec<String>,
+    #[serde(rename = "locations.status")]
+    pub locations_status: Vec<EncounterLocationStatus>,
+    #[serde(
+        rename = "locations.period_start",
+        with = "super::serde_helpers::datetime_option_vec"
+    )]
+    pub locations_period_start: Vec<Option<time::OffsetDateTime>>,
+    #[serde(rename = "locations.period_start_resolution")]
+    pub locations_period_start_resolution: Vec<TimeResolution>,
+    #[serde(
+        rename = "locations.period_end",
+        with = "super::serde_helpers::datetime_option_vec"
+    )]
+    pub locations_period_end: Vec<Option<time::OffsetDateTime>>,
+    #[serde(rename = "locations.period_end_resolution")]
+    pub locations_period_end_resolution: Vec<TimeResolution>,
 
+    /// Organization id
+    pub service_provider: String,
 }
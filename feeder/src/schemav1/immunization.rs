@@ -0,0 +1,32 @@
+use clickhouse::Row;
+use serde::Serialize;
+
+use super::TimeResolution;
+
+#[derive(Debug, Row, Serialize)]
+pub struct Immunization {
+    pub id: String,
+    /// `completed`, `entered-in-error` or `not-done`.
+    pub status: String,
+    /// First code of Immunization.statusReason, e.g. `PATOBJ`.
+    pub status_reason: String,
+    /// The CVX coding of the vaccine if it has one, otherwise its first
+    /// coding with a code.
+    pub vaccine_code: String,
+    pub vaccine_description: String,
+    pub vaccine_system: String,
+    /// Patient id, from Immunization.patient.
+    pub subject: String,
+    /// Encounter id
+    pub encounter: String,
+    /// occurrenceDateTime, an occurrenceString goes in `occurrence_text`.
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub occurrence: Option<time::OffsetDateTime>,
+    pub occurrence_resolution: TimeResolution,
+    pub occurrence_text: String,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub recorded: Option<time::OffsetDateTime>,
+    pub recorded_resolution: TimeResolution,
+    pub primary_source: Option<bool>,
+    pub lot_number: String,
+}
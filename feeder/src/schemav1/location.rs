@@ -0,0 +1,141 @@
+use std::collections::{HashMap, HashSet};
+
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Row, Serialize)]
+pub struct Location {
+    pub id: String,
+    /// `active`, `suspended` or `inactive`, empty if not given.
+    pub status: String,
+    pub name: String,
+    pub aliases: Vec<String>,
+    /// `instance` or `kind`.
+    pub mode: String,
+    /// First code of each Location.type, e.g. `HOSP` or `ER`.
+    pub types: Vec<String>,
+    /// First code of Location.physicalType, e.g. `bd` for a bed or `wa` for
+    /// a ward.
+    pub physical_type: String,
+    pub address_line: String,
+    pub address_city: String,
+    pub address_state: String,
+    pub address_postal_code: String,
+    pub address_country: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Organization id
+    pub managing_organization: String,
+    /// Id of the Location this one is part of, e.g. the room of a bed.
+    pub part_of: String,
+}
+
+/// The `partOf` link of a Location, as read back from its table.
+#[derive(Debug, Clone, Row, Deserialize)]
+pub struct LocationEdge {
+    pub id: String,
+    pub part_of: String,
+}
+
+/// One row per Location and each of the Locations above it, including
+/// itself at depth 0. Everything under a facility is then
+/// `SELECT descendant FROM LocationClosure WHERE ancestor = ?`, which joins to
+/// `Encounter.locations.location`.
+#[derive(Debug, Clone, PartialEq, Eq, Row, Serialize)]
+pub struct LocationClosure {
+    pub ancestor: String,
+    pub descendant: String,
+    pub depth: u32,
+}
+
+/// Walks up from every Location to the top of its hierarchy. A Location
+/// stored twice with different parents gets both, and a cycle is only
+/// followed until it gets back to a Location already seen.
+pub fn location_closure(edges: &[LocationEdge]) -> Vec<LocationClosure> {
+    let mut parents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for edge in edges {
+        let edge_parents = parents.entry(edge.id.as_str()).or_default();
+        if !edge.part_of.is_empty() && !edge_parents.contains(&edge.part_of.as_str()) {
+            edge_parents.push(edge.part_of.as_str());
+        }
+    }
+
+    let mut ids: Vec<&str> = parents.keys().copied().collect();
+    ids.sort_unstable();
+    let mut closure = vec![];
+    for id in ids {
+        let mut seen = HashSet::from([id]);
+        let mut level = vec![id];
+        let mut depth = 0;
+        while !level.is_empty() {
+            let mut next = vec![];
+            for ancestor in level {
+                closure.push(LocationClosure {
+                    ancestor: ancestor.to_string(),
+                    descendant: id.to_string(),
+                    depth,
+                });
+                for parent in parents.get(ancestor).into_iter().flatten() {
+                    if seen.insert(parent) {
+                        next.push(*parent);
+                    }
+                }
+            }
+            level = next;
+            depth += 1;
+        }
+    }
+    closure
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(id: &str, part_of: &str) -> LocationEdge {
+        LocationEdge {
+            id: id.to_string(),
+            part_of: part_of.to_string(),
+        }
+    }
+
+    fn pairs(closure: &[LocationClosure], descendant: &str) -> Vec<(String, u32)> {
+        closure
+            .iter()
+            .filter(|row| row.descendant == descendant)
+            .map(|row| (row.ancestor.clone(), row.depth))
+            .collect()
+    }
+
+    #[test]
+    fn test_closure_of_chain() {
+        let closure = location_closure(&[
+            edge("bed", "room"),
+            edge("room", "ward"),
+            edge("ward", "building"),
+            edge("building", "site"),
+            edge("site", ""),
+        ]);
+        assert_eq!(
+            pairs(&closure, "bed"),
+            vec![
+                ("bed".to_string(), 0),
+                ("room".to_string(), 1),
+                ("ward".to_string(), 2),
+                ("building".to_string(), 3),
+                ("site".to_string(), 4),
+            ]
+        );
+        assert_eq!(pairs(&closure, "site"), vec![("site".to_string(), 0)]);
+        assert_eq!(closure.len(), 5 + 4 + 3 + 2 + 1);
+    }
+
+    #[test]
+    fn test_closure_stops_at_cycles() {
+        let closure = location_closure(&[edge("a", "b"), edge("b", "a")]);
+        assert_eq!(
+            pairs(&closure, "a"),
+            vec![("a".to_string(), 0), ("b".to_string(), 1)]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use clickhouse::Row;
+use serde::Serialize;
+
+use super::TimeResolution;
+
+// Medications themselves don't get a table, every row below carries the code
+// of the Medication it points at instead.
+
+#[derive(Debug, Row, Serialize)]
+pub struct MedicationRequest {
+    pub id: String,
+    /// e.g. `active` or `stopped`.
+    pub status: String,
+    /// e.g. `order` or `plan`.
+    pub intent: String,
+    /// First code of each MedicationRequest.category.
+    pub category: Vec<String>,
+    /// Id of the Medication, empty for a medicationCodeableConcept.
+    pub medication: String,
+    /// The RxNorm coding of the medication if it has one, otherwise its first
+    /// coding with a code.
+    pub medication_code: String,
+    pub medication_description: String,
+    pub medication_system: String,
+    /// Patient id, empty when the subject is a Group.
+    pub subject: String,
+    /// Encounter id
+    pub encounter: String,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub authored_on: Option<time::OffsetDateTime>,
+    pub authored_on_resolution: TimeResolution,
+    /// Practitioner, PractitionerRole, Organization, Patient, RelatedPerson
+    /// or Device.
+    pub requester_type: String,
+    pub requester: String,
+
+    /// One per dosageInstruction.
+    #[serde(rename = "dosages.sequence")]
+    pub dosages_sequence: Vec<Option<i32>>,
+    #[serde(rename = "dosages.text")]
+    pub dosages_text: Vec<String>,
+    /// First code of Dosage.route, e.g. `26643006` for oral.
+    #[serde(rename = "dosages.route")]
+    pub dosages_route: Vec<String>,
+    /// doseQuantity, a doseRange goes in `dose_low` and `dose_high`.
+    #[serde(rename = "dosages.dose_value")]
+    pub dosages_dose_value: Vec<Option<f64>>,
+    #[serde(rename = "dosages.dose_low")]
+    pub dosages_dose_low: Vec<Option<f64>>,
+    #[serde(rename = "dosages.dose_high")]
+    pub dosages_dose_high: Vec<Option<f64>>,
+    #[serde(rename = "dosages.dose_unit")]
+    pub dosages_dose_unit: Vec<String>,
+    /// Times per `period`, from Dosage.timing.repeat.
+    #[serde(rename = "dosages.frequency")]
+    pub dosages_frequency: Vec<Option<u32>>,
+    #[serde(rename = "dosages.period")]
+    pub dosages_period: Vec<Option<f64>>,
+    /// e.g. `d` for days.
+    #[serde(rename = "dosages.period_unit")]
+    pub dosages_period_unit: Vec<String>,
+    /// First code of Dosage.timing.code, e.g. `BID`.
+    #[serde(rename = "dosages.timing_code")]
+    pub dosages_timing_code: Vec<String>,
+}
+
+#[derive(Debug, Row, Serialize)]
+pub struct MedicationStatement {
+    pub id: String,
+    /// e.g. `active` or `completed`.
+    pub status: String,
+    /// First code of MedicationStatement.category.
+    pub category: String,
+    /// Same as in [`MedicationRequest`].
+    pub medication: String,
+    pub medication_code: String,
+    pub medication_description: String,
+    pub medication_system: String,
+    /// Patient id, empty when the subject is a Group.
+    pub subject: String,
+    /// Encounter id, from MedicationStatement.context.
+    pub encounter: String,
+    /// effectiveDateTime only has a start.
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub effective_start: Option<time::OffsetDateTime>,
+    pub effective_start_resolution: TimeResolution,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub effective_end: Option<time::OffsetDateTime>,
+    pub effective_end_resolution: TimeResolution,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub date_asserted: Option<time::OffsetDateTime>,
+    pub date_asserted_resolution: TimeResolution,
+
+    /// Same as in [`MedicationRequest`], one per dosage.
+    #[serde(rename = "dosages.sequence")]
+    pub dosages_sequence: Vec<Option<i32>>,
+    #[serde(rename = "dosages.text")]
+    pub dosages_text: Vec<String>,
+    #[serde(rename = "dosages.route")]
+    pub dosages_route: Vec<String>,
+    #[serde(rename = "dosages.dose_value")]
+    pub dosages_dose_value: Vec<Option<f64>>,
+    #[serde(rename = "dosages.dose_low")]
+    pub dosages_dose_low: Vec<Option<f64>>,
+    #[serde(rename = "dosages.dose_high")]
+    pub dosages_dose_high: Vec<Option<f64>>,
+    #[serde(rename = "dosages.dose_unit")]
+    pub dosages_dose_unit: Vec<String>,
+    #[serde(rename = "dosages.frequency")]
+    pub dosages_frequency: Vec<Option<u32>>,
+    #[serde(rename = "dosages.period")]
+    pub dosages_period: Vec<Option<f64>>,
+    #[serde(rename = "dosages.period_unit")]
+    pub dosages_period_unit: Vec<String>,
+    #[serde(rename = "dosages.timing_code")]
+    pub dosages_timing_code: Vec<String>,
+}
+
+#[derive(Debug, Row, Serialize)]
+pub struct MedicationAdministration {
+    pub id: String,
+    /// e.g. `completed` or `not-done`.
+    pub status: String,
+    /// First code of MedicationAdministration.category.
+    pub category: String,
+    /// Same as in [`MedicationRequest`].
+    pub medication: String,
+    pub medication_code: String,
+    pub medication_description: String,
+    pub medication_system: String,
+    /// Patient id, empty when the subject is a Group.
+    pub subject: String,
+    /// Encounter id, from MedicationAdministration.context.
+    pub encounter: String,
+    /// effectiveDateTime only has a start.
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub effective_start: Option<time::OffsetDateTime>,
+    pub effective_start_resolution: TimeResolution,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub effective_end: Option<time::OffsetDateTime>,
+    pub effective_end_resolution: TimeResolution,
+    /// MedicationRequest id
+    pub request: String,
+
+    pub dosage_text: String,
+    /// First code of MedicationAdministration.dosage.route.
+    pub route: String,
+    pub dose_value: Option<f64>,
+    pub dose_unit: String,
+}
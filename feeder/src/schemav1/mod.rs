@@ -1,14 +1,96 @@
 mod patient;
 mod encounter;
+mod observation;
+mod condition;
+mod medication;
+mod procedure;
+mod immunization;
+mod allergy;
+mod diagnostic_report;
+mod provider;
+mod location;
+mod quarantine;
+mod serde_helpers;
+mod stats;
 pub mod db_ops;
 
 pub use patient::*;
 pub use encounter::*;
+pub use observation::*;
+pub use condition::*;
+pub use medication::*;
+pub use procedure::*;
+pub use immunization::*;
+pub use allergy::*;
+pub use diagnostic_report::*;
+pub use provider::*;
+pub use location::*;
+pub use quarantine::*;
+pub use stats::*;
 
 
+// Only a bundle's worth of rows is around at a time, boxing them isn't worth
+// the noise.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum Resource {
     Patient(AggregatePatient),
     Encounter(Encounter),
+    Observation(Observation),
+    Condition(Condition),
+    MedicationRequest(MedicationRequest),
+    MedicationStatement(MedicationStatement),
+    MedicationAdministration(MedicationAdministration),
+    Procedure(Procedure),
+    Immunization(Immunization),
+    AllergyIntolerance(AllergyIntolerance),
+    DiagnosticReport(DiagnosticReport),
+    Practitioner(Practitioner),
+    PractitionerRole(PractitionerRole),
+    Organization(Organization),
+    Location(Location),
+}
+
+impl Resource {
+    pub fn id(&self) -> &str {
+        match self {
+            Resource::Patient(patient) => &patient.id,
+            Resource::Encounter(encounter) => &encounter.id,
+            Resource::Observation(observation) => &observation.id,
+            Resource::Condition(condition) => &condition.id,
+            Resource::MedicationRequest(request) => &request.id,
+            Resource::MedicationStatement(statement) => &statement.id,
+            Resource::MedicationAdministration(administration) => &administration.id,
+            Resource::Procedure(procedure) => &procedure.id,
+            Resource::Immunization(immunization) => &immunization.id,
+            Resource::AllergyIntolerance(allergy) => &allergy.id,
+            Resource::DiagnosticReport(report) => &report.id,
+            Resource::Practitioner(practitioner) => &practitioner.id,
+            Resource::PractitionerRole(role) => &role.id,
+            Resource::Organization(organization) => &organization.id,
+            Resource::Location(location) => &location.id,
+        }
+    }
+
+    /// The FHIR resource type the row was made from.
+    pub fn resource_type(&self) -> &'static str {
+        match self {
+            Resource::Patient(_) => "Patient",
+            Resource::Encounter(_) => "Encounter",
+            Resource::Observation(_) => "Observation",
+            Resource::Condition(_) => "Condition",
+            Resource::MedicationRequest(_) => "MedicationRequest",
+            Resource::MedicationStatement(_) => "MedicationStatement",
+            Resource::MedicationAdministration(_) => "MedicationAdministration",
+            Resource::Procedure(_) => "Procedure",
+            Resource::Immunization(_) => "Immunization",
+            Resource::AllergyIntolerance(_) => "AllergyIntolerance",
+            Resource::DiagnosticReport(_) => "DiagnosticReport",
+            Resource::Practitioner(_) => "Practitioner",
+            Resource::PractitionerRole(_) => "PractitionerRole",
+            Resource::Organization(_) => "Organization",
+            Resource::Location(_) => "Location",
+        }
+    }
 }
 
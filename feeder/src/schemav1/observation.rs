@@ -0,0 +1,141 @@
+use clickhouse::Row;
+use serde::Serialize;
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use super::TimeResolution;
+
+#[derive(Debug, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum ObservationStatus {
+    Registered = 0,
+    Preliminary = 1,
+    Final = 2,
+    Amended = 3,
+    Corrected = 4,
+    Cancelled = 5,
+    EnteredInError = 6,
+    Unknown = 7,
+}
+
+/// Which of the `value_*` columns hold the value[x].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum ValueType {
+    /// No value, see `data_absent_reason`.
+    None = 0,
+    Quantity = 1,
+    CodeableConcept = 2,
+    String = 3,
+    Boolean = 4,
+    Integer = 5,
+    Range = 6,
+    Ratio = 7,
+    Period = 8,
+    /// A type we don't have columns for, e.g. SampledData.
+    Other = 9,
+}
+
+#[derive(Debug, Row, Serialize)]
+pub struct Observation {
+    pub id: String,
+    pub status: ObservationStatus,
+    pub code: String,
+    pub code_description: String,
+    pub code_system: String,
+    /// First code of each Observation.category, e.g. `vital-signs`.
+    pub category: Vec<String>,
+    /// Patient id, empty when the subject is a Group, Device or Location.
+    pub subject: String,
+    /// Encounter id
+    pub encounter: String,
+
+    /// effectiveDateTime and effectiveInstant only have a start.
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub effective_start: Option<time::OffsetDateTime>,
+    pub effective_start_resolution: TimeResolution,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub effective_end: Option<time::OffsetDateTime>,
+    pub effective_end_resolution: TimeResolution,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub issued: Option<time::OffsetDateTime>,
+
+    pub value_type: ValueType,
+    pub value_quantity: Option<f64>,
+    /// Quantity comparator, e.g. `<`.
+    pub value_comparator: String,
+    /// Unit of the quantity, or of a range's low or a ratio's numerator.
+    pub value_unit: String,
+    pub value_code: String,
+    pub value_code_description: String,
+    pub value_code_system: String,
+    pub value_string: String,
+    pub value_boolean: Option<bool>,
+    pub value_integer: Option<i32>,
+    pub value_low: Option<f64>,
+    pub value_high: Option<f64>,
+    pub value_numerator: Option<f64>,
+    pub value_denominator: Option<f64>,
+    pub value_denominator_unit: String,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub value_period_start: Option<time::OffsetDateTime>,
+    pub value_period_start_resolution: TimeResolution,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub value_period_end: Option<time::OffsetDateTime>,
+    pub value_period_end_resolution: TimeResolution,
+    /// First code of Observation.dataAbsentReason.
+    pub data_absent_reason: String,
+
+    /// Same columns as the value above, one entry per component.
+    #[serde(rename = "components.code")]
+    pub components_code: Vec<String>,
+    #[serde(rename = "components.code_description")]
+    pub components_code_description: Vec<String>,
+    #[serde(rename = "components.code_system")]
+    pub components_code_system: Vec<String>,
+    #[serde(rename = "components.value_type")]
+    pub components_value_type: Vec<ValueType>,
+    #[serde(rename = "components.value_quantity")]
+    pub components_value_quantity: Vec<Option<f64>>,
+    #[serde(rename = "components.value_comparator")]
+    pub components_value_comparator: Vec<String>,
+    #[serde(rename = "components.value_unit")]
+    pub components_value_unit: Vec<String>,
+    #[serde(rename = "components.value_code")]
+    pub components_value_code: Vec<String>,
+    #[serde(rename = "components.value_code_description")]
+    pub components_value_code_description: Vec<String>,
+    #[serde(rename = "components.value_code_system")]
+    pub components_value_code_system: Vec<String>,
+    #[serde(rename = "components.value_string")]
+    pub components_value_string: Vec<String>,
+    #[serde(rename = "components.value_boolean")]
+    pub components_value_boolean: Vec<Option<bool>>,
+    #[serde(rename = "components.value_integer")]
+    pub components_value_integer: Vec<Option<i32>>,
+    #[serde(rename = "components.value_low")]
+    pub components_value_low: Vec<Option<f64>>,
+    #[serde(rename = "components.value_high")]
+    pub components_value_high: Vec<Option<f64>>,
+    #[serde(rename = "components.value_numerator")]
+    pub components_value_numerator: Vec<Option<f64>>,
+    #[serde(rename = "components.value_denominator")]
+    pub components_value_denominator: Vec<Option<f64>>,
+    #[serde(rename = "components.value_denominator_unit")]
+    pub components_value_denominator_unit: Vec<String>,
+    #[serde(
+        rename = "components.value_period_start",
+        with = "super::serde_helpers::datetime_option_vec"
+    )]
+    pub components_value_period_start: Vec<Option<time::OffsetDateTime>>,
+    #[serde(rename = "components.value_period_start_resolution")]
+    pub components_value_period_start_resolution: Vec<TimeResolution>,
+    #[serde(
+        rename = "components.value_period_end",
+        with = "super::serde_helpers::datetime_option_vec"
+    )]
+    pub components_value_period_end: Vec<Option<time::OffsetDateTime>>,
+    #[serde(rename = "components.value_period_end_resolution")]
+    pub components_value_period_end_resolution: Vec<TimeResolution>,
+    #[serde(rename = "components.data_absent_reason")]
+    pub components_data_absent_reason: Vec<String>,
+}
@@ -4,11 +4,17 @@ use serde_repr::{Deserialize_repr, Serialize_repr};
 
 // TODO: Implement unwrap for the clickhouse library
 
-#[derive(Debug)]
+/// How much of a date or time the source actually gave us. Anything finer
+/// was filled in from the facility profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TimeResolution {
+    /// There's no time at all.
+    Unknown,
     Year,
     Month,
     Day,
+    /// A full timestamp.
+    Second,
 }
 
 impl Serialize for TimeResolution {
@@ -16,9 +22,11 @@ impl Serialize for TimeResolution {
     where
         S: serde::Serializer {
         serializer.serialize_str(match self {
+            TimeResolution::Unknown => "unknown",
             TimeResolution::Year => "year",
             TimeResolution::Month => "month",
             TimeResolution::Day => "day",
+            TimeResolution::Second => "second",
         })
     }
 }
@@ -51,18 +59,145 @@ pub enum AddressType {
     Both = 3,
 }
 
+#[derive(Debug, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum NameUse {
+    Unknown = 0,
+    Usual = 1,
+    Official = 2,
+    Temp = 3,
+    Nickname = 4,
+    Anonymous = 5,
+    Old = 6,
+    Maiden = 7,
+}
+
+#[derive(Debug, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum Gender {
+    Unknown = 0,
+    Male = 1,
+    Female = 2,
+    Other = 3,
+}
+
+#[derive(Debug, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum IdentifierUse {
+    Unknown = 0,
+    Usual = 1,
+    Official = 2,
+    Temp = 3,
+    Secondary = 4,
+    Old = 5,
+}
+
+#[derive(Debug, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum ContactPointSystem {
+    Unknown = 0,
+    Phone = 1,
+    Fax = 2,
+    Email = 3,
+    Pager = 4,
+    Url = 5,
+    Sms = 6,
+    Other = 7,
+}
+
+#[derive(Debug, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum ContactPointUse {
+    Unknown = 0,
+    Home = 1,
+    Work = 2,
+    Temp = 3,
+    Old = 4,
+    Mobile = 5,
+}
+
 #[derive(Debug, Row, Serialize)]
 pub struct AggregatePatient {
     pub id: String,
+    /// The display name, see `names` for all of them.
     pub name_given: String,
     pub name_family: String,
-    #[serde(with = "clickhouse::serde::time::date32")]
-    pub birth_time: time::Date,
+    #[serde(rename = "names.use")]
+    pub names_use: Vec<NameUse>,
+    #[serde(rename = "names.family")]
+    pub names_family: Vec<String>,
+    #[serde(rename = "names.given")]
+    pub names_given: Vec<String>,
+    #[serde(rename = "names.prefix")]
+    pub names_prefix: Vec<String>,
+    #[serde(rename = "names.suffix")]
+    pub names_suffix: Vec<String>,
+    #[serde(
+        rename = "names.period_start",
+        with = "super::serde_helpers::datetime_option_vec"
+    )]
+    pub names_period_start: Vec<Option<time::OffsetDateTime>>,
+    #[serde(rename = "names.period_start_resolution")]
+    pub names_period_start_resolution: Vec<TimeResolution>,
+    #[serde(
+        rename = "names.period_end",
+        with = "super::serde_helpers::datetime_option_vec"
+    )]
+    pub names_period_end: Vec<Option<time::OffsetDateTime>>,
+    #[serde(rename = "names.period_end_resolution")]
+    pub names_period_end_resolution: Vec<TimeResolution>,
+    #[serde(with = "clickhouse::serde::time::date32::option")]
+    pub birth_time: Option<time::Date>,
     pub birth_time_resolution: TimeResolution,
 
+    /// From the patient-birthTime extension, when the source has one.
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub birth_datetime: Option<time::OffsetDateTime>,
+    /// US Core birth sex code, e.g. `F`.
+    pub birth_sex: String,
+    /// US Core race and ethnicity codes.
+    pub race_omb: Vec<String>,
+    pub race_detailed: Vec<String>,
+    pub race_text: String,
+    pub ethnicity_omb: Vec<String>,
+    pub ethnicity_detailed: Vec<String>,
+    pub ethnicity_text: String,
+
     #[serde(with = "clickhouse::serde::time::datetime::option")]
     pub death_time: Option<time::OffsetDateTime>,
+    pub death_time_resolution: TimeResolution,
     pub deceased: Deceased,
+    pub gender: Gender,
+    pub active: Option<bool>,
+    /// First code of Patient.maritalStatus, e.g. `M`.
+    pub marital_status: String,
+    /// Id of the managing Organization.
+    pub managing_organization: String,
+
+    #[serde(rename = "identifiers.use")]
+    pub identifiers_use: Vec<IdentifierUse>,
+    /// First code of Identifier.type, e.g. `MR` or `SS`.
+    #[serde(rename = "identifiers.type")]
+    pub identifiers_type: Vec<String>,
+    #[serde(rename = "identifiers.system")]
+    pub identifiers_system: Vec<String>,
+    #[serde(rename = "identifiers.value")]
+    pub identifiers_value: Vec<String>,
+
+    #[serde(rename = "telecom.system")]
+    pub telecom_system: Vec<ContactPointSystem>,
+    #[serde(rename = "telecom.use")]
+    pub telecom_use: Vec<ContactPointUse>,
+    #[serde(rename = "telecom.value")]
+    pub telecom_value: Vec<String>,
+    #[serde(rename = "telecom.rank")]
+    pub telecom_rank: Vec<Option<u32>>,
+
+    /// First code of each Patient.communication.language, e.g. `en-US`.
+    #[serde(rename = "communication.language")]
+    pub communication_language: Vec<String>,
+    #[serde(rename = "communication.preferred")]
+    pub communication_preferred: Vec<bool>,
 
     #[serde(rename = "addresses.use")]
     pub addresses_use: Vec<AddressUse>,
@@ -72,4 +207,34 @@ pub struct AggregatePatient {
     pub addresses_city: Vec<String>,
     #[serde(rename = "addresses.line")]
     pub addresses_line: Vec<String>,
+    #[serde(rename = "addresses.district")]
+    pub addresses_district: Vec<String>,
+    #[serde(rename = "addresses.state")]
+    pub addresses_state: Vec<String>,
+    #[serde(rename = "addresses.postal_code")]
+    pub addresses_postal_code: Vec<String>,
+    #[serde(rename = "addresses.country")]
+    pub addresses_country: Vec<String>,
+    #[serde(rename = "addresses.text")]
+    pub addresses_text: Vec<String>,
+    #[serde(
+        rename = "addresses.period_start",
+        with = "super::serde_helpers::datetime_option_vec"
+    )]
+    pub addresses_period_start: Vec<Option<time::OffsetDateTime>>,
+    #[serde(rename = "addresses.period_start_resolution")]
+    pub addresses_period_start_resolution: Vec<TimeResolution>,
+    #[serde(
+        rename = "addresses.period_end",
+        with = "super::serde_helpers::datetime_option_vec"
+    )]
+    pub addresses_period_end: Vec<Option<time::OffsetDateTime>>,
+    #[serde(rename = "addresses.period_end_resolution")]
+    pub addresses_period_end_resolution: Vec<TimeResolution>,
+
+    /// Local extensions stored by [`keep_as_text`](crate::fhir_r4b_shemav1::keep_as_text).
+    #[serde(rename = "extensions.url")]
+    pub extensions_url: Vec<String>,
+    #[serde(rename = "extensions.value")]
+    pub extensions_value: Vec<String>,
 }
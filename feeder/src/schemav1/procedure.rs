@@ -0,0 +1,58 @@
+use clickhouse::Row;
+use serde::Serialize;
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use super::{ClinicalTimeType, TimeResolution};
+
+#[derive(Debug, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum ProcedureStatus {
+    Preparation = 0,
+    InProgress = 1,
+    NotDone = 2,
+    OnHold = 3,
+    Stopped = 4,
+    Completed = 5,
+    EnteredInError = 6,
+    Unknown = 7,
+}
+
+#[derive(Debug, Row, Serialize)]
+pub struct Procedure {
+    pub id: String,
+    pub status: ProcedureStatus,
+    /// First code of Procedure.statusReason.
+    pub status_reason: String,
+    /// First code of Procedure.category.
+    pub category: String,
+    pub code: String,
+    pub code_description: String,
+    pub code_system: String,
+    /// Patient id, empty when the subject is a Group.
+    pub subject: String,
+    /// Encounter id
+    pub encounter: String,
+
+    /// Same as the onset columns of [`Condition`](super::Condition).
+    pub performed_type: ClinicalTimeType,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub performed_start: Option<time::OffsetDateTime>,
+    pub performed_start_resolution: TimeResolution,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub performed_end: Option<time::OffsetDateTime>,
+    pub performed_end_resolution: TimeResolution,
+    pub performed_age_low: Option<f64>,
+    pub performed_age_high: Option<f64>,
+    pub performed_age_unit: String,
+    pub performed_text: String,
+
+    /// First code of each Procedure.bodySite.
+    pub body_site: Vec<String>,
+    /// First coding of each Procedure.reasonCode.
+    #[serde(rename = "reasons.code")]
+    pub reasons_code: Vec<String>,
+    #[serde(rename = "reasons.description")]
+    pub reasons_description: Vec<String>,
+    #[serde(rename = "reasons.system")]
+    pub reasons_system: Vec<String>,
+}
@@ -0,0 +1,180 @@
+use clickhouse::Row;
+use serde::Serialize;
+
+use super::{AddressType, AddressUse, Gender, IdentifierUse, NameUse, TimeResolution};
+
+// Dimension tables for the practitioners and organizations other rows point
+// at. Names, identifiers and addresses have the same columns as in
+// AggregatePatient.
+
+#[derive(Debug, Row, Serialize)]
+pub struct Practitioner {
+    pub id: String,
+    pub active: Option<bool>,
+    /// Value of the identifier with the US NPI system, empty if none.
+    pub npi: String,
+    /// The display name, see `names` for all of them.
+    pub name_given: String,
+    pub name_family: String,
+    #[serde(rename = "names.use")]
+    pub names_use: Vec<NameUse>,
+    #[serde(rename = "names.family")]
+    pub names_family: Vec<String>,
+    #[serde(rename = "names.given")]
+    pub names_given: Vec<String>,
+    #[serde(rename = "names.prefix")]
+    pub names_prefix: Vec<String>,
+    #[serde(rename = "names.suffix")]
+    pub names_suffix: Vec<String>,
+    #[serde(
+        rename = "names.period_start",
+        with = "super::serde_helpers::datetime_option_vec"
+    )]
+    pub names_period_start: Vec<Option<time::OffsetDateTime>>,
+    #[serde(rename = "names.period_start_resolution")]
+    pub names_period_start_resolution: Vec<TimeResolution>,
+    #[serde(
+        rename = "names.period_end",
+        with = "super::serde_helpers::datetime_option_vec"
+    )]
+    pub names_period_end: Vec<Option<time::OffsetDateTime>>,
+    #[serde(rename = "names.period_end_resolution")]
+    pub names_period_end_resolution: Vec<TimeResolution>,
+    pub gender: Gender,
+    /// First code of each Practitioner.qualification, e.g. `MD`.
+    pub qualifications: Vec<String>,
+
+    #[serde(rename = "identifiers.use")]
+    pub identifiers_use: Vec<IdentifierUse>,
+    #[serde(rename = "identifiers.type")]
+    pub identifiers_type: Vec<String>,
+    #[serde(rename = "identifiers.system")]
+    pub identifiers_system: Vec<String>,
+    #[serde(rename = "identifiers.value")]
+    pub identifiers_value: Vec<String>,
+
+    #[serde(rename = "addresses.use")]
+    pub addresses_use: Vec<AddressUse>,
+    #[serde(rename = "addresses.type")]
+    pub addresses_type: Vec<AddressType>,
+    #[serde(rename = "addresses.city")]
+    pub addresses_city: Vec<String>,
+    #[serde(rename = "addresses.line")]
+    pub addresses_line: Vec<String>,
+    #[serde(rename = "addresses.district")]
+    pub addresses_district: Vec<String>,
+    #[serde(rename = "addresses.state")]
+    pub addresses_state: Vec<String>,
+    #[serde(rename = "addresses.postal_code")]
+    pub addresses_postal_code: Vec<String>,
+    #[serde(rename = "addresses.country")]
+    pub addresses_country: Vec<String>,
+    #[serde(rename = "addresses.text")]
+    pub addresses_text: Vec<String>,
+    #[serde(
+        rename = "addresses.period_start",
+        with = "super::serde_helpers::datetime_option_vec"
+    )]
+    pub addresses_period_start: Vec<Option<time::OffsetDateTime>>,
+    #[serde(rename = "addresses.period_start_resolution")]
+    pub addresses_period_start_resolution: Vec<TimeResolution>,
+    #[serde(
+        rename = "addresses.period_end",
+        with = "super::serde_helpers::datetime_option_vec"
+    )]
+    pub addresses_period_end: Vec<Option<time::OffsetDateTime>>,
+    #[serde(rename = "addresses.period_end_resolution")]
+    pub addresses_period_end_resolution: Vec<TimeResolution>,
+}
+
+#[derive(Debug, Row, Serialize)]
+pub struct PractitionerRole {
+    pub id: String,
+    pub active: Option<bool>,
+    /// Practitioner id
+    pub practitioner: String,
+    /// Organization id
+    pub organization: String,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub period_start: Option<time::OffsetDateTime>,
+    pub period_start_resolution: TimeResolution,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub period_end: Option<time::OffsetDateTime>,
+    pub period_end_resolution: TimeResolution,
+    /// First code of each PractitionerRole.code, e.g. `doctor`.
+    pub roles: Vec<String>,
+    /// First coding of each PractitionerRole.specialty.
+    #[serde(rename = "specialties.code")]
+    pub specialties_code: Vec<String>,
+    #[serde(rename = "specialties.description")]
+    pub specialties_description: Vec<String>,
+    #[serde(rename = "specialties.system")]
+    pub specialties_system: Vec<String>,
+    /// Location ids, leaving out locations we can't resolve to one.
+    pub locations: Vec<String>,
+
+    #[serde(rename = "identifiers.use")]
+    pub identifiers_use: Vec<IdentifierUse>,
+    #[serde(rename = "identifiers.type")]
+    pub identifiers_type: Vec<String>,
+    #[serde(rename = "identifiers.system")]
+    pub identifiers_system: Vec<String>,
+    #[serde(rename = "identifiers.value")]
+    pub identifiers_value: Vec<String>,
+}
+
+#[derive(Debug, Row, Serialize)]
+pub struct Organization {
+    pub id: String,
+    pub active: Option<bool>,
+    /// Same as in [`Practitioner`].
+    pub npi: String,
+    pub name: String,
+    pub aliases: Vec<String>,
+    /// First code of each Organization.type, e.g. `prov`.
+    pub types: Vec<String>,
+    /// Id of the Organization this one is part of.
+    pub part_of: String,
+
+    #[serde(rename = "identifiers.use")]
+    pub identifiers_use: Vec<IdentifierUse>,
+    #[serde(rename = "identifiers.type")]
+    pub identifiers_type: Vec<String>,
+    #[serde(rename = "identifiers.system")]
+    pub identifiers_system: Vec<String>,
+    #[serde(rename = "identifiers.value")]
+    pub identifiers_value: Vec<String>,
+
+    #[serde(rename = "addresses.use")]
+    pub addresses_use: Vec<AddressUse>,
+    #[serde(rename = "addresses.type")]
+    pub addresses_type: Vec<AddressType>,
+    #[serde(rename = "addresses.city")]
+    pub addresses_city: Vec<String>,
+    #[serde(rename = "addresses.line")]
+    pub addresses_line: Vec<String>,
+    #[serde(rename = "addresses.district")]
+    pub addresses_district: Vec<String>,
+    #[serde(rename = "addresses.state")]
+    pub addresses_state: Vec<String>,
+    #[serde(rename = "addresses.postal_code")]
+    pub addresses_postal_code: Vec<String>,
+    #[serde(rename = "addresses.country")]
+    pub addresses_country: Vec<String>,
+    #[serde(rename = "addresses.text")]
+    pub addresses_text: Vec<String>,
+    #[serde(
+        rename = "addresses.period_start",
+        with = "super::serde_helpers::datetime_option_vec"
+    )]
+    pub addresses_period_start: Vec<Option<time::OffsetDateTime>>,
+    #[serde(rename = "addresses.period_start_resolution")]
+    pub addresses_period_start_resolution: Vec<TimeResolution>,
+    #[serde(
+        rename = "addresses.period_end",
+        with = "super::serde_helpers::datetime_option_vec"
+    )]
+    pub addresses_period_end: Vec<Option<time::OffsetDateTime>>,
+    #[serde(rename = "addresses.period_end_resolution")]
+    pub addresses_period_end_resolution: Vec<TimeResolution>,
+}
@@ -0,0 +1,27 @@
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+
+/// Something we received but didn't store, kept so it can be looked at and
+/// fixed.
+#[derive(Debug, Clone, Row, Serialize, Deserialize)]
+pub struct QuarantineEntry {
+    #[serde(with = "clickhouse::serde::time::datetime")]
+    pub time: time::OffsetDateTime,
+    pub kafka_topic: String,
+    pub kafka_partition: i32,
+    pub kafka_offset: i64,
+    pub facility: String,
+    /// `Bundle` when the whole message was turned away, in which case the
+    /// path says where in it things went wrong.
+    pub resource_type: String,
+    /// Empty when the resource had no id.
+    pub resource_id: String,
+    /// Rejection rule name or conversion error reason code.
+    pub reason: String,
+    /// FHIR element path of the offending element.
+    pub path: String,
+    /// The resource's json as it was sent, or the whole message for bundles.
+    /// Medication requests, statements and administrations are kept in a
+    /// bundle along with the message's Medications, which they may point at.
+    pub payload: String,
+}
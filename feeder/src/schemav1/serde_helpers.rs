@@ -0,0 +1,23 @@
+//! Serde adapters for column types `clickhouse::serde` only covers as single
+//! values.
+
+/// `Array(Nullable(DateTime))`, e.g. a Nested column of optional times.
+pub mod datetime_option_vec {
+    use serde::{Serialize, Serializer};
+    use time::OffsetDateTime;
+
+    struct Item<'a>(&'a Option<OffsetDateTime>);
+
+    impl Serialize for Item<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            clickhouse::serde::time::datetime::option::serialize(self.0, serializer)
+        }
+    }
+
+    pub fn serialize<S: Serializer>(
+        values: &[Option<OffsetDateTime>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(Item))
+    }
+}
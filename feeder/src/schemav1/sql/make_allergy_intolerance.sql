@@ -0,0 +1,51 @@
+CREATE TABLE IF NOT EXISTS AllergyIntolerance (
+id String,
+clinical_status LowCardinality(String),
+verification_status LowCardinality(String),
+allergy_type Enum(
+  'unknown' = 0,
+  'allergy' = 1,
+  'intolerance' = 2
+),
+category Array(LowCardinality(String)),
+criticality Enum(
+  'unknown' = 0,
+  'low' = 1,
+  'high' = 2,
+  'unable-to-assess' = 3
+),
+code LowCardinality(String),
+code_description LowCardinality(String),
+code_system LowCardinality(String),
+subject String,
+encounter String,
+onset_type Enum(
+  'none' = 0,
+  'date_time' = 1,
+  'age' = 2,
+  'period' = 3,
+  'range' = 4,
+  'string' = 5
+),
+onset_start Nullable(DateTime),
+onset_start_resolution LowCardinality(String),
+onset_end Nullable(DateTime),
+onset_end_resolution LowCardinality(String),
+onset_age_low Nullable(Float64),
+onset_age_high Nullable(Float64),
+onset_age_unit LowCardinality(String),
+onset_text String,
+recorded_date Nullable(DateTime),
+recorded_date_resolution LowCardinality(String),
+reactions Nested(
+  manifestation LowCardinality(String),
+  manifestation_description LowCardinality(String),
+  severity Enum(
+    'unknown' = 0,
+    'mild' = 1,
+    'moderate' = 2,
+    'severe' = 3
+  )
+),
+) ENGINE = ReplacingMergeTree
+ORDER BY id
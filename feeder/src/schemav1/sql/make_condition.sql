@@ -0,0 +1,51 @@
+CREATE TABLE IF NOT EXISTS Condition (
+id String,
+clinical_status LowCardinality(String),
+verification_status LowCardinality(String),
+category Array(LowCardinality(String)),
+code LowCardinality(String),
+code_description LowCardinality(String),
+code_system LowCardinality(String),
+codings Nested(
+  code LowCardinality(String),
+  display LowCardinality(String),
+  system LowCardinality(String)
+),
+subject String,
+encounter String,
+onset_type Enum(
+  'none' = 0,
+  'date_time' = 1,
+  'age' = 2,
+  'period' = 3,
+  'range' = 4,
+  'string' = 5
+),
+onset_start Nullable(DateTime),
+onset_start_resolution LowCardinality(String),
+onset_end Nullable(DateTime),
+onset_end_resolution LowCardinality(String),
+onset_age_low Nullable(Float64),
+onset_age_high Nullable(Float64),
+onset_age_unit LowCardinality(String),
+onset_text String,
+abatement_type Enum(
+  'none' = 0,
+  'date_time' = 1,
+  'age' = 2,
+  'period' = 3,
+  'range' = 4,
+  'string' = 5
+),
+abatement_start Nullable(DateTime),
+abatement_start_resolution LowCardinality(String),
+abatement_end Nullable(DateTime),
+abatement_end_resolution LowCardinality(String),
+abatement_age_low Nullable(Float64),
+abatement_age_high Nullable(Float64),
+abatement_age_unit LowCardinality(String),
+abatement_text String,
+recorded_date Nullable(DateTime),
+recorded_date_resolution LowCardinality(String),
+) ENGINE = ReplacingMergeTree
+ORDER BY id
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS ConversionStats (
+time DateTime,
+facility LowCardinality(String),
+resource_type LowCardinality(String),
+field LowCardinality(String),
+outcome Enum('missing' = 0, 'defaulted' = 1, 'truncated' = 2, 'rejected' = 3),
+count UInt64,
+) ENGINE = SummingMergeTree(count)
+ORDER BY (facility, resource_type, field, outcome, time)
@@ -0,0 +1,40 @@
+CREATE TABLE IF NOT EXISTS DiagnosticReport (
+id String,
+status Enum(
+  'registered' = 0,
+  'partial' = 1,
+  'preliminary' = 2,
+  'final' = 3,
+  'amended' = 4,
+  'corrected' = 5,
+  'appended' = 6,
+  'cancelled' = 7,
+  'entered-in-error' = 8,
+  'unknown' = 9
+),
+category Array(LowCardinality(String)),
+code LowCardinality(String),
+code_description LowCardinality(String),
+code_system LowCardinality(String),
+subject String,
+encounter String,
+effective_start Nullable(DateTime),
+effective_start_resolution LowCardinality(String),
+effective_end Nullable(DateTime),
+effective_end_resolution LowCardinality(String),
+issued Nullable(DateTime),
+performers Nested(
+  type LowCardinality(String),
+  id String
+),
+results Array(String),
+presented_forms Nested(
+  content_type LowCardinality(String),
+  language LowCardinality(String),
+  title String,
+  url String,
+  size Nullable(UInt32),
+  hash String
+),
+) ENGINE = ReplacingMergeTree
+ORDER BY id
@@ -0,0 +1,65 @@
+CREATE TABLE IF NOT EXISTS Encounter (
+id String,
+status Enum(
+  'arrived' = 0,
+  'cancelled' = 1,
+  'entered-in-error' = 2,
+  'finished' = 3,
+  'in-progress' = 4,
+  'onleave' = 5,
+  'planned' = 6,
+  'triaged' = 7,
+  'unknown' = 8
+),
+subject String,
+period_start Nullable(DateTime),
+period_start_resolution LowCardinality(String),
+period_end Nullable(DateTime),
+period_end_resolution LowCardinality(String),
+class_code LowCardinality(String),
+class_description LowCardinality(String),
+class_system LowCardinality(String),
+types Nested(
+  code LowCardinality(String),
+  description LowCardinality(String),
+  system LowCardinality(String)
+),
+reasons Nested(
+  code LowCardinality(String),
+  description LowCardinality(String),
+  system LowCardinality(String)
+),
+diagnoses Nested(
+  condition String,
+  use LowCardinality(String),
+  rank Nullable(UInt32)
+),
+admit_source LowCardinality(String),
+re_admission LowCardinality(String),
+discharge_disposition LowCardinality(String),
+participants Nested(
+  type LowCardinality(String),
+  individual_type LowCardinality(String),
+  individual String,
+  period_start Nullable(DateTime),
+  period_start_resolution LowCardinality(String),
+  period_end Nullable(DateTime),
+  period_end_resolution LowCardinality(String)
+),
+locations Nested(
+  location String,
+  status Enum(
+    'unknown' = 0,
+    'planned' = 1,
+    'active' = 2,
+    'reserved' = 3,
+    'completed' = 4
+  ),
+  period_start Nullable(DateTime),
+  period_start_resolution LowCardinality(String),
+  period_end Nullable(DateTime),
+  period_end_resolution LowCardinality(String)
+),
+service_provider String,
+) ENGINE = ReplacingMergeTree
+ORDER BY id
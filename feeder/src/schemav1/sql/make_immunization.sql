@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS Immunization (
+id String,
+status LowCardinality(String),
+status_reason LowCardinality(String),
+vaccine_code LowCardinality(String),
+vaccine_description LowCardinality(String),
+vaccine_system LowCardinality(String),
+subject String,
+encounter String,
+occurrence Nullable(DateTime),
+occurrence_resolution LowCardinality(String),
+occurrence_text String,
+recorded Nullable(DateTime),
+recorded_resolution LowCardinality(String),
+primary_source Nullable(Bool),
+lot_number String,
+) ENGINE = ReplacingMergeTree
+ORDER BY id
@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS Location (
+id String,
+status LowCardinality(String),
+name String,
+aliases Array(String),
+mode LowCardinality(String),
+types Array(LowCardinality(String)),
+physical_type LowCardinality(String),
+address_line String,
+address_city String,
+address_state LowCardinality(String),
+address_postal_code String,
+address_country LowCardinality(String),
+latitude Nullable(Float64),
+longitude Nullable(Float64),
+managing_organization String,
+part_of String,
+) ENGINE = ReplacingMergeTree
+ORDER BY id
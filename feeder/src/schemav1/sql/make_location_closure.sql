@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS LocationClosure (
+ancestor String,
+descendant String,
+depth UInt32,
+) ENGINE = MergeTree
+ORDER BY (ancestor, descendant)
@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS MedicationAdministration (
+id String,
+status LowCardinality(String),
+category LowCardinality(String),
+medication String,
+medication_code LowCardinality(String),
+medication_description LowCardinality(String),
+medication_system LowCardinality(String),
+subject String,
+encounter String,
+effective_start Nullable(DateTime),
+effective_start_resolution LowCardinality(String),
+effective_end Nullable(DateTime),
+effective_end_resolution LowCardinality(String),
+request String,
+dosage_text String,
+route LowCardinality(String),
+dose_value Nullable(Float64),
+dose_unit LowCardinality(String),
+) ENGINE = ReplacingMergeTree
+ORDER BY id
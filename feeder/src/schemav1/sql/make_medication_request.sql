@@ -0,0 +1,30 @@
+CREATE TABLE IF NOT EXISTS MedicationRequest (
+id String,
+status LowCardinality(String),
+intent LowCardinality(String),
+category Array(LowCardinality(String)),
+medication String,
+medication_code LowCardinality(String),
+medication_description LowCardinality(String),
+medication_system LowCardinality(String),
+subject String,
+encounter String,
+authored_on Nullable(DateTime),
+authored_on_resolution LowCardinality(String),
+requester_type LowCardinality(String),
+requester String,
+dosages Nested(
+  sequence Nullable(Int32),
+  text String,
+  route LowCardinality(String),
+  dose_value Nullable(Float64),
+  dose_low Nullable(Float64),
+  dose_high Nullable(Float64),
+  dose_unit LowCardinality(String),
+  frequency Nullable(UInt32),
+  period Nullable(Float64),
+  period_unit LowCardinality(String),
+  timing_code LowCardinality(String)
+),
+) ENGINE = ReplacingMergeTree
+ORDER BY id
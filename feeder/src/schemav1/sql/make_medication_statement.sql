@@ -0,0 +1,31 @@
+CREATE TABLE IF NOT EXISTS MedicationStatement (
+id String,
+status LowCardinality(String),
+category LowCardinality(String),
+medication String,
+medication_code LowCardinality(String),
+medication_description LowCardinality(String),
+medication_system LowCardinality(String),
+subject String,
+encounter String,
+effective_start Nullable(DateTime),
+effective_start_resolution LowCardinality(String),
+effective_end Nullable(DateTime),
+effective_end_resolution LowCardinality(String),
+date_asserted Nullable(DateTime),
+date_asserted_resolution LowCardinality(String),
+dosages Nested(
+  sequence Nullable(Int32),
+  text String,
+  route LowCardinality(String),
+  dose_value Nullable(Float64),
+  dose_low Nullable(Float64),
+  dose_high Nullable(Float64),
+  dose_unit LowCardinality(String),
+  frequency Nullable(UInt32),
+  period Nullable(Float64),
+  period_unit LowCardinality(String),
+  timing_code LowCardinality(String)
+),
+) ENGINE = ReplacingMergeTree
+ORDER BY id
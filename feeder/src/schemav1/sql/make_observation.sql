@@ -0,0 +1,92 @@
+CREATE TABLE IF NOT EXISTS Observation (
+id String,
+status Enum(
+  'registered' = 0,
+  'preliminary' = 1,
+  'final' = 2,
+  'amended' = 3,
+  'corrected' = 4,
+  'cancelled' = 5,
+  'entered-in-error' = 6,
+  'unknown' = 7
+),
+code LowCardinality(String),
+code_description LowCardinality(String),
+code_system LowCardinality(String),
+category Array(LowCardinality(String)),
+subject String,
+encounter String,
+effective_start Nullable(DateTime),
+effective_start_resolution LowCardinality(String),
+effective_end Nullable(DateTime),
+effective_end_resolution LowCardinality(String),
+issued Nullable(DateTime),
+value_type Enum(
+  'none' = 0,
+  'quantity' = 1,
+  'codeable_concept' = 2,
+  'string' = 3,
+  'boolean' = 4,
+  'integer' = 5,
+  'range' = 6,
+  'ratio' = 7,
+  'period' = 8,
+  'other' = 9
+),
+value_quantity Nullable(Float64),
+value_comparator LowCardinality(String),
+value_unit LowCardinality(String),
+value_code LowCardinality(String),
+value_code_description LowCardinality(String),
+value_code_system LowCardinality(String),
+value_string String,
+value_boolean Nullable(Bool),
+value_integer Nullable(Int32),
+value_low Nullable(Float64),
+value_high Nullable(Float64),
+value_numerator Nullable(Float64),
+value_denominator Nullable(Float64),
+value_denominator_unit LowCardinality(String),
+value_period_start Nullable(DateTime),
+value_period_start_resolution LowCardinality(String),
+value_period_end Nullable(DateTime),
+value_period_end_resolution LowCardinality(String),
+data_absent_reason LowCardinality(String),
+components Nested(
+  code LowCardinality(String),
+  code_description LowCardinality(String),
+  code_system LowCardinality(String),
+  value_type Enum(
+    'none' = 0,
+    'quantity' = 1,
+    'codeable_concept' = 2,
+    'string' = 3,
+    'boolean' = 4,
+    'integer' = 5,
+    'range' = 6,
+    'ratio' = 7,
+    'period' = 8,
+    'other' = 9
+  ),
+  value_quantity Nullable(Float64),
+  value_comparator LowCardinality(String),
+  value_unit LowCardinality(String),
+  value_code LowCardinality(String),
+  value_code_description LowCardinality(String),
+  value_code_system LowCardinality(String),
+  value_string String,
+  value_boolean Nullable(Bool),
+  value_integer Nullable(Int32),
+  value_low Nullable(Float64),
+  value_high Nullable(Float64),
+  value_numerator Nullable(Float64),
+  value_denominator Nullable(Float64),
+  value_denominator_unit LowCardinality(String),
+  value_period_start Nullable(DateTime),
+  value_period_start_resolution LowCardinality(String),
+  value_period_end Nullable(DateTime),
+  value_period_end_resolution LowCardinality(String),
+  data_absent_reason LowCardinality(String)
+),
+) ENGINE = ReplacingMergeTree
+ORDER BY id
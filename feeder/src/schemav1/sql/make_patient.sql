@@ -3,16 +3,68 @@ CREATE TABLE IF NOT EXISTS AggregatePatient (
 id String,
 name_given String,
 name_family String,
-birth_time Date32,
+names Nested(
+  use Enum('unknown' = 0, 'usual' = 1, 'official' = 2, 'temp' = 3, 'nickname' = 4, 'anonymous' = 5, 'old' = 6, 'maiden' = 7),
+  family String,
+  given String,
+  prefix String,
+  suffix String,
+  period_start Nullable(DateTime),
+  period_start_resolution LowCardinality(String),
+  period_end Nullable(DateTime),
+  period_end_resolution LowCardinality(String),
+),
+birth_time Nullable(Date32),
 birth_time_resolution LowCardinality(String),
+birth_datetime Nullable(DateTime),
+birth_sex LowCardinality(String),
+race_omb Array(LowCardinality(String)),
+race_detailed Array(LowCardinality(String)),
+race_text String,
+ethnicity_omb Array(LowCardinality(String)),
+ethnicity_detailed Array(LowCardinality(String)),
+ethnicity_text String,
 death_time Nullable(DateTime32),
+death_time_resolution LowCardinality(String),
 deceased Enum('unknown' = 1, 'alive' = 2, 'dead' = 3),
+gender Enum('unknown' = 0, 'male' = 1, 'female' = 2, 'other' = 3),
+active Nullable(Bool),
+marital_status LowCardinality(String),
+managing_organization String,
+identifiers Nested(
+  use Enum('unknown' = 0, 'usual' = 1, 'official' = 2, 'temp' = 3, 'secondary' = 4, 'old' = 5),
+  type LowCardinality(String),
+  system String,
+  value String,
+),
+telecom Nested(
+  system Enum('unknown' = 0, 'phone' = 1, 'fax' = 2, 'email' = 3, 'pager' = 4, 'url' = 5, 'sms' = 6, 'other' = 7),
+  use Enum('unknown' = 0, 'home' = 1, 'work' = 2, 'temp' = 3, 'old' = 4, 'mobile' = 5),
+  value String,
+  rank Nullable(UInt32),
+),
+communication Nested(
+  language LowCardinality(String),
+  preferred Bool,
+),
 addresses Nested(
   use Enum( 'unknown' = 0, 'billing' = 1, 'home' = 2, 'old' = 3, 'temp' = 4, 'work' = 5),
   type Enum( 'unknown' = 0, 'physical' = 1, 'postal' = 2, 'both' = 3 ),
   city String,
   line String,
-  country String,
+  district String,
+  state String,
   postal_code String,
+  country String,
+  text String,
+  period_start Nullable(DateTime),
+  period_start_resolution LowCardinality(String),
+  period_end Nullable(DateTime),
+  period_end_resolution LowCardinality(String),
+),
+extensions Nested(
+  url LowCardinality(String),
+  value String,
 ),
-) ORDER BY ()
+) ENGINE = ReplacingMergeTree
+ORDER BY id
@@ -0,0 +1,42 @@
+CREATE TABLE IF NOT EXISTS Practitioner (
+id String,
+active Nullable(Bool),
+npi String,
+name_given String,
+name_family String,
+names Nested(
+  use Enum('unknown' = 0, 'usual' = 1, 'official' = 2, 'temp' = 3, 'nickname' = 4, 'anonymous' = 5, 'old' = 6, 'maiden' = 7),
+  family String,
+  given String,
+  prefix String,
+  suffix String,
+  period_start Nullable(DateTime),
+  period_start_resolution LowCardinality(String),
+  period_end Nullable(DateTime),
+  period_end_resolution LowCardinality(String),
+),
+gender Enum('unknown' = 0, 'male' = 1, 'female' = 2, 'other' = 3),
+qualifications Array(LowCardinality(String)),
+identifiers Nested(
+  use Enum('unknown' = 0, 'usual' = 1, 'official' = 2, 'temp' = 3, 'secondary' = 4, 'old' = 5),
+  type LowCardinality(String),
+  system String,
+  value String,
+),
+addresses Nested(
+  use Enum( 'unknown' = 0, 'billing' = 1, 'home' = 2, 'old' = 3, 'temp' = 4, 'work' = 5),
+  type Enum( 'unknown' = 0, 'physical' = 1, 'postal' = 2, 'both' = 3 ),
+  city String,
+  line String,
+  district String,
+  state String,
+  postal_code String,
+  country String,
+  text String,
+  period_start Nullable(DateTime),
+  period_start_resolution LowCardinality(String),
+  period_end Nullable(DateTime),
+  period_end_resolution LowCardinality(String),
+),
+) ENGINE = ReplacingMergeTree
+ORDER BY id
@@ -0,0 +1,24 @@
+CREATE TABLE IF NOT EXISTS PractitionerRole (
+id String,
+active Nullable(Bool),
+practitioner String,
+organization String,
+period_start Nullable(DateTime),
+period_start_resolution LowCardinality(String),
+period_end Nullable(DateTime),
+period_end_resolution LowCardinality(String),
+roles Array(LowCardinality(String)),
+specialties Nested(
+  code LowCardinality(String),
+  description LowCardinality(String),
+  system LowCardinality(String)
+),
+locations Array(String),
+identifiers Nested(
+  use Enum('unknown' = 0, 'usual' = 1, 'official' = 2, 'temp' = 3, 'secondary' = 4, 'old' = 5),
+  type LowCardinality(String),
+  system String,
+  value String,
+),
+) ENGINE = ReplacingMergeTree
+ORDER BY id
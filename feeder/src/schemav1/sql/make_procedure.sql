@@ -0,0 +1,43 @@
+CREATE TABLE IF NOT EXISTS Procedure (
+id String,
+status Enum(
+  'preparation' = 0,
+  'in-progress' = 1,
+  'not-done' = 2,
+  'on-hold' = 3,
+  'stopped' = 4,
+  'completed' = 5,
+  'entered-in-error' = 6,
+  'unknown' = 7
+),
+status_reason LowCardinality(String),
+category LowCardinality(String),
+code LowCardinality(String),
+code_description LowCardinality(String),
+code_system LowCardinality(String),
+subject String,
+encounter String,
+performed_type Enum(
+  'none' = 0,
+  'date_time' = 1,
+  'age' = 2,
+  'period' = 3,
+  'range' = 4,
+  'string' = 5
+),
+performed_start Nullable(DateTime),
+performed_start_resolution LowCardinality(String),
+performed_end Nullable(DateTime),
+performed_end_resolution LowCardinality(String),
+performed_age_low Nullable(Float64),
+performed_age_high Nullable(Float64),
+performed_age_unit LowCardinality(String),
+performed_text String,
+body_site Array(LowCardinality(String)),
+reasons Nested(
+  code LowCardinality(String),
+  description LowCardinality(String),
+  system LowCardinality(String)
+),
+) ENGINE = ReplacingMergeTree
+ORDER BY id
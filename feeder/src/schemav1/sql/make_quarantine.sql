@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS Quarantine (
+time DateTime,
+kafka_topic LowCardinality(String),
+kafka_partition Int32,
+kafka_offset Int64,
+facility LowCardinality(String),
+resource_type LowCardinality(String),
+resource_id String,
+reason LowCardinality(String),
+path String,
+payload String,
+) ENGINE = MergeTree
+ORDER BY (facility, time)
@@ -0,0 +1,28 @@
+use clickhouse::Row;
+use serde::Serialize;
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+/// What happened to a field while converting it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum FieldOutcome {
+    /// Absent and left empty.
+    Missing = 0,
+    /// Absent or partial, and filled in from the facility profile.
+    Defaulted = 1,
+    /// Present, but some of it didn't fit in the row.
+    Truncated = 2,
+    /// The resource was rejected because of this field.
+    Rejected = 3,
+}
+
+#[derive(Debug, Row, Serialize)]
+pub struct ConversionStatsRow {
+    #[serde(with = "clickhouse::serde::time::datetime")]
+    pub time: time::OffsetDateTime,
+    pub facility: String,
+    pub resource_type: String,
+    pub field: String,
+    pub outcome: FieldOutcome,
+    pub count: u64,
+}
@@ -0,0 +1,112 @@
+//! Counts of the data quality problems we run into while converting, so we
+//! can tell which feeds send us what kind of garbage.
+
+use std::collections::HashMap;
+
+use time::OffsetDateTime;
+
+use crate::schemav1::{ConversionStatsRow, FieldOutcome};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct StatKey {
+    facility: String,
+    /// FHIR element path, the resource type is its first segment.
+    field: String,
+    outcome: FieldOutcome,
+}
+
+/// Tally of field outcomes per facility, resource type and field.
+#[derive(Debug, Default)]
+pub struct ConversionStats {
+    counts: HashMap<StatKey, u64>,
+}
+
+impl ConversionStats {
+    pub fn record(&mut self, facility: &str, field: &str, outcome: FieldOutcome) {
+        let key = StatKey {
+            facility: facility.to_string(),
+            field: field.to_string(),
+            outcome,
+        };
+        *self.counts.entry(key).or_default() += 1;
+    }
+
+    pub fn count(&self, facility: &str, field: &str, outcome: FieldOutcome) -> u64 {
+        let key = StatKey {
+            facility: facility.to_string(),
+            field: field.to_string(),
+            outcome,
+        };
+        self.counts.get(&key).copied().unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    pub fn merge(&mut self, other: ConversionStats) {
+        for (key, count) in other.counts {
+            *self.counts.entry(key).or_default() += count;
+        }
+    }
+
+    /// Rows for the ConversionStats table, stamped with `time`.
+    pub fn to_rows(&self, time: OffsetDateTime) -> Vec<ConversionStatsRow> {
+        self.counts
+            .iter()
+            .map(|(key, count)| ConversionStatsRow {
+                time,
+                facility: key.facility.clone(),
+                resource_type: key.field.split('.').next().unwrap_or_default().to_string(),
+                field: key.field.clone(),
+                outcome: key.outcome,
+                count: *count,
+            })
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.counts.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_adds_counts() {
+        let mut a = ConversionStats::default();
+        a.record("olathe", "Encounter.period.start", FieldOutcome::Defaulted);
+        let mut b = ConversionStats::default();
+        b.record("olathe", "Encounter.period.start", FieldOutcome::Defaulted);
+        b.record("olathe", "Encounter.period.start", FieldOutcome::Missing);
+        b.record("default", "Encounter.period.start", FieldOutcome::Defaulted);
+
+        a.merge(b);
+        assert_eq!(
+            a.count("olathe", "Encounter.period.start", FieldOutcome::Defaulted),
+            2
+        );
+        assert_eq!(
+            a.count("olathe", "Encounter.period.start", FieldOutcome::Missing),
+            1
+        );
+        assert_eq!(
+            a.count("default", "Encounter.period.start", FieldOutcome::Defaulted),
+            1
+        );
+    }
+
+    #[test]
+    fn test_rows_carry_resource_type() {
+        let mut stats = ConversionStats::default();
+        stats.record("olathe", "Patient.birthDate", FieldOutcome::Missing);
+
+        let rows = stats.to_rows(OffsetDateTime::UNIX_EPOCH);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].resource_type, "Patient");
+        assert_eq!(rows[0].field, "Patient.birthDate");
+        assert_eq!(rows[0].count, 1);
+    }
+}
@@ -0,0 +1,111 @@
+mod utils;
+
+use feeder::{
+    fhir_r4b_shemav1::{ConversionErrorReason, convert_allergy_intolerance},
+    profile::FacilityProfile,
+    schemav1::{
+        AllergyCriticality, AllergyType, ClinicalTimeType, FieldOutcome, ReactionSeverity,
+        Resource, TimeResolution,
+        db_ops::{insert_bundle, install_schema_v1},
+    },
+    stats::ConversionStats,
+};
+use fhir_model::r4b::resources::AllergyIntolerance;
+use time::macros::datetime;
+use utils::{connect_to_clickhouse_test_container, drop_db};
+
+const ALLERGY_INTOLERANCE_1: &str = include_str!("assets/allergy_intolerance_1.json");
+
+#[tokio::test]
+async fn parse_and_insert() {
+    let client = connect_to_clickhouse_test_container();
+
+    let fhir_allergy = serde_json::from_str::<AllergyIntolerance>(ALLERGY_INTOLERANCE_1).unwrap();
+    let allergy = convert_allergy_intolerance(
+        &fhir_allergy,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+
+    drop_db(&client, "attempt_1_1").await;
+    install_schema_v1(&client, "attempt_1_1").await.unwrap();
+
+    insert_bundle(
+        &client,
+        "attempt_1_1",
+        &[Resource::AllergyIntolerance(allergy)],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn peanut_allergy_is_kept() {
+    let fhir_allergy = serde_json::from_str::<AllergyIntolerance>(ALLERGY_INTOLERANCE_1).unwrap();
+    let mut stats = ConversionStats::default();
+    let allergy =
+        convert_allergy_intolerance(&fhir_allergy, &FacilityProfile::default(), &mut stats)
+            .unwrap();
+
+    assert_eq!(allergy.clinical_status, "active");
+    assert_eq!(allergy.verification_status, "confirmed");
+    assert_eq!(allergy.allergy_type, AllergyType::Allergy);
+    assert_eq!(allergy.category, vec!["food"]);
+    assert_eq!(allergy.criticality, AllergyCriticality::High);
+    assert_eq!(allergy.code, "91935009");
+    assert_eq!(allergy.subject, "7d9aa431-cd72-8aa2-9559-5920937d9330");
+    assert_eq!(allergy.onset_type, ClinicalTimeType::DateTime);
+    assert_eq!(allergy.onset_start, Some(datetime!(1994-03-01 0:00 UTC)));
+    assert_eq!(allergy.onset_start_resolution, TimeResolution::Month);
+    assert_eq!(
+        allergy.recorded_date,
+        Some(datetime!(2004-09-14 10:22:05 -04:00))
+    );
+}
+
+#[tokio::test]
+async fn first_manifestation_of_each_reaction_is_kept() {
+    let fhir_allergy = serde_json::from_str::<AllergyIntolerance>(ALLERGY_INTOLERANCE_1).unwrap();
+    let mut stats = ConversionStats::default();
+    let allergy =
+        convert_allergy_intolerance(&fhir_allergy, &FacilityProfile::default(), &mut stats)
+            .unwrap();
+
+    assert_eq!(
+        allergy.reactions_manifestation,
+        vec!["39579001", "247472004"]
+    );
+    assert_eq!(
+        allergy.reactions_manifestation_description,
+        vec!["Anaphylaxis", "Wheal"]
+    );
+    assert_eq!(
+        allergy.reactions_severity,
+        vec![ReactionSeverity::Severe, ReactionSeverity::Mild]
+    );
+    assert_eq!(
+        stats.count(
+            "default",
+            "AllergyIntolerance.reaction.manifestation",
+            FieldOutcome::Truncated
+        ),
+        1
+    );
+}
+
+#[tokio::test]
+async fn required_code_is_enforced() {
+    let mut json: serde_json::Value = serde_json::from_str(ALLERGY_INTOLERANCE_1).unwrap();
+    json.as_object_mut().unwrap().remove("code");
+    let fhir_allergy: AllergyIntolerance = serde_json::from_value(json).unwrap();
+
+    let profile = FacilityProfile {
+        required: vec!["AllergyIntolerance.code".to_string()],
+        ..Default::default()
+    };
+    let err = convert_allergy_intolerance(&fhir_allergy, &profile, &mut ConversionStats::default())
+        .unwrap_err();
+    assert_eq!(err.reason, ConversionErrorReason::MissingRequired);
+    assert_eq!(err.path, "AllergyIntolerance.code");
+}
@@ -1,24 +1,82 @@
 mod utils;
 
-use clickhouse::Client;
 use feeder::{
-    fhir_r4b_shemav1::{ConvertedBundle, convert_bundle, convert_patient},
-    schemav1::db_ops::install_schema_v1,
+    fhir_r4b_shemav1::{BundlePolicy, ConversionErrorReason, convert_bundle},
+    profile::FacilityProfile,
+    schemav1::{Resource, db_ops::install_schema_v1},
+    stats::ConversionStats,
 };
-use fhir_model::r4b::resources::{Bundle, Patient};
-use utils::{connect_to_clickhouse_test_container, drop_db};
+use fhir_model::r4b::resources::Bundle;
+use utils::{bundle_of, connect_to_clickhouse_test_container, drop_db};
 
 const BUNDLE_1: &str = include_str!("assets/bundle_1.json");
+const PATIENT_1: &str = include_str!("assets/patient_1.json");
+const ENCOUNTER_1: &str = include_str!("assets/encounter_1.json");
 
 #[tokio::test]
 async fn parse_and_insert() {
     let client = connect_to_clickhouse_test_container();
 
     let fhir_bundle = serde_json::from_str::<Bundle>(BUNDLE_1).unwrap();
-    let converted_bundle = convert_bundle(&fhir_bundle).unwrap();
+    let converted_bundle = convert_bundle(
+        &fhir_bundle,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
     dbg!(&converted_bundle);
 
     drop_db(&client, "attempt_1_1").await;
     install_schema_v1(&client, "attempt_1_1").await.unwrap();
 }
 
+#[tokio::test]
+async fn one_bad_entry_fails_whole_bundle() {
+    let bundle = bundle_of(&[PATIENT_1, &encounter_without_id()]);
+    let fhir_bundle = serde_json::from_str::<Bundle>(&bundle).unwrap();
+
+    let err = convert_bundle(
+        &fhir_bundle,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap_err();
+    assert_eq!(err.reason, ConversionErrorReason::MissingId);
+}
+
+#[tokio::test]
+async fn best_effort_keeps_good_entries() {
+    let bundle = bundle_of(&[PATIENT_1, &encounter_without_id(), ENCOUNTER_1]);
+    let fhir_bundle = serde_json::from_str::<Bundle>(&bundle).unwrap();
+
+    let profile = FacilityProfile {
+        bundle_policy: BundlePolicy::BestEffort,
+        ..Default::default()
+    };
+    let converted =
+        convert_bundle(&fhir_bundle, &profile, &mut ConversionStats::default()).unwrap();
+    assert_eq!(converted.resources.len(), 2);
+    assert_eq!(converted.failures.len(), 1);
+    assert_eq!(converted.failures[0].path, "Encounter.id");
+}
+
+fn encounter_without_id() -> String {
+    let mut json: serde_json::Value = serde_json::from_str(ENCOUNTER_1).unwrap();
+    json.as_object_mut().unwrap().remove("id");
+    json.to_string()
+}
+
+#[tokio::test]
+async fn unaccepted_types_are_skipped() {
+    let bundle = bundle_of(&[PATIENT_1, ENCOUNTER_1]);
+    let fhir_bundle = serde_json::from_str::<Bundle>(&bundle).unwrap();
+
+    let profile = FacilityProfile {
+        accept: Some(vec!["Encounter".to_string()]),
+        ..Default::default()
+    };
+    let converted =
+        convert_bundle(&fhir_bundle, &profile, &mut ConversionStats::default()).unwrap();
+    assert_eq!(converted.resources.len(), 1);
+    assert!(matches!(converted.resources[0], Resource::Encounter(_)));
+}
@@ -0,0 +1,188 @@
+mod utils;
+
+use feeder::{
+    fhir_r4b_shemav1::{ConversionErrorReason, convert_condition},
+    profile::FacilityProfile,
+    schemav1::{
+        ClinicalTimeType, FieldOutcome, Resource, TimeResolution,
+        db_ops::{insert_bundle, install_schema_v1},
+    },
+    stats::ConversionStats,
+};
+use fhir_model::r4b::resources::Condition;
+use serde_json::json;
+use time::macros::datetime;
+use utils::{connect_to_clickhouse_test_container, drop_db};
+
+const CONDITION_1: &str = include_str!("assets/condition_1.json");
+
+/// CONDITION_1 with its onset and abatement replaced by `times`.
+fn condition_with_times(times: serde_json::Value) -> Condition {
+    let mut json: serde_json::Value = serde_json::from_str(CONDITION_1).unwrap();
+    let object = json.as_object_mut().unwrap();
+    object.remove("onsetDateTime");
+    object.remove("abatementDateTime");
+    object.extend(times.as_object().unwrap().clone());
+    serde_json::from_value(json).unwrap()
+}
+
+#[tokio::test]
+async fn parse_and_insert() {
+    let client = connect_to_clickhouse_test_container();
+
+    let fhir_condition = serde_json::from_str::<Condition>(CONDITION_1).unwrap();
+    let condition = convert_condition(
+        &fhir_condition,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+
+    drop_db(&client, "attempt_1_1").await;
+    install_schema_v1(&client, "attempt_1_1").await.unwrap();
+
+    insert_bundle(&client, "attempt_1_1", &[Resource::Condition(condition)])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn encounter_diagnosis_is_kept() {
+    let fhir_condition = serde_json::from_str::<Condition>(CONDITION_1).unwrap();
+    let mut stats = ConversionStats::default();
+    let condition =
+        convert_condition(&fhir_condition, &FacilityProfile::default(), &mut stats).unwrap();
+
+    assert_eq!(condition.clinical_status, "resolved");
+    assert_eq!(condition.verification_status, "confirmed");
+    assert_eq!(condition.category, vec!["encounter-diagnosis"]);
+    assert_eq!(condition.code, "10509002");
+    assert_eq!(condition.code_system, "http://snomed.info/sct");
+    assert_eq!(condition.codings_code, vec!["10509002", "J20.9"]);
+    assert_eq!(
+        condition.codings_system,
+        vec![
+            "http://snomed.info/sct",
+            "http://hl7.org/fhir/sid/icd-10-cm"
+        ]
+    );
+    assert_eq!(condition.subject, "7d9aa431-cd72-8aa2-9559-5920937d9330");
+    assert_eq!(condition.encounter, "00017486-6c88-2b8a-ca28-7f147efb8848");
+    assert_eq!(condition.onset_type, ClinicalTimeType::DateTime);
+    assert_eq!(
+        condition.onset_start,
+        Some(datetime!(1998-04-16 15:59:37 -04:00))
+    );
+    assert_eq!(condition.onset_start_resolution, TimeResolution::Second);
+    assert_eq!(condition.abatement_type, ClinicalTimeType::DateTime);
+    assert_eq!(
+        condition.abatement_start,
+        Some(datetime!(1998-04-30 15:59:37 -04:00))
+    );
+    assert_eq!(
+        condition.recorded_date,
+        Some(datetime!(1998-04-16 15:59:37 -04:00))
+    );
+    assert!(stats.is_empty());
+}
+
+#[tokio::test]
+async fn every_onset_type_is_kept() {
+    let mut stats = ConversionStats::default();
+    let condition = convert_condition(
+        &condition_with_times(json!({
+            "onsetPeriod": { "start": "1998", "end": "1999-03-02" },
+            "abatementString": "in childhood"
+        })),
+        &FacilityProfile::default(),
+        &mut stats,
+    )
+    .unwrap();
+    assert_eq!(condition.onset_type, ClinicalTimeType::Period);
+    assert_eq!(condition.onset_start, Some(datetime!(1998-01-01 0:00 UTC)));
+    assert_eq!(condition.onset_start_resolution, TimeResolution::Year);
+    assert_eq!(condition.onset_end, Some(datetime!(1999-03-02 0:00 UTC)));
+    assert_eq!(condition.onset_end_resolution, TimeResolution::Day);
+    // Only the year was filled in
+    assert_eq!(
+        stats.count(
+            "default",
+            "Condition.onsetPeriod.start",
+            FieldOutcome::Defaulted
+        ),
+        1
+    );
+    assert_eq!(
+        stats.count(
+            "default",
+            "Condition.onsetPeriod.end",
+            FieldOutcome::Defaulted
+        ),
+        0
+    );
+    assert_eq!(condition.abatement_type, ClinicalTimeType::String);
+    assert_eq!(condition.abatement_text, "in childhood");
+
+    let condition = convert_condition(
+        &condition_with_times(json!({
+            "onsetAge": { "value": 42, "unit": "years", "code": "a" },
+            "abatementRange": { "low": { "value": 50, "code": "a" }, "high": { "value": 55, "code": "a" } }
+        })),
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+    assert_eq!(condition.onset_type, ClinicalTimeType::Age);
+    assert_eq!(condition.onset_age_low, Some(42.0));
+    assert_eq!(condition.onset_age_high, Some(42.0));
+    assert_eq!(condition.onset_age_unit, "years");
+    assert_eq!(condition.onset_start, None);
+    assert_eq!(condition.abatement_type, ClinicalTimeType::Range);
+    assert_eq!(condition.abatement_age_low, Some(50.0));
+    assert_eq!(condition.abatement_age_high, Some(55.0));
+    assert_eq!(condition.abatement_age_unit, "a");
+}
+
+#[tokio::test]
+async fn required_onset_is_enforced() {
+    let fhir_condition = condition_with_times(json!({}));
+
+    let mut stats = ConversionStats::default();
+    let condition =
+        convert_condition(&fhir_condition, &FacilityProfile::default(), &mut stats).unwrap();
+    assert_eq!(condition.onset_type, ClinicalTimeType::None);
+    assert_eq!(condition.abatement_type, ClinicalTimeType::None);
+    assert_eq!(
+        stats.count("default", "Condition.onset", FieldOutcome::Missing),
+        1
+    );
+
+    let profile = FacilityProfile {
+        required: vec!["Condition.onset".to_string()],
+        ..Default::default()
+    };
+    let err =
+        convert_condition(&fhir_condition, &profile, &mut ConversionStats::default()).unwrap_err();
+    assert_eq!(err.reason, ConversionErrorReason::MissingRequired);
+    assert_eq!(err.path, "Condition.onset");
+    assert_eq!(
+        err.resource_id.as_deref(),
+        Some("3a7f2c9e-1b0d-4f5e-8c6a-9d2e4b7f1a03")
+    );
+}
+
+#[tokio::test]
+async fn group_subjects_are_left_empty() {
+    let mut json: serde_json::Value = serde_json::from_str(CONDITION_1).unwrap();
+    json["subject"]["reference"] = "Group/1".into();
+    let fhir_condition = serde_json::from_value::<Condition>(json).unwrap();
+
+    let mut stats = ConversionStats::default();
+    let condition =
+        convert_condition(&fhir_condition, &FacilityProfile::default(), &mut stats).unwrap();
+    assert_eq!(condition.subject, "");
+    assert_eq!(
+        stats.count("default", "Condition.subject", FieldOutcome::Truncated),
+        1
+    );
+}
@@ -0,0 +1,184 @@
+mod utils;
+
+use feeder::{
+    fhir_r4b_shemav1::convert_diagnostic_report,
+    profile::FacilityProfile,
+    schemav1::{
+        DiagnosticReportStatus, FieldOutcome, Resource, TimeResolution,
+        db_ops::{insert_bundle, install_schema_v1},
+    },
+    stats::ConversionStats,
+};
+use fhir_model::r4b::resources::DiagnosticReport;
+use time::macros::datetime;
+use utils::{connect_to_clickhouse_test_container, drop_db};
+
+const DIAGNOSTIC_REPORT_1: &str = include_str!("assets/diagnostic_report_1.json");
+
+#[tokio::test]
+async fn parse_and_insert() {
+    let client = connect_to_clickhouse_test_container();
+
+    let fhir_report = serde_json::from_str::<DiagnosticReport>(DIAGNOSTIC_REPORT_1).unwrap();
+    let report = convert_diagnostic_report(
+        &fhir_report,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+
+    drop_db(&client, "attempt_1_1").await;
+    install_schema_v1(&client, "attempt_1_1").await.unwrap();
+
+    insert_bundle(
+        &client,
+        "attempt_1_1",
+        &[Resource::DiagnosticReport(report)],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn lab_panel_keeps_its_results() {
+    let fhir_report = serde_json::from_str::<DiagnosticReport>(DIAGNOSTIC_REPORT_1).unwrap();
+    let mut stats = ConversionStats::default();
+    let report =
+        convert_diagnostic_report(&fhir_report, &FacilityProfile::default(), &mut stats).unwrap();
+
+    assert_eq!(report.status, DiagnosticReportStatus::Final);
+    assert_eq!(report.category, vec!["LAB"]);
+    assert_eq!(report.code, "58410-2");
+    assert_eq!(report.code_system, "http://loinc.org");
+    assert_eq!(report.subject, "7d9aa431-cd72-8aa2-9559-5920937d9330");
+    assert_eq!(report.encounter, "00017486-6c88-2b8a-ca28-7f147efb8848");
+    assert_eq!(
+        report.effective_start,
+        Some(datetime!(2012-02-07 09:54:18 -05:00))
+    );
+    assert_eq!(report.effective_start_resolution, TimeResolution::Second);
+    assert_eq!(report.effective_end, None);
+    assert_eq!(
+        report.issued,
+        Some(datetime!(2012-02-07 09:54:18.417 -05:00))
+    );
+    assert_eq!(report.performers_type, vec!["Organization"]);
+    assert_eq!(
+        report.performers_id,
+        vec!["5f1a2b3c-4d5e-4f60-8a7b-9c0d1e2f3a4b"]
+    );
+    assert_eq!(
+        report.results,
+        vec![
+            "0a1b2c3d-4e5f-4a6b-8c7d-9e0f1a2b3c4d",
+            "1b2c3d4e-5f6a-4b7c-9d8e-0f1a2b3c4d5e",
+            "2c3d4e5f-6a7b-4c8d-8e9f-1a2b3c4d5e6f",
+        ]
+    );
+    assert_eq!(
+        stats.count(
+            "default",
+            "DiagnosticReport.issued",
+            FieldOutcome::Truncated
+        ),
+        1
+    );
+}
+
+#[tokio::test]
+async fn presented_forms_keep_only_metadata() {
+    let fhir_report = serde_json::from_str::<DiagnosticReport>(DIAGNOSTIC_REPORT_1).unwrap();
+    let mut stats = ConversionStats::default();
+    let report =
+        convert_diagnostic_report(&fhir_report, &FacilityProfile::default(), &mut stats).unwrap();
+
+    assert_eq!(
+        report.presented_forms_content_type,
+        vec!["text/plain", "application/pdf"]
+    );
+    assert_eq!(report.presented_forms_language, vec!["en-US", ""]);
+    assert_eq!(report.presented_forms_title, vec!["CBC report", ""]);
+    assert_eq!(
+        report.presented_forms_url,
+        vec!["", "https://lab.example.org/reports/c4e6a8b0.pdf"]
+    );
+    // The first one's size comes from its data
+    assert_eq!(report.presented_forms_size, vec![Some(39), Some(48213)]);
+    assert_eq!(
+        report.presented_forms_hash,
+        vec!["18b50b308eb4cef921cf2541873b10dea65c9c8e", ""]
+    );
+    assert_eq!(
+        stats.count(
+            "default",
+            "DiagnosticReport.presentedForm.data",
+            FieldOutcome::Truncated
+        ),
+        1
+    );
+
+    // Without a hash we hash the data ourselves
+    let mut json: serde_json::Value = serde_json::from_str(DIAGNOSTIC_REPORT_1).unwrap();
+    json["presentedForm"][0]
+        .as_object_mut()
+        .unwrap()
+        .remove("hash");
+    let fhir_report = serde_json::from_value::<DiagnosticReport>(json).unwrap();
+    let report = convert_diagnostic_report(
+        &fhir_report,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        report.presented_forms_hash,
+        vec!["18b50b308eb4cef921cf2541873b10dea65c9c8e", ""]
+    );
+}
+
+#[tokio::test]
+async fn unresolvable_performers_are_left_out() {
+    let mut json: serde_json::Value = serde_json::from_str(DIAGNOSTIC_REPORT_1).unwrap();
+    json["performer"]
+        .as_array_mut()
+        .unwrap()
+        .push(serde_json::json!({ "display": "Dr. Somebody" }));
+    let fhir_report = serde_json::from_value::<DiagnosticReport>(json).unwrap();
+    let mut stats = ConversionStats::default();
+    let report =
+        convert_diagnostic_report(&fhir_report, &FacilityProfile::default(), &mut stats).unwrap();
+
+    assert_eq!(report.performers_type, vec!["Organization"]);
+    assert_eq!(
+        report.performers_id,
+        vec!["5f1a2b3c-4d5e-4f60-8a7b-9c0d1e2f3a4b"]
+    );
+    assert_eq!(
+        stats.count(
+            "default",
+            "DiagnosticReport.performer",
+            FieldOutcome::Truncated
+        ),
+        1
+    );
+}
+
+#[tokio::test]
+async fn device_subjects_are_left_empty() {
+    let mut json: serde_json::Value = serde_json::from_str(DIAGNOSTIC_REPORT_1).unwrap();
+    json["subject"]["reference"] = "Device/1".into();
+    let fhir_report = serde_json::from_value::<DiagnosticReport>(json).unwrap();
+    let mut stats = ConversionStats::default();
+    let report =
+        convert_diagnostic_report(&fhir_report, &FacilityProfile::default(), &mut stats).unwrap();
+
+    assert_eq!(report.subject, "");
+    assert_eq!(
+        stats.count(
+            "default",
+            "DiagnosticReport.subject",
+            FieldOutcome::Truncated
+        ),
+        1
+    );
+}
@@ -1,5 +1,17 @@
-use feeder::fhir_r4b_shemav1::convert_encounter;
+mod utils;
+
+use feeder::{
+    fhir_r4b_shemav1::{ConversionErrorReason, convert_encounter},
+    profile::{FacilityProfile, FacilityProfiles},
+    schemav1::{
+        EncounterLocationStatus, FieldOutcome, Resource, TimeResolution,
+        db_ops::{insert_bundle, install_schema_v1},
+    },
+    stats::ConversionStats,
+};
 use fhir_model::r4b::resources::Encounter;
+use time::macros::datetime;
+use utils::{connect_to_clickhouse_test_container, drop_db};
 
 const ENCOUNTER_1: &str = include_str!("assets/encounter_1.json");
 
@@ -9,6 +21,229 @@ async fn parse_1() {
     // let mut client = clickhouse::Client::default().with_url(mock_db.url());
 
     let fhir_patient = serde_json::from_str::<Encounter>(ENCOUNTER_1).unwrap();
-    let aggregate_patient = convert_encounter(&fhir_patient).unwrap();
+    let aggregate_patient = convert_encounter(
+        &fhir_patient,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
     dbg!(&aggregate_patient);
 }
+
+#[tokio::test]
+async fn parse_and_insert() {
+    let client = connect_to_clickhouse_test_container();
+
+    let fhir_encounter = serde_json::from_str::<Encounter>(ENCOUNTER_1).unwrap();
+    let encounter = convert_encounter(
+        &fhir_encounter,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+
+    drop_db(&client, "attempt_1_1").await;
+    install_schema_v1(&client, "attempt_1_1").await.unwrap();
+
+    insert_bundle(&client, "attempt_1_1", &[Resource::Encounter(encounter)])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn subject_must_be_a_patient() {
+    let mut json: serde_json::Value = serde_json::from_str(ENCOUNTER_1).unwrap();
+    json["subject"]["reference"] = "Group/1".into();
+    let fhir_encounter = serde_json::from_value::<Encounter>(json).unwrap();
+
+    let err = convert_encounter(
+        &fhir_encounter,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap_err();
+    assert_eq!(err.reason, ConversionErrorReason::BadReferenceType);
+    assert_eq!(err.path, "Encounter.subject.reference");
+    assert_eq!(err.resource_type, "Encounter");
+    assert_eq!(
+        err.resource_id.as_deref(),
+        Some("00017486-6c88-2b8a-ca28-7f147efb8848")
+    );
+}
+
+#[tokio::test]
+async fn dates_without_time_use_facility_offset() {
+    let mut json: serde_json::Value = serde_json::from_str(ENCOUNTER_1).unwrap();
+    json["period"]["start"] = "1998-04".into();
+    json["period"].as_object_mut().unwrap().remove("end");
+    let fhir_encounter = serde_json::from_value::<Encounter>(json).unwrap();
+
+    let profiles = FacilityProfiles::from_json(
+        r#"{ "default": { "utc_offset_minutes": -300, "fill_day": 15 } }"#,
+    )
+    .unwrap();
+    let encounter = convert_encounter(
+        &fhir_encounter,
+        profiles.get(None),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        encounter.period_start,
+        Some(datetime!(1998-04-15 00:00 -05:00))
+    );
+    assert_eq!(encounter.period_start_resolution, TimeResolution::Month);
+    assert_eq!(encounter.period_end, None);
+    assert_eq!(encounter.period_end_resolution, TimeResolution::Unknown);
+}
+
+#[tokio::test]
+async fn fill_day_is_clamped_to_short_months() {
+    let mut json: serde_json::Value = serde_json::from_str(ENCOUNTER_1).unwrap();
+    json["period"]["start"] = "2021-02".into();
+    json["period"]["end"] = "2021-04".into();
+    let fhir_encounter = serde_json::from_value::<Encounter>(json).unwrap();
+
+    let profiles = FacilityProfiles::from_json(r#"{ "default": { "fill_day": 31 } }"#).unwrap();
+    let encounter = convert_encounter(
+        &fhir_encounter,
+        profiles.get(None),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        encounter.period_start,
+        Some(datetime!(2021-02-28 00:00 UTC))
+    );
+    assert_eq!(encounter.period_end, Some(datetime!(2021-04-30 00:00 UTC)));
+}
+
+#[tokio::test]
+async fn required_subject_is_enforced() {
+    let mut json: serde_json::Value = serde_json::from_str(ENCOUNTER_1).unwrap();
+    json.as_object_mut().unwrap().remove("subject");
+    let fhir_encounter = serde_json::from_value::<Encounter>(json).unwrap();
+
+    assert!(
+        convert_encounter(
+            &fhir_encounter,
+            &FacilityProfile::default(),
+            &mut ConversionStats::default()
+        )
+        .is_ok()
+    );
+
+    let profile = FacilityProfile {
+        required: vec!["Encounter.subject".to_string()],
+        ..Default::default()
+    };
+    let err =
+        convert_encounter(&fhir_encounter, &profile, &mut ConversionStats::default()).unwrap_err();
+    assert_eq!(err.reason, ConversionErrorReason::MissingRequired);
+    assert_eq!(err.path, "Encounter.subject");
+}
+
+#[tokio::test]
+async fn admission_details_are_kept() {
+    let mut json: serde_json::Value = serde_json::from_str(ENCOUNTER_1).unwrap();
+    json["reasonCode"] = serde_json::json!([
+        { "coding": [{ "system": "http://snomed.info/sct", "code": "10509002" }], "text": "Acute bronchitis" }
+    ]);
+    json["diagnosis"] = serde_json::json!([
+        {
+            "condition": { "reference": "Condition/c1" },
+            "use": { "coding": [{ "code": "AD" }] },
+            "rank": 1
+        },
+        { "condition": { "reference": "Condition/c2" } }
+    ]);
+    json["hospitalization"] = serde_json::json!({
+        "admitSource": { "coding": [{ "code": "emd" }] },
+        "reAdmission": { "coding": [{ "code": "R" }] },
+        "dischargeDisposition": { "coding": [{ "code": "home" }] }
+    });
+    json["participant"][0]["individual"]["reference"] = "Practitioner/p1".into();
+    json["location"][0]["location"]["reference"] = "Location/l1".into();
+    json["location"][0]["status"] = "completed".into();
+    json["serviceProvider"]["reference"] = "Organization/o1".into();
+    let fhir_encounter = serde_json::from_value::<Encounter>(json).unwrap();
+
+    let mut stats = ConversionStats::default();
+    let encounter =
+        convert_encounter(&fhir_encounter, &FacilityProfile::default(), &mut stats).unwrap();
+
+    assert_eq!(encounter.types_code, vec!["185347001"]);
+    assert_eq!(
+        encounter.types_description,
+        vec!["Encounter for problem (procedure)"]
+    );
+    assert_eq!(encounter.types_system, vec!["http://snomed.info/sct"]);
+    assert_eq!(encounter.reasons_code, vec!["10509002"]);
+    // No display, so the text is used
+    assert_eq!(encounter.reasons_description, vec!["Acute bronchitis"]);
+    assert_eq!(encounter.diagnoses_condition, vec!["c1", "c2"]);
+    assert_eq!(encounter.diagnoses_use, vec!["AD", ""]);
+    assert_eq!(encounter.diagnoses_rank, vec![Some(1), None]);
+    assert_eq!(encounter.admit_source, "emd");
+    assert_eq!(encounter.re_admission, "R");
+    assert_eq!(encounter.discharge_disposition, "home");
+    assert_eq!(encounter.participants_type, vec!["PPRF"]);
+    assert_eq!(encounter.participants_individual_type, vec!["Practitioner"]);
+    assert_eq!(encounter.participants_individual, vec!["p1"]);
+    assert_eq!(
+        encounter.participants_period_start,
+        vec![Some(datetime!(1998-04-16 15:59:37 -04:00))]
+    );
+    assert_eq!(
+        encounter.participants_period_end_resolution,
+        vec![TimeResolution::Second]
+    );
+    assert_eq!(encounter.locations_location, vec!["l1"]);
+    assert!(matches!(
+        encounter.locations_status[..],
+        [EncounterLocationStatus::Completed]
+    ));
+    assert_eq!(encounter.locations_period_start, vec![None]);
+    assert_eq!(
+        encounter.locations_period_start_resolution,
+        vec![TimeResolution::Unknown]
+    );
+    assert_eq!(encounter.service_provider, "o1");
+    assert!(stats.is_empty());
+}
+
+#[tokio::test]
+async fn conditional_references_are_left_empty() {
+    let fhir_encounter = serde_json::from_str::<Encounter>(ENCOUNTER_1).unwrap();
+
+    let mut stats = ConversionStats::default();
+    let encounter =
+        convert_encounter(&fhir_encounter, &FacilityProfile::default(), &mut stats).unwrap();
+
+    assert_eq!(encounter.participants_individual, vec![""]);
+    assert_eq!(encounter.locations_location, vec![""]);
+    assert_eq!(encounter.service_provider, "");
+    for path in [
+        "Encounter.participant.individual",
+        "Encounter.location.location",
+        "Encounter.serviceProvider",
+    ] {
+        assert_eq!(stats.count("default", path, FieldOutcome::Truncated), 1);
+    }
+}
+
+#[tokio::test]
+async fn location_must_be_a_location() {
+    let mut json: serde_json::Value = serde_json::from_str(ENCOUNTER_1).unwrap();
+    json["location"][0]["location"]["reference"] = "Organization/o1".into();
+    let fhir_encounter = serde_json::from_value::<Encounter>(json).unwrap();
+
+    let err = convert_encounter(
+        &fhir_encounter,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap_err();
+    assert_eq!(err.reason, ConversionErrorReason::BadReferenceType);
+    assert_eq!(err.path, "Encounter.location.location.reference");
+}
@@ -0,0 +1,123 @@
+mod utils;
+
+use feeder::{
+    fhir_r4b_shemav1::convert_immunization,
+    profile::FacilityProfile,
+    schemav1::{
+        FieldOutcome, Resource, TimeResolution,
+        db_ops::{insert_bundle, install_schema_v1},
+    },
+    stats::ConversionStats,
+};
+use fhir_model::r4b::resources::Immunization;
+use serde_json::json;
+use time::macros::datetime;
+use utils::{connect_to_clickhouse_test_container, drop_db};
+
+const IMMUNIZATION_1: &str = include_str!("assets/immunization_1.json");
+
+#[tokio::test]
+async fn parse_and_insert() {
+    let client = connect_to_clickhouse_test_container();
+
+    let fhir_immunization = serde_json::from_str::<Immunization>(IMMUNIZATION_1).unwrap();
+    let immunization = convert_immunization(
+        &fhir_immunization,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+
+    drop_db(&client, "attempt_1_1").await;
+    install_schema_v1(&client, "attempt_1_1").await.unwrap();
+
+    insert_bundle(
+        &client,
+        "attempt_1_1",
+        &[Resource::Immunization(immunization)],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn cvx_coding_is_preferred() {
+    let fhir_immunization = serde_json::from_str::<Immunization>(IMMUNIZATION_1).unwrap();
+    let mut stats = ConversionStats::default();
+    let immunization =
+        convert_immunization(&fhir_immunization, &FacilityProfile::default(), &mut stats).unwrap();
+
+    assert_eq!(immunization.status, "completed");
+    assert_eq!(immunization.vaccine_code, "140");
+    assert_eq!(immunization.vaccine_system, "http://hl7.org/fhir/sid/cvx");
+    assert_eq!(
+        immunization.vaccine_description,
+        "Influenza, seasonal, injectable, preservative free"
+    );
+    assert_eq!(immunization.subject, "7d9aa431-cd72-8aa2-9559-5920937d9330");
+    assert_eq!(
+        immunization.occurrence,
+        Some(datetime!(2016-10-03 15:59:37 -04:00))
+    );
+    assert_eq!(immunization.occurrence_resolution, TimeResolution::Second);
+    assert_eq!(immunization.recorded_resolution, TimeResolution::Day);
+    assert_eq!(immunization.lot_number, "UT4811AA");
+    assert_eq!(
+        stats.count("default", "Immunization.recorded", FieldOutcome::Defaulted),
+        0
+    );
+}
+
+#[tokio::test]
+async fn not_done_keeps_status_reason_and_occurrence_string() {
+    let mut json: serde_json::Value = serde_json::from_str(IMMUNIZATION_1).unwrap();
+    let object = json.as_object_mut().unwrap();
+    object.remove("occurrenceDateTime");
+    object.remove("lotNumber");
+    object.extend(
+        json!({
+            "status": "not-done",
+            "statusReason": { "coding": [{
+                "system": "http://terminology.hl7.org/CodeSystem/v3-ActReason",
+                "code": "PATOBJ"
+            }] },
+            "occurrenceString": "last flu season"
+        })
+        .as_object()
+        .unwrap()
+        .clone(),
+    );
+    let fhir_immunization: Immunization = serde_json::from_value(json).unwrap();
+
+    let immunization = convert_immunization(
+        &fhir_immunization,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+    assert_eq!(immunization.status, "not-done");
+    assert_eq!(immunization.status_reason, "PATOBJ");
+    assert_eq!(immunization.occurrence, None);
+    assert_eq!(immunization.occurrence_text, "last flu season");
+    assert_eq!(immunization.lot_number, "");
+}
+
+#[tokio::test]
+async fn partial_occurrence_is_counted_under_its_type() {
+    let mut json: serde_json::Value = serde_json::from_str(IMMUNIZATION_1).unwrap();
+    json["occurrenceDateTime"] = "2016-10".into();
+    let fhir_immunization: Immunization = serde_json::from_value(json).unwrap();
+
+    let mut stats = ConversionStats::default();
+    let immunization =
+        convert_immunization(&fhir_immunization, &FacilityProfile::default(), &mut stats).unwrap();
+    assert_eq!(immunization.occurrence_resolution, TimeResolution::Month);
+    assert_eq!(
+        stats.count(
+            "default",
+            "Immunization.occurrenceDateTime",
+            FieldOutcome::Defaulted
+        ),
+        1
+    );
+}
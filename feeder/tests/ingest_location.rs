@@ -0,0 +1,129 @@
+mod utils;
+
+use feeder::{
+    fhir_r4b_shemav1::{convert_bundle, convert_location},
+    profile::FacilityProfile,
+    schemav1::{
+        LocationClosure, LocationEdge,
+        db_ops::{insert_bundle, install_schema_v1, refresh_location_closure},
+        location_closure,
+    },
+    stats::ConversionStats,
+};
+use fhir_model::r4b::resources::{Bundle, Location};
+use utils::{bundle_of, connect_to_clickhouse_test_container, drop_db};
+
+const LOCATION_1: &str = include_str!("assets/location_1.json");
+
+/// A Location with only an id, a physical type and a parent.
+fn location(id: &str, physical_type: &str, part_of: Option<&str>) -> String {
+    let part_of = part_of
+        .map(|part_of| format!(r#", "partOf": {{ "reference": "Location/{}" }}"#, part_of))
+        .unwrap_or_default();
+    format!(
+        r#"{{ "resourceType": "Location", "id": "{}", "physicalType": {{ "coding": [{{ "code": "{}" }}] }}{} }}"#,
+        id, physical_type, part_of
+    )
+}
+
+#[tokio::test]
+async fn parse_and_insert() {
+    let client = connect_to_clickhouse_test_container();
+
+    let bundle = bundle_of(&[
+        LOCATION_1,
+        &location("room-4n-12", "ro", Some("ward-4n")),
+        &location("ward-4n", "wa", Some("building-main")),
+        &location("building-main", "bu", Some("site-leominster")),
+        &location("site-leominster", "si", None),
+    ]);
+    let fhir_bundle: Bundle = serde_json::from_str(&bundle).unwrap();
+    let converted = convert_bundle(
+        &fhir_bundle,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+    assert_eq!(converted.resources.len(), 5);
+
+    drop_db(&client, "attempt_1_1").await;
+    install_schema_v1(&client, "attempt_1_1").await.unwrap();
+
+    insert_bundle(&client, "attempt_1_1", &converted.resources)
+        .await
+        .unwrap();
+    refresh_location_closure(&client, "attempt_1_1")
+        .await
+        .unwrap();
+
+    let under_site: Vec<String> = client
+        .query(
+            "SELECT descendant FROM attempt_1_1.LocationClosure \
+             WHERE ancestor = 'site-leominster' ORDER BY depth",
+        )
+        .fetch_all()
+        .await
+        .unwrap();
+    assert_eq!(
+        under_site,
+        vec![
+            "site-leominster",
+            "building-main",
+            "ward-4n",
+            "room-4n-12",
+            "bed-4n-12a"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn bed_keeps_its_room() {
+    let fhir_location = serde_json::from_str::<Location>(LOCATION_1).unwrap();
+    let mut stats = ConversionStats::default();
+    let location =
+        convert_location(&fhir_location, &FacilityProfile::default(), &mut stats).unwrap();
+
+    assert_eq!(location.status, "active");
+    assert_eq!(location.mode, "instance");
+    assert_eq!(location.types, vec!["HU"]);
+    assert_eq!(location.physical_type, "bd");
+    assert_eq!(location.part_of, "room-4n-12");
+    assert_eq!(
+        location.managing_organization,
+        "ef58ea08-d883-3957-8300-150554edc8fb"
+    );
+    assert_eq!(location.address_line, "60 HOSPITAL ROAD");
+    assert_eq!(location.address_postal_code, "01453");
+    assert_eq!(location.latitude, Some(42.5195));
+    assert_eq!(location.longitude, Some(-71.7606));
+    assert!(stats.is_empty());
+}
+
+#[tokio::test]
+async fn location_without_parent_is_a_root() {
+    let fhir_location =
+        serde_json::from_str::<Location>(&location("site-leominster", "si", None)).unwrap();
+    let location = convert_location(
+        &fhir_location,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+    assert_eq!(location.part_of, "");
+    assert_eq!(location.status, "");
+    assert_eq!(location.latitude, None);
+
+    // A root only has its own row in the closure
+    let closure = location_closure(&[LocationEdge {
+        id: location.id.clone(),
+        part_of: location.part_of.clone(),
+    }]);
+    assert_eq!(
+        closure,
+        vec![LocationClosure {
+            ancestor: "site-leominster".to_string(),
+            descendant: "site-leominster".to_string(),
+            depth: 0,
+        }]
+    );
+}
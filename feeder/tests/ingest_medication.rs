@@ -0,0 +1,329 @@
+mod utils;
+
+use feeder::{
+    fhir_r4b_shemav1::{
+        BundleMedications, ConversionErrorReason, RXNORM, convert_bundle,
+        convert_medication_administration, convert_medication_request,
+        convert_medication_statement,
+    },
+    profile::FacilityProfile,
+    schemav1::{
+        FieldOutcome, Resource, TimeResolution,
+        db_ops::{insert_bundle, install_schema_v1},
+    },
+    stats::ConversionStats,
+};
+use fhir_model::r4b::resources::{
+    Bundle, MedicationAdministration, MedicationRequest, MedicationStatement,
+};
+use serde_json::json;
+use time::macros::datetime;
+use utils::{connect_to_clickhouse_test_container, drop_db};
+
+const MEDICATION_1: &str = include_str!("assets/medication_1.json");
+const MEDICATION_REQUEST_1: &str = include_str!("assets/medication_request_1.json");
+
+/// MEDICATION_REQUEST_1 and the Medication it points at, the way Synthea
+/// bundles them.
+fn request_bundle() -> Bundle {
+    serde_json::from_value(json!({
+        "resourceType": "Bundle",
+        "type": "transaction",
+        "entry": [
+            {
+                "fullUrl": "urn:uuid:5b2e9f0c-7d3a-4e61-b8c4-1f9a6d2e3c58",
+                "resource": serde_json::from_str::<serde_json::Value>(MEDICATION_REQUEST_1).unwrap()
+            },
+            {
+                "fullUrl": "urn:uuid:c6a8b5e2-4f1d-4a7b-9e3c-2d8f6b1a0e47",
+                "resource": serde_json::from_str::<serde_json::Value>(MEDICATION_1).unwrap()
+            }
+        ]
+    }))
+    .unwrap()
+}
+
+fn medication_statement(extra: serde_json::Value) -> MedicationStatement {
+    let mut json = json!({
+        "resourceType": "MedicationStatement",
+        "id": "statement-1",
+        "status": "active",
+        "subject": { "reference": "Patient/7d9aa431-cd72-8aa2-9559-5920937d9330" }
+    });
+    json.as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    serde_json::from_value(json).unwrap()
+}
+
+#[tokio::test]
+async fn parse_and_insert() {
+    let client = connect_to_clickhouse_test_container();
+
+    let converted = convert_bundle(
+        &request_bundle(),
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+
+    drop_db(&client, "attempt_1_1").await;
+    install_schema_v1(&client, "attempt_1_1").await.unwrap();
+
+    insert_bundle(&client, "attempt_1_1", &converted.resources)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn request_resolves_medication_in_bundle() {
+    let mut stats = ConversionStats::default();
+    let converted =
+        convert_bundle(&request_bundle(), &FacilityProfile::default(), &mut stats).unwrap();
+
+    // The Medication itself has no row of its own
+    assert_eq!(converted.resources.len(), 1);
+    let Resource::MedicationRequest(request) = &converted.resources[0] else {
+        panic!(
+            "expected a MedicationRequest, got {:?}",
+            converted.resources[0]
+        );
+    };
+    assert_eq!(request.status, "active");
+    assert_eq!(request.intent, "order");
+    assert_eq!(request.category, vec!["community"]);
+    assert_eq!(request.medication, "c6a8b5e2-4f1d-4a7b-9e3c-2d8f6b1a0e47");
+    // RxNorm wins over the first coding
+    assert_eq!(request.medication_code, "199224");
+    assert_eq!(
+        request.medication_description,
+        "anastrozole 1 MG Oral Tablet"
+    );
+    assert_eq!(request.medication_system, RXNORM);
+    assert_eq!(request.subject, "7d9aa431-cd72-8aa2-9559-5920937d9330");
+    assert_eq!(request.encounter, "00017486-6c88-2b8a-ca28-7f147efb8848");
+    assert_eq!(
+        request.authored_on,
+        Some(datetime!(1998-04-16 15:59:37 -04:00))
+    );
+    assert_eq!(request.authored_on_resolution, TimeResolution::Second);
+
+    assert_eq!(request.dosages_sequence, vec![Some(1)]);
+    assert_eq!(request.dosages_route, vec!["26643006"]);
+    assert_eq!(request.dosages_dose_value, vec![Some(1.0)]);
+    assert_eq!(request.dosages_dose_unit, vec!["tablet"]);
+    assert_eq!(request.dosages_frequency, vec![Some(1)]);
+    assert_eq!(request.dosages_period, vec![Some(1.0)]);
+    assert_eq!(request.dosages_period_unit, vec!["d"]);
+    assert_eq!(request.dosages_timing_code, vec!["QD"]);
+
+    // The requester is a conditional reference
+    assert_eq!(request.requester, "");
+    assert_eq!(
+        stats.count(
+            "default",
+            "MedicationRequest.requester",
+            FieldOutcome::Truncated
+        ),
+        1
+    );
+}
+
+#[tokio::test]
+async fn contained_medication_is_resolved() {
+    let mut json: serde_json::Value = serde_json::from_str(MEDICATION_REQUEST_1).unwrap();
+    json["contained"] = json!([serde_json::from_str::<serde_json::Value>(MEDICATION_1).unwrap()]);
+    json["contained"][0]["id"] = "med".into();
+    json["medicationReference"]["reference"] = "#med".into();
+    let fhir_request = serde_json::from_value::<MedicationRequest>(json).unwrap();
+
+    let request = convert_medication_request(
+        &fhir_request,
+        &BundleMedications::default(),
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+    assert_eq!(request.medication, "med");
+    assert_eq!(request.medication_code, "199224");
+}
+
+#[tokio::test]
+async fn unresolved_medication_can_be_required() {
+    let mut json: serde_json::Value = serde_json::from_str(MEDICATION_REQUEST_1).unwrap();
+    json["medicationReference"]["reference"] = "Medication/elsewhere".into();
+    let fhir_request = serde_json::from_value::<MedicationRequest>(json).unwrap();
+
+    let mut stats = ConversionStats::default();
+    let request = convert_medication_request(
+        &fhir_request,
+        &BundleMedications::default(),
+        &FacilityProfile::default(),
+        &mut stats,
+    )
+    .unwrap();
+    assert_eq!(request.medication, "elsewhere");
+    assert_eq!(request.medication_code, "");
+    assert_eq!(
+        stats.count(
+            "default",
+            "MedicationRequest.medication",
+            FieldOutcome::Missing
+        ),
+        1
+    );
+
+    let profile = FacilityProfile {
+        required: vec!["MedicationRequest.medication".to_string()],
+        ..Default::default()
+    };
+    let err = convert_medication_request(
+        &fhir_request,
+        &BundleMedications::default(),
+        &profile,
+        &mut ConversionStats::default(),
+    )
+    .unwrap_err();
+    assert_eq!(err.reason, ConversionErrorReason::MissingRequired);
+    assert_eq!(err.path, "MedicationRequest.medication");
+}
+
+#[tokio::test]
+async fn statement_keeps_its_period_and_dose_range() {
+    let fhir_statement = medication_statement(json!({
+        "medicationCodeableConcept": {
+            "coding": [{ "system": "http://www.nlm.nih.gov/research/umls/rxnorm", "code": "314076" }],
+            "text": "lisinopril 10 MG Oral Tablet"
+        },
+        "context": { "reference": "EpisodeOfCare/1" },
+        "effectivePeriod": { "start": "2001-02", "end": "2001-05-03" },
+        "dosage": [{
+            "doseAndRate": [{ "doseRange": { "low": { "value": 1, "unit": "tablet" }, "high": { "value": 2, "unit": "tablet" } } }],
+            "timing": { "repeat": { "frequency": 2, "period": 1, "periodUnit": "d" } }
+        }]
+    }));
+
+    let mut stats = ConversionStats::default();
+    let statement = convert_medication_statement(
+        &fhir_statement,
+        &BundleMedications::default(),
+        &FacilityProfile::default(),
+        &mut stats,
+    )
+    .unwrap();
+    assert_eq!(statement.medication, "");
+    assert_eq!(statement.medication_code, "314076");
+    assert_eq!(
+        statement.medication_description,
+        "lisinopril 10 MG Oral Tablet"
+    );
+    assert_eq!(
+        statement.effective_start,
+        Some(datetime!(2001-02-01 0:00 UTC))
+    );
+    assert_eq!(statement.effective_start_resolution, TimeResolution::Month);
+    assert_eq!(
+        statement.effective_end,
+        Some(datetime!(2001-05-03 0:00 UTC))
+    );
+    assert_eq!(statement.dosages_dose_value, vec![None]);
+    assert_eq!(statement.dosages_dose_low, vec![Some(1.0)]);
+    assert_eq!(statement.dosages_dose_high, vec![Some(2.0)]);
+    assert_eq!(statement.dosages_dose_unit, vec!["tablet"]);
+    assert_eq!(statement.dosages_frequency, vec![Some(2)]);
+
+    // An EpisodeOfCare context has nowhere to go
+    assert_eq!(statement.encounter, "");
+    assert_eq!(
+        stats.count(
+            "default",
+            "MedicationStatement.context",
+            FieldOutcome::Truncated
+        ),
+        1
+    );
+}
+
+#[tokio::test]
+async fn group_subjects_are_left_empty() {
+    let mut fhir_statement = medication_statement(json!({
+        "medicationCodeableConcept": {
+            "coding": [{ "system": "http://www.nlm.nih.gov/research/umls/rxnorm", "code": "314076" }]
+        }
+    }));
+    fhir_statement.subject.reference = Some("Group/1".to_string());
+
+    let mut stats = ConversionStats::default();
+    let statement = convert_medication_statement(
+        &fhir_statement,
+        &BundleMedications::default(),
+        &FacilityProfile::default(),
+        &mut stats,
+    )
+    .unwrap();
+    assert_eq!(statement.subject, "");
+    assert_eq!(
+        stats.count(
+            "default",
+            "MedicationStatement.subject",
+            FieldOutcome::Truncated
+        ),
+        1
+    );
+}
+
+#[tokio::test]
+async fn administration_keeps_its_dose() {
+    let fhir_administration = serde_json::from_value::<MedicationAdministration>(json!({
+        "resourceType": "MedicationAdministration",
+        "id": "administration-1",
+        "status": "completed",
+        "medicationReference": { "reference": "Medication/c6a8b5e2-4f1d-4a7b-9e3c-2d8f6b1a0e47" },
+        "subject": { "reference": "Patient/7d9aa431-cd72-8aa2-9559-5920937d9330" },
+        "context": { "reference": "Encounter/00017486-6c88-2b8a-ca28-7f147efb8848" },
+        "effectiveDateTime": "1998-04-16T16:30:00-04:00",
+        "request": { "reference": "MedicationRequest/5b2e9f0c-7d3a-4e61-b8c4-1f9a6d2e3c58" },
+        "dosage": {
+            "text": "1 tablet",
+            "route": { "coding": [{ "code": "26643006" }] },
+            "dose": { "value": 1, "unit": "tablet" }
+        }
+    }))
+    .unwrap();
+    let bundle = serde_json::from_value::<Bundle>(json!({
+        "resourceType": "Bundle",
+        "type": "collection",
+        "entry": [{
+            "fullUrl": "https://example.com/base/Medication/c6a8b5e2-4f1d-4a7b-9e3c-2d8f6b1a0e47",
+            "resource": serde_json::from_str::<serde_json::Value>(MEDICATION_1).unwrap()
+        }]
+    }))
+    .unwrap();
+
+    let administration = convert_medication_administration(
+        &fhir_administration,
+        &BundleMedications::from_bundle(&bundle),
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+    assert_eq!(administration.status, "completed");
+    assert_eq!(administration.medication_code, "199224");
+    assert_eq!(
+        administration.encounter,
+        "00017486-6c88-2b8a-ca28-7f147efb8848"
+    );
+    assert_eq!(
+        administration.effective_start,
+        Some(datetime!(1998-04-16 16:30:00 -04:00))
+    );
+    assert_eq!(administration.effective_end, None);
+    assert_eq!(
+        administration.request,
+        "5b2e9f0c-7d3a-4e61-b8c4-1f9a6d2e3c58"
+    );
+    assert_eq!(administration.dosage_text, "1 tablet");
+    assert_eq!(administration.route, "26643006");
+    assert_eq!(administration.dose_value, Some(1.0));
+    assert_eq!(administration.dose_unit, "tablet");
+}
@@ -0,0 +1,236 @@
+mod utils;
+
+use feeder::{
+    fhir_r4b_shemav1::{ConversionErrorReason, convert_bundle, convert_observation},
+    profile::FacilityProfile,
+    schemav1::{
+        FieldOutcome, ObservationStatus, Resource, TimeResolution, ValueType,
+        db_ops::{insert_bundle, install_schema_v1},
+    },
+    stats::ConversionStats,
+};
+use fhir_model::r4b::resources::{Bundle, Observation};
+use serde_json::json;
+use time::macros::datetime;
+use utils::{bundle_of, connect_to_clickhouse_test_container, drop_db};
+
+const OBSERVATION_1: &str = include_str!("assets/observation_1.json");
+const PATIENT_1: &str = include_str!("assets/patient_1.json");
+
+fn observation_with_value(value: serde_json::Value) -> Observation {
+    let mut json: serde_json::Value = serde_json::from_str(OBSERVATION_1).unwrap();
+    let object = json.as_object_mut().unwrap();
+    object.remove("component");
+    object.extend(value.as_object().unwrap().clone());
+    serde_json::from_value(json).unwrap()
+}
+
+fn convert(src: &Observation) -> feeder::schemav1::Observation {
+    convert_observation(
+        src,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn parse_and_insert() {
+    let client = connect_to_clickhouse_test_container();
+
+    let fhir_observation = serde_json::from_str::<Observation>(OBSERVATION_1).unwrap();
+    let observation = convert(&fhir_observation);
+
+    drop_db(&client, "attempt_1_1").await;
+    install_schema_v1(&client, "attempt_1_1").await.unwrap();
+
+    insert_bundle(
+        &client,
+        "attempt_1_1",
+        &[Resource::Observation(observation)],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn blood_pressure_panel_keeps_its_components() {
+    let fhir_observation = serde_json::from_str::<Observation>(OBSERVATION_1).unwrap();
+    let mut stats = ConversionStats::default();
+    let observation =
+        convert_observation(&fhir_observation, &FacilityProfile::default(), &mut stats).unwrap();
+
+    assert!(matches!(observation.status, ObservationStatus::Final));
+    assert_eq!(observation.code, "85354-9");
+    assert_eq!(observation.code_system, "http://loinc.org");
+    assert_eq!(observation.category, vec!["vital-signs"]);
+    assert_eq!(observation.subject, "7d9aa431-cd72-8aa2-9559-5920937d9330");
+    assert_eq!(
+        observation.encounter,
+        "00017486-6c88-2b8a-ca28-7f147efb8848"
+    );
+    assert_eq!(
+        observation.effective_start,
+        Some(datetime!(1998-04-16 15:59:37 -04:00))
+    );
+    assert_eq!(
+        observation.effective_start_resolution,
+        TimeResolution::Second
+    );
+    assert_eq!(observation.effective_end, None);
+    assert_eq!(
+        observation.issued,
+        Some(datetime!(1998-04-16 15:59:37.521 -04:00))
+    );
+    assert_eq!(observation.value_type, ValueType::None);
+
+    assert_eq!(observation.components_code, vec!["8462-4", "8480-6"]);
+    assert_eq!(
+        observation.components_value_type,
+        vec![ValueType::Quantity, ValueType::Quantity]
+    );
+    assert_eq!(
+        observation.components_value_quantity,
+        vec![Some(82.0), Some(131.0)]
+    );
+    assert_eq!(observation.components_value_unit, vec!["mm[Hg]", "mm[Hg]"]);
+    assert_eq!(observation.components_value_period_start, vec![None, None]);
+
+    // Sub-second issued times don't fit in a DateTime column
+    assert_eq!(
+        stats.count("default", "Observation.issued", FieldOutcome::Truncated),
+        1
+    );
+}
+
+#[tokio::test]
+async fn every_value_type_is_kept() {
+    let observation = convert(&observation_with_value(json!({
+        "valueQuantity": { "value": 5.5, "comparator": "<", "code": "mmol/L" }
+    })));
+    assert_eq!(observation.value_type, ValueType::Quantity);
+    assert_eq!(observation.value_quantity, Some(5.5));
+    assert_eq!(observation.value_comparator, "<");
+    assert_eq!(observation.value_unit, "mmol/L");
+
+    let observation = convert(&observation_with_value(json!({
+        "valueCodeableConcept": { "coding": [{ "system": "http://snomed.info/sct", "code": "260385009" }], "text": "Negative" }
+    })));
+    assert_eq!(observation.value_type, ValueType::CodeableConcept);
+    assert_eq!(observation.value_code, "260385009");
+    assert_eq!(observation.value_code_description, "Negative");
+    assert_eq!(observation.value_code_system, "http://snomed.info/sct");
+
+    let observation = convert(&observation_with_value(json!({ "valueString": "clear" })));
+    assert_eq!(observation.value_type, ValueType::String);
+    assert_eq!(observation.value_string, "clear");
+
+    let observation = convert(&observation_with_value(json!({ "valueBoolean": false })));
+    assert_eq!(observation.value_type, ValueType::Boolean);
+    assert_eq!(observation.value_boolean, Some(false));
+
+    let observation = convert(&observation_with_value(json!({ "valueInteger": 3 })));
+    assert_eq!(observation.value_type, ValueType::Integer);
+    assert_eq!(observation.value_integer, Some(3));
+
+    let observation = convert(&observation_with_value(json!({
+        "valueRange": { "low": { "value": 1.0, "unit": "g" }, "high": { "value": 2.0, "unit": "g" } }
+    })));
+    assert_eq!(observation.value_type, ValueType::Range);
+    assert_eq!(observation.value_low, Some(1.0));
+    assert_eq!(observation.value_high, Some(2.0));
+    assert_eq!(observation.value_unit, "g");
+
+    let observation = convert(&observation_with_value(json!({
+        "valueRatio": { "numerator": { "value": 1.0, "unit": "mg" }, "denominator": { "value": 10.0, "unit": "mL" } }
+    })));
+    assert_eq!(observation.value_type, ValueType::Ratio);
+    assert_eq!(observation.value_numerator, Some(1.0));
+    assert_eq!(observation.value_unit, "mg");
+    assert_eq!(observation.value_denominator, Some(10.0));
+    assert_eq!(observation.value_denominator_unit, "mL");
+
+    let mut stats = ConversionStats::default();
+    let observation = convert_observation(
+        &observation_with_value(json!({ "valuePeriod": { "start": "2001-02" } })),
+        &FacilityProfile::default(),
+        &mut stats,
+    )
+    .unwrap();
+    assert_eq!(observation.value_type, ValueType::Period);
+    assert_eq!(
+        observation.value_period_start,
+        Some(datetime!(2001-02-01 0:00 UTC))
+    );
+    assert_eq!(
+        observation.value_period_start_resolution,
+        TimeResolution::Month
+    );
+    assert_eq!(observation.value_period_end, None);
+    assert_eq!(
+        stats.count(
+            "default",
+            "Observation.valuePeriod.start",
+            FieldOutcome::Defaulted
+        ),
+        1
+    );
+}
+
+#[tokio::test]
+async fn unsupported_values_are_counted() {
+    let fhir_observation = observation_with_value(json!({
+        "valueTime": "10:00:00",
+        "dataAbsentReason": { "coding": [{ "code": "unknown" }] }
+    }));
+    let mut stats = ConversionStats::default();
+    let observation =
+        convert_observation(&fhir_observation, &FacilityProfile::default(), &mut stats).unwrap();
+
+    assert_eq!(observation.value_type, ValueType::Other);
+    assert_eq!(observation.data_absent_reason, "unknown");
+    assert_eq!(
+        stats.count("default", "Observation.value", FieldOutcome::Truncated),
+        1
+    );
+}
+
+#[tokio::test]
+async fn group_subjects_are_left_empty() {
+    let mut json: serde_json::Value = serde_json::from_str(OBSERVATION_1).unwrap();
+    json["id"] = "group-observation".into();
+    json["subject"]["reference"] = "Group/1".into();
+    let bundle = bundle_of(&[PATIENT_1, OBSERVATION_1, &json.to_string()]);
+    let fhir_bundle: Bundle = serde_json::from_str(&bundle).unwrap();
+
+    let mut stats = ConversionStats::default();
+    let converted = convert_bundle(&fhir_bundle, &FacilityProfile::default(), &mut stats).unwrap();
+
+    assert_eq!(converted.resources.len(), 3);
+    let Resource::Observation(observation) = &converted.resources[2] else {
+        panic!("expected an Observation");
+    };
+    assert_eq!(observation.id, "group-observation");
+    assert_eq!(observation.subject, "");
+    assert_eq!(
+        stats.count("default", "Observation.subject", FieldOutcome::Truncated),
+        1
+    );
+}
+
+#[tokio::test]
+async fn subject_must_be_a_patient_group_device_or_location() {
+    let mut json: serde_json::Value = serde_json::from_str(OBSERVATION_1).unwrap();
+    json["subject"]["reference"] = "Practitioner/1".into();
+    let fhir_observation = serde_json::from_value::<Observation>(json).unwrap();
+
+    let err = convert_observation(
+        &fhir_observation,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap_err();
+    assert_eq!(err.reason, ConversionErrorReason::BadReferenceType);
+    assert_eq!(err.path, "Observation.subject.reference");
+    assert_eq!(err.resource_type, "Observation");
+}
@@ -1,8 +1,21 @@
 mod utils;
 
-use feeder::{fhir_r4b_shemav1::convert_patient, schemav1::db_ops::install_schema_v1};
+use feeder::{
+    fhir_r4b_shemav1::{
+        ConversionErrorReason, US_CORE_BIRTH_SEX, US_CORE_ETHNICITY, US_CORE_RACE, convert_patient,
+        keep_as_text,
+    },
+    profile::{FacilityProfile, FacilityProfiles},
+    schemav1::db_ops::install_schema_v1,
+    schemav1::{
+        ContactPointSystem, ContactPointUse, Deceased, FieldOutcome, Gender, IdentifierUse,
+        NameUse, TimeResolution,
+    },
+    stats::ConversionStats,
+};
 use fhir_model::r4b::resources::Patient;
-use utils::{drop_db, connect_to_clickhouse_test_container};
+use time::macros::datetime;
+use utils::{connect_to_clickhouse_test_container, drop_db};
 
 const PATIENT_1: &str = include_str!("assets/patient_1.json");
 
@@ -11,10 +24,14 @@ async fn parse_and_insert() {
     let client = connect_to_clickhouse_test_container();
 
     let fhir_patient = serde_json::from_str::<Patient>(PATIENT_1).unwrap();
-    let aggregate_patient = convert_patient(&fhir_patient).unwrap();
+    let aggregate_patient = convert_patient(
+        &fhir_patient,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
     dbg!(&aggregate_patient);
 
-
     drop_db(&client, "attempt_1_1").await;
     install_schema_v1(&client, "attempt_1_1").await.unwrap();
 
@@ -22,3 +39,292 @@ async fn parse_and_insert() {
     insert.write(&aggregate_patient).await.unwrap();
     insert.end().await.unwrap();
 }
+
+#[tokio::test]
+async fn missing_id_is_reported() {
+    let mut json: serde_json::Value = serde_json::from_str(PATIENT_1).unwrap();
+    json.as_object_mut().unwrap().remove("id");
+    let fhir_patient = serde_json::from_value::<Patient>(json).unwrap();
+
+    let err = convert_patient(
+        &fhir_patient,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap_err();
+    assert_eq!(err.reason, ConversionErrorReason::MissingId);
+    assert_eq!(err.path, "Patient.id");
+    assert_eq!(err.resource_id, None);
+}
+
+#[tokio::test]
+async fn every_address_element_is_kept() {
+    let fhir_patient = serde_json::from_str::<Patient>(PATIENT_1).unwrap();
+    let patient = convert_patient(
+        &fhir_patient,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+
+    assert_eq!(patient.addresses_line, vec!["534 Erewhon St"]);
+    assert_eq!(patient.addresses_district, vec!["Rainbow"]);
+    assert_eq!(patient.addresses_state, vec!["Vic"]);
+    assert_eq!(patient.addresses_postal_code, vec!["3999"]);
+    assert_eq!(patient.addresses_country, vec![""]);
+    assert_eq!(
+        patient.addresses_text,
+        vec!["534 Erewhon St PeasantVille, Rainbow, Vic  3999"]
+    );
+    assert_eq!(
+        patient.addresses_period_start,
+        vec![Some(datetime!(1974-12-25 00:00 UTC))]
+    );
+    assert_eq!(patient.addresses_period_end, vec![None]);
+}
+
+#[tokio::test]
+async fn demographics_are_kept() {
+    let mut json: serde_json::Value = serde_json::from_str(PATIENT_1).unwrap();
+    json["maritalStatus"] = serde_json::json!({ "coding": [{ "code": "M" }], "text": "Married" });
+    json["communication"] = serde_json::json!([
+        { "language": { "coding": [{ "code": "nl-NL" }] }, "preferred": true },
+        { "language": { "text": "English" } },
+    ]);
+    let fhir_patient = serde_json::from_value::<Patient>(json).unwrap();
+    let patient = convert_patient(
+        &fhir_patient,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+
+    assert!(matches!(patient.gender, Gender::Male));
+    assert_eq!(patient.active, Some(true));
+    assert_eq!(patient.marital_status, "M");
+    assert_eq!(patient.managing_organization, "1");
+
+    assert!(matches!(
+        patient.identifiers_use[..],
+        [IdentifierUse::Usual]
+    ));
+    assert_eq!(patient.identifiers_type, vec!["MR"]);
+    assert_eq!(patient.identifiers_value, vec!["12345"]);
+
+    assert_eq!(patient.telecom_value.len(), 4);
+    assert!(matches!(
+        patient.telecom_system[..2],
+        [ContactPointSystem::Unknown, ContactPointSystem::Phone]
+    ));
+    assert!(matches!(patient.telecom_use[2], ContactPointUse::Mobile));
+    assert_eq!(patient.telecom_rank, vec![None, Some(1), Some(2), None]);
+
+    assert_eq!(patient.communication_language, vec!["nl-NL", ""]);
+    assert_eq!(patient.communication_preferred, vec![true, false]);
+}
+
+#[tokio::test]
+async fn unresolvable_managing_organization_is_left_empty() {
+    let mut json: serde_json::Value = serde_json::from_str(PATIENT_1).unwrap();
+    json["managingOrganization"] = serde_json::json!({ "display": "Good Health Clinic" });
+    let fhir_patient = serde_json::from_value::<Patient>(json).unwrap();
+    let mut stats = ConversionStats::default();
+    let patient = convert_patient(&fhir_patient, &FacilityProfile::default(), &mut stats).unwrap();
+
+    assert_eq!(patient.managing_organization, "");
+    assert_eq!(
+        stats.count(
+            "default",
+            "Patient.managingOrganization",
+            FieldOutcome::Truncated
+        ),
+        1
+    );
+}
+
+#[tokio::test]
+async fn every_name_is_kept_and_official_one_displayed() {
+    let mut json: serde_json::Value = serde_json::from_str(PATIENT_1).unwrap();
+    // Put the maiden name first so it isn't picked by position
+    json["name"].as_array_mut().unwrap().reverse();
+    let fhir_patient = serde_json::from_value::<Patient>(json).unwrap();
+    let patient = convert_patient(
+        &fhir_patient,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+
+    assert_eq!(patient.name_family, "Chalmers");
+    assert_eq!(patient.name_given, "Peter James");
+
+    assert!(matches!(
+        patient.names_use[..],
+        [NameUse::Maiden, NameUse::Usual, NameUse::Official]
+    ));
+    assert_eq!(patient.names_family, vec!["Windsor", "", "Chalmers"]);
+    assert_eq!(
+        patient.names_given,
+        vec!["Peter James", "Jim", "Peter James"]
+    );
+    assert_eq!(
+        patient.names_period_end,
+        vec![Some(datetime!(2002-01-01 00:00 UTC)), None, None]
+    );
+}
+
+#[tokio::test]
+async fn missing_birth_date_is_kept_unless_required() {
+    let mut json: serde_json::Value = serde_json::from_str(PATIENT_1).unwrap();
+    json.as_object_mut().unwrap().remove("birthDate");
+    let fhir_patient = serde_json::from_value::<Patient>(json).unwrap();
+
+    let mut stats = ConversionStats::default();
+    let patient = convert_patient(&fhir_patient, &FacilityProfile::default(), &mut stats).unwrap();
+    assert_eq!(patient.birth_time, None);
+    assert!(matches!(
+        patient.birth_time_resolution,
+        TimeResolution::Unknown
+    ));
+    assert_eq!(
+        stats.count("default", "Patient.birthDate", FieldOutcome::Missing),
+        1
+    );
+
+    let profile = FacilityProfile {
+        required: vec!["Patient.birthDate".to_string()],
+        ..Default::default()
+    };
+    let err =
+        convert_patient(&fhir_patient, &profile, &mut ConversionStats::default()).unwrap_err();
+    assert_eq!(err.reason, ConversionErrorReason::MissingRequired);
+    assert_eq!(err.path, "Patient.birthDate");
+}
+
+#[tokio::test]
+async fn death_time_keeps_its_resolution() {
+    let mut json: serde_json::Value = serde_json::from_str(PATIENT_1).unwrap();
+    json.as_object_mut().unwrap().remove("deceasedBoolean");
+    json["deceasedDateTime"] = "2015".into();
+    let fhir_patient = serde_json::from_value::<Patient>(json).unwrap();
+    let patient = convert_patient(
+        &fhir_patient,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+
+    assert!(matches!(patient.deceased, Deceased::Dead));
+    assert_eq!(patient.death_time, Some(datetime!(2015-01-01 00:00 UTC)));
+    assert_eq!(patient.death_time_resolution, TimeResolution::Year);
+    assert_eq!(
+        patient.addresses_period_start_resolution,
+        vec![TimeResolution::Day]
+    );
+    assert_eq!(
+        patient.names_period_end_resolution,
+        vec![
+            TimeResolution::Unknown,
+            TimeResolution::Unknown,
+            TimeResolution::Year
+        ]
+    );
+}
+
+#[tokio::test]
+async fn known_extensions_are_mapped() {
+    let mut json: serde_json::Value = serde_json::from_str(PATIENT_1).unwrap();
+    json["extension"] = serde_json::json!([
+        {
+            "url": US_CORE_RACE,
+            "extension": [
+                { "url": "ombCategory", "valueCoding": { "code": "2106-3" } },
+                { "url": "detailed", "valueCoding": { "code": "2108-9" } },
+                { "url": "text", "valueString": "White" }
+            ]
+        },
+        {
+            "url": US_CORE_ETHNICITY,
+            "extension": [
+                { "url": "ombCategory", "valueCoding": { "code": "2186-5" } },
+                { "url": "text", "valueString": "Not Hispanic or Latino" }
+            ]
+        },
+        { "url": US_CORE_BIRTH_SEX, "valueCode": "M" },
+        { "url": "http://example.org/fhir/favourite-colour", "valueString": "green" }
+    ]);
+    let fhir_patient = serde_json::from_value::<Patient>(json).unwrap();
+
+    let mut stats = ConversionStats::default();
+    let patient = convert_patient(&fhir_patient, &FacilityProfile::default(), &mut stats).unwrap();
+    assert_eq!(patient.race_omb, vec!["2106-3"]);
+    assert_eq!(patient.race_detailed, vec!["2108-9"]);
+    assert_eq!(patient.race_text, "White");
+    assert_eq!(patient.ethnicity_omb, vec!["2186-5"]);
+    assert!(patient.ethnicity_detailed.is_empty());
+    assert_eq!(patient.birth_sex, "M");
+    // The sample carries a patient-birthTime on its birthDate
+    assert_eq!(
+        patient.birth_datetime,
+        Some(datetime!(1974-12-25 14:35:45 -05:00))
+    );
+    // Nobody knows about the local one
+    assert!(patient.extensions_url.is_empty());
+    assert_eq!(
+        stats.count("default", "Patient.extension", FieldOutcome::Truncated),
+        1
+    );
+}
+
+#[tokio::test]
+async fn malformed_extensions_are_truncated() {
+    let mut json: serde_json::Value = serde_json::from_str(PATIENT_1).unwrap();
+    json["extension"] = serde_json::json!([{ "url": US_CORE_BIRTH_SEX, "valueString": "male" }]);
+    json["_birthDate"]["extension"][0] = serde_json::json!({
+        "url": "http://hl7.org/fhir/StructureDefinition/patient-birthTime",
+        "valueString": "half past two"
+    });
+    let fhir_patient = serde_json::from_value::<Patient>(json).unwrap();
+
+    let mut stats = ConversionStats::default();
+    let patient = convert_patient(&fhir_patient, &FacilityProfile::default(), &mut stats).unwrap();
+    assert_eq!(patient.birth_sex, "");
+    assert_eq!(patient.birth_datetime, None);
+    assert_eq!(
+        stats.count("default", "Patient.extension", FieldOutcome::Truncated),
+        1
+    );
+    assert_eq!(
+        stats.count(
+            "default",
+            "Patient.birthDate.extension",
+            FieldOutcome::Truncated
+        ),
+        1
+    );
+}
+
+#[tokio::test]
+async fn local_extensions_can_be_registered() {
+    let mut json: serde_json::Value = serde_json::from_str(PATIENT_1).unwrap();
+    json["extension"] = serde_json::json!([
+        { "url": "http://example.org/fhir/favourite-colour", "valueString": "green" }
+    ]);
+    let fhir_patient = serde_json::from_value::<Patient>(json).unwrap();
+
+    let mut profiles =
+        FacilityProfiles::from_json(r#"{ "facilities": { "olathe": {} } }"#).unwrap();
+    profiles.register_patient_extension("http://example.org/fhir/favourite-colour", keep_as_text);
+
+    let patient = convert_patient(
+        &fhir_patient,
+        profiles.get(Some("olathe")),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        patient.extensions_url,
+        vec!["http://example.org/fhir/favourite-colour"]
+    );
+    assert_eq!(patient.extensions_value, vec!["green"]);
+}
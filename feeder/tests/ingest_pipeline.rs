@@ -0,0 +1,289 @@
+mod utils;
+
+use std::sync::Mutex;
+
+use clickhouse::{
+    Client,
+    test::{Mock, handlers},
+};
+use feeder::{
+    fhir_r4b_shemav1::BundlePolicy,
+    ingest::{MessageOrigin, flush_stats, process_payload, reprocess_quarantine},
+    profile::{FacilityProfile, FacilityProfiles},
+    rules::RejectionRule,
+    schemav1::{FieldOutcome, QuarantineEntry},
+    stats::ConversionStats,
+};
+use utils::bundle_of;
+
+const PATIENT_1: &str = include_str!("assets/patient_1.json");
+const ENCOUNTER_1: &str = include_str!("assets/encounter_1.json");
+const LOCATION_1: &str = include_str!("assets/location_1.json");
+const MEDICATION_1: &str = include_str!("assets/medication_1.json");
+const MEDICATION_REQUEST_1: &str = include_str!("assets/medication_request_1.json");
+
+#[tokio::test]
+async fn process_bundle_inserts_each_table() {
+    let mock = Mock::new();
+    let client = Client::default().with_url(mock.url());
+    // One insert per table, the mock panics if they aren't made.
+    let _ = mock.add(handlers::record::<()>());
+    let _ = mock.add(handlers::record::<()>());
+
+    let bundle = bundle_of(&[PATIENT_1, ENCOUNTER_1]);
+    process_payload(
+        &client,
+        "attempt_1_1",
+        &FacilityProfile::default(),
+        &Mutex::new(ConversionStats::default()),
+        &MessageOrigin::default(),
+        bundle.as_bytes(),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn rejected_resources_are_quarantined() {
+    let mock = Mock::new();
+    let client = Client::default().with_url(mock.url());
+    // Patient, Encounter and Quarantine
+    let _ = mock.add(handlers::record::<()>());
+    let _ = mock.add(handlers::record::<()>());
+    let recorded = mock.add(handlers::record::<QuarantineEntry>());
+
+    let mut json: serde_json::Value = serde_json::from_str(ENCOUNTER_1).unwrap();
+    json["id"] = "no-subject".into();
+    json.as_object_mut().unwrap().remove("subject");
+    let no_subject = serde_json::to_string_pretty(&json).unwrap();
+    let bundle = bundle_of(&[PATIENT_1, ENCOUNTER_1, &no_subject]);
+
+    let profile = FacilityProfile {
+        bundle_policy: BundlePolicy::BestEffort,
+        rules: vec![RejectionRule::SubjectNotPatient],
+        ..Default::default()
+    };
+    let rejected = process_payload(
+        &client,
+        "attempt_1_1",
+        &profile,
+        &Mutex::new(ConversionStats::default()),
+        &MessageOrigin::default(),
+        bundle.as_bytes(),
+    )
+    .await
+    .unwrap()
+    .rejected;
+    assert_eq!(rejected.len(), 1);
+    assert_eq!(rejected[0].resource_id.as_deref(), Some("no-subject"));
+
+    // The resource is kept exactly as it was sent
+    let entries = recorded.collect::<Vec<QuarantineEntry>>().await;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].resource_type, "Encounter");
+    assert_eq!(entries[0].payload, no_subject);
+}
+
+#[tokio::test]
+async fn quarantined_medication_requests_keep_their_medications() {
+    let mock = Mock::new();
+    let client = Client::default().with_url(mock.url());
+    // Only the quarantine, a Medication has no table of its own
+    let recorded = mock.add(handlers::record::<QuarantineEntry>());
+
+    let mut request: serde_json::Value = serde_json::from_str(MEDICATION_REQUEST_1).unwrap();
+    request["subject"]["reference"] = "Patient?identifier=123".into();
+    let bundle = serde_json::json!({
+        "resourceType": "Bundle",
+        "type": "transaction",
+        "entry": [
+            { "fullUrl": "urn:uuid:5b2e9f0c-7d3a-4e61-b8c4-1f9a6d2e3c58", "resource": request },
+            {
+                "fullUrl": "urn:uuid:c6a8b5e2-4f1d-4a7b-9e3c-2d8f6b1a0e47",
+                "resource": serde_json::from_str::<serde_json::Value>(MEDICATION_1).unwrap()
+            }
+        ]
+    });
+
+    let profile = FacilityProfile {
+        bundle_policy: BundlePolicy::BestEffort,
+        ..Default::default()
+    };
+    let rejected = process_payload(
+        &client,
+        "attempt_1_1",
+        &profile,
+        &Mutex::new(ConversionStats::default()),
+        &MessageOrigin::default(),
+        bundle.to_string().as_bytes(),
+    )
+    .await
+    .unwrap()
+    .rejected;
+    assert_eq!(rejected.len(), 1);
+
+    // The request's entry comes along with the Medication entry it points at,
+    // so its code can still be found when it's reprocessed
+    let entries = recorded.collect::<Vec<QuarantineEntry>>().await;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].resource_type, "MedicationRequest");
+    let quarantined: serde_json::Value = serde_json::from_str(&entries[0].payload).unwrap();
+    assert_eq!(quarantined["resourceType"], "Bundle");
+    assert_eq!(quarantined["entry"], bundle["entry"]);
+}
+
+#[tokio::test]
+async fn process_garbage_is_quarantined() {
+    let mock = Mock::new();
+    let client = Client::default().with_url(mock.url());
+    let recorded = mock.add(handlers::record::<QuarantineEntry>());
+
+    let origin = MessageOrigin {
+        topic: "test_bundles".to_string(),
+        partition: 0,
+        offset: 42,
+    };
+    let rejected = process_payload(
+        &client,
+        "attempt_1_1",
+        &FacilityProfile::default(),
+        &Mutex::new(ConversionStats::default()),
+        &origin,
+        b"{ not json",
+    )
+    .await
+    .unwrap()
+    .rejected;
+    assert_eq!(rejected.len(), 1);
+    assert_eq!(rejected[0].reason, "invalid_json");
+
+    let entries = recorded.collect::<Vec<QuarantineEntry>>().await;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].resource_type, "Bundle");
+    assert_eq!(entries[0].kafka_offset, 42);
+    assert_eq!(entries[0].payload, "{ not json");
+}
+
+#[tokio::test]
+async fn bundles_that_now_convert_leave_quarantine() {
+    let mock = Mock::new();
+    let client = Client::default().with_url(mock.url());
+
+    let entry = |resource_type: &str, payload: &str| QuarantineEntry {
+        time: time::OffsetDateTime::UNIX_EPOCH,
+        kafka_topic: "test_bundles".to_string(),
+        kafka_partition: 0,
+        kafka_offset: 7,
+        facility: "default".to_string(),
+        resource_type: resource_type.to_string(),
+        resource_id: String::new(),
+        reason: "missing_required".to_string(),
+        path: "Patient.birthDate".to_string(),
+        payload: payload.to_string(),
+    };
+    let _ = mock.add(handlers::provide(vec![
+        entry("Patient", PATIENT_1),
+        entry("Bundle", "{ still not json"),
+    ]));
+    // The patient is stored, then removed from the quarantine
+    let _ = mock.add(handlers::record::<()>());
+    let deleted = mock.add(handlers::record_ddl());
+
+    let summary = reprocess_quarantine(&client, "attempt_1_1", &FacilityProfiles::default())
+        .await
+        .unwrap();
+    assert_eq!(summary.resolved, 1);
+    assert_eq!(summary.remaining, 1);
+    assert!(
+        deleted
+            .query()
+            .await
+            .contains("DELETE FROM attempt_1_1.Quarantine")
+    );
+}
+
+#[tokio::test]
+async fn stats_are_counted_and_flushed() {
+    let mock = Mock::new();
+    let client = Client::default().with_url(mock.url());
+    let _ = mock.add(handlers::record::<()>());
+    let _ = mock.add(handlers::record::<()>());
+
+    let mut json: serde_json::Value = serde_json::from_str(ENCOUNTER_1).unwrap();
+    json.as_object_mut().unwrap().remove("period");
+    let bundle = bundle_of(&[PATIENT_1, &json.to_string()]);
+
+    let stats = Mutex::new(ConversionStats::default());
+    process_payload(
+        &client,
+        "attempt_1_1",
+        &FacilityProfile::default(),
+        &stats,
+        &MessageOrigin::default(),
+        bundle.as_bytes(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        stats
+            .lock()
+            .unwrap()
+            .count("default", "Encounter.period.start", FieldOutcome::Missing),
+        1
+    );
+
+    // One more insert for the stats themselves
+    let _ = mock.add(handlers::record::<()>());
+    flush_stats(&client, "attempt_1_1", &stats).await.unwrap();
+    assert!(stats.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn stats_of_bundles_quarantined_whole_are_not_counted() {
+    let mock = Mock::new();
+    let client = Client::default().with_url(mock.url());
+    // Only the quarantine
+    let _ = mock.add(handlers::record::<()>());
+
+    let mut json: serde_json::Value = serde_json::from_str(ENCOUNTER_1).unwrap();
+    json.as_object_mut().unwrap().remove("period");
+    json["subject"] = serde_json::json!({ "display": "somebody" });
+    let bundle = bundle_of(&[PATIENT_1, &json.to_string()]);
+
+    let stats = Mutex::new(ConversionStats::default());
+    let rejected = process_payload(
+        &client,
+        "attempt_1_1",
+        &FacilityProfile::default(),
+        &stats,
+        &MessageOrigin::default(),
+        bundle.as_bytes(),
+    )
+    .await
+    .unwrap()
+    .rejected;
+    assert!(rejected[0].is_whole_bundle());
+    assert!(stats.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn locations_are_stored_without_refreshing_the_closure() {
+    let mock = Mock::new();
+    let client = Client::default().with_url(mock.url());
+    // Only the Location insert, the closure is left to the ingest loop
+    let _ = mock.add(handlers::record::<()>());
+
+    let bundle = bundle_of(&[LOCATION_1]);
+    let processed = process_payload(
+        &client,
+        "attempt_1_1",
+        &FacilityProfile::default(),
+        &Mutex::new(ConversionStats::default()),
+        &MessageOrigin::default(),
+        bundle.as_bytes(),
+    )
+    .await
+    .unwrap();
+    assert!(processed.rejected.is_empty());
+    assert!(processed.stored_locations);
+}
@@ -0,0 +1,106 @@
+mod utils;
+
+use feeder::{
+    fhir_r4b_shemav1::{ConversionErrorReason, convert_procedure},
+    profile::FacilityProfile,
+    schemav1::{
+        ClinicalTimeType, FieldOutcome, ProcedureStatus, Resource, TimeResolution,
+        db_ops::{insert_bundle, install_schema_v1},
+    },
+    stats::ConversionStats,
+};
+use fhir_model::r4b::resources::Procedure;
+use time::macros::datetime;
+use utils::{connect_to_clickhouse_test_container, drop_db};
+
+const PROCEDURE_1: &str = include_str!("assets/procedure_1.json");
+
+#[tokio::test]
+async fn parse_and_insert() {
+    let client = connect_to_clickhouse_test_container();
+
+    let fhir_procedure = serde_json::from_str::<Procedure>(PROCEDURE_1).unwrap();
+    let procedure = convert_procedure(
+        &fhir_procedure,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+
+    drop_db(&client, "attempt_1_1").await;
+    install_schema_v1(&client, "attempt_1_1").await.unwrap();
+
+    insert_bundle(&client, "attempt_1_1", &[Resource::Procedure(procedure)])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn performed_period_is_kept() {
+    let fhir_procedure = serde_json::from_str::<Procedure>(PROCEDURE_1).unwrap();
+    let mut stats = ConversionStats::default();
+    let procedure =
+        convert_procedure(&fhir_procedure, &FacilityProfile::default(), &mut stats).unwrap();
+
+    assert!(matches!(procedure.status, ProcedureStatus::Completed));
+    assert_eq!(procedure.category, "387713003");
+    assert_eq!(procedure.code, "80146002");
+    assert_eq!(procedure.code_description, "Appendectomy");
+    assert_eq!(procedure.subject, "7d9aa431-cd72-8aa2-9559-5920937d9330");
+    assert_eq!(procedure.encounter, "00017486-6c88-2b8a-ca28-7f147efb8848");
+    assert_eq!(procedure.performed_type, ClinicalTimeType::Period);
+    assert_eq!(
+        procedure.performed_start,
+        Some(datetime!(2001-06-11 09:12 -04:00))
+    );
+    assert_eq!(procedure.performed_start_resolution, TimeResolution::Second);
+    assert_eq!(
+        procedure.performed_end,
+        Some(datetime!(2001-06-11 10:41 -04:00))
+    );
+    assert_eq!(procedure.body_site, vec!["66754008"]);
+    assert_eq!(procedure.reasons_code, vec!["74400008"]);
+    assert_eq!(procedure.reasons_description, vec!["Appendicitis"]);
+    assert!(stats.is_empty());
+}
+
+#[tokio::test]
+async fn required_code_is_enforced() {
+    let mut json: serde_json::Value = serde_json::from_str(PROCEDURE_1).unwrap();
+    json.as_object_mut().unwrap().remove("code");
+    let fhir_procedure: Procedure = serde_json::from_value(json).unwrap();
+
+    let mut stats = ConversionStats::default();
+    let procedure =
+        convert_procedure(&fhir_procedure, &FacilityProfile::default(), &mut stats).unwrap();
+    assert_eq!(procedure.code, "");
+    assert_eq!(
+        stats.count("default", "Procedure.code", FieldOutcome::Missing),
+        1
+    );
+
+    let profile = FacilityProfile {
+        required: vec!["Procedure.code".to_string()],
+        ..Default::default()
+    };
+    let err =
+        convert_procedure(&fhir_procedure, &profile, &mut ConversionStats::default()).unwrap_err();
+    assert_eq!(err.reason, ConversionErrorReason::MissingRequired);
+    assert_eq!(err.path, "Procedure.code");
+}
+
+#[tokio::test]
+async fn group_subjects_are_left_empty() {
+    let mut json: serde_json::Value = serde_json::from_str(PROCEDURE_1).unwrap();
+    json["subject"]["reference"] = "Group/1".into();
+    let fhir_procedure: Procedure = serde_json::from_value(json).unwrap();
+
+    let mut stats = ConversionStats::default();
+    let procedure =
+        convert_procedure(&fhir_procedure, &FacilityProfile::default(), &mut stats).unwrap();
+    assert_eq!(procedure.subject, "");
+    assert_eq!(
+        stats.count("default", "Procedure.subject", FieldOutcome::Truncated),
+        1
+    );
+}
@@ -0,0 +1,161 @@
+mod utils;
+
+use feeder::{
+    fhir_r4b_shemav1::{
+        ConversionErrorReason, convert_organization, convert_practitioner,
+        convert_practitioner_role,
+    },
+    profile::FacilityProfile,
+    schemav1::{
+        FieldOutcome, Resource, TimeResolution,
+        db_ops::{insert_bundle, install_schema_v1},
+    },
+    stats::ConversionStats,
+};
+use fhir_model::r4b::resources::{Organization, Practitioner, PractitionerRole};
+use time::macros::datetime;
+use utils::{connect_to_clickhouse_test_container, drop_db};
+
+const PRACTITIONER_1: &str = include_str!("assets/practitioner_1.json");
+const PRACTITIONER_ROLE_1: &str = include_str!("assets/practitioner_role_1.json");
+const ORGANIZATION_1: &str = include_str!("assets/organization_1.json");
+
+#[tokio::test]
+async fn parse_and_insert() {
+    let client = connect_to_clickhouse_test_container();
+    let profile = FacilityProfile::default();
+    let mut stats = ConversionStats::default();
+
+    let practitioner = convert_practitioner(
+        &serde_json::from_str::<Practitioner>(PRACTITIONER_1).unwrap(),
+        &profile,
+        &mut stats,
+    )
+    .unwrap();
+    let role = convert_practitioner_role(
+        &serde_json::from_str::<PractitionerRole>(PRACTITIONER_ROLE_1).unwrap(),
+        &profile,
+        &mut stats,
+    )
+    .unwrap();
+    let organization = convert_organization(
+        &serde_json::from_str::<Organization>(ORGANIZATION_1).unwrap(),
+        &profile,
+        &mut stats,
+    )
+    .unwrap();
+
+    drop_db(&client, "attempt_1_1").await;
+    install_schema_v1(&client, "attempt_1_1").await.unwrap();
+
+    insert_bundle(
+        &client,
+        "attempt_1_1",
+        &[
+            Resource::Practitioner(practitioner),
+            Resource::PractitionerRole(role),
+            Resource::Organization(organization),
+        ],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn practitioner_keeps_npi_and_name() {
+    let fhir_practitioner = serde_json::from_str::<Practitioner>(PRACTITIONER_1).unwrap();
+    let mut stats = ConversionStats::default();
+    let practitioner =
+        convert_practitioner(&fhir_practitioner, &FacilityProfile::default(), &mut stats).unwrap();
+
+    assert_eq!(practitioner.npi, "9999997084");
+    assert_eq!(practitioner.name_given, "Delphine Ann");
+    assert_eq!(practitioner.name_family, "Kuhn");
+    assert_eq!(practitioner.names_prefix, vec!["Dr."]);
+    assert_eq!(practitioner.qualifications, vec!["MD"]);
+    assert_eq!(practitioner.addresses_city, vec!["LEOMINSTER"]);
+    assert!(stats.is_empty());
+}
+
+#[tokio::test]
+async fn role_links_practitioner_and_organization() {
+    let fhir_role = serde_json::from_str::<PractitionerRole>(PRACTITIONER_ROLE_1).unwrap();
+    let role = convert_practitioner_role(
+        &fhir_role,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+
+    assert_eq!(role.practitioner, "0000016f-57cb-cdaa-0000-000000000a8c");
+    assert_eq!(role.organization, "ef58ea08-d883-3957-8300-150554edc8fb");
+    assert_eq!(role.period_start, Some(datetime!(2015-01-01 0:00 UTC)));
+    assert_eq!(role.period_start_resolution, TimeResolution::Day);
+    assert_eq!(role.period_end, None);
+    assert_eq!(role.roles, vec!["doctor"]);
+    assert_eq!(role.specialties_code, vec!["208D00000X"]);
+    assert_eq!(role.specialties_description, vec!["General Practice"]);
+    assert_eq!(role.locations, vec!["1b3d5f7a-9c1e-4a3b-8d5f-7a9c1e3b5d72"]);
+}
+
+#[tokio::test]
+async fn unresolvable_role_locations_are_left_out() {
+    let mut json: serde_json::Value = serde_json::from_str(PRACTITIONER_ROLE_1).unwrap();
+    json["location"]
+        .as_array_mut()
+        .unwrap()
+        .push(serde_json::json!({ "display": "Main street clinic" }));
+    let fhir_role = serde_json::from_value::<PractitionerRole>(json).unwrap();
+    let mut stats = ConversionStats::default();
+    let role =
+        convert_practitioner_role(&fhir_role, &FacilityProfile::default(), &mut stats).unwrap();
+
+    assert_eq!(role.locations, vec!["1b3d5f7a-9c1e-4a3b-8d5f-7a9c1e3b5d72"]);
+    assert_eq!(
+        stats.count(
+            "default",
+            "PractitionerRole.location",
+            FieldOutcome::Truncated
+        ),
+        1
+    );
+}
+
+#[tokio::test]
+async fn organization_keeps_its_parent() {
+    let fhir_organization = serde_json::from_str::<Organization>(ORGANIZATION_1).unwrap();
+    let organization = convert_organization(
+        &fhir_organization,
+        &FacilityProfile::default(),
+        &mut ConversionStats::default(),
+    )
+    .unwrap();
+
+    assert_eq!(organization.name, "HEALTHALLIANCE HOSPITALS, INC");
+    assert_eq!(organization.npi, "1234567893");
+    assert_eq!(organization.aliases, vec!["HealthAlliance Leominster"]);
+    assert_eq!(organization.types, vec!["prov"]);
+    assert_eq!(organization.part_of, "4a9e2c1d-7b3f-4e8a-a6c5-0d2f8b1e3c97");
+    assert_eq!(organization.identifiers_value.len(), 2);
+    assert_eq!(organization.addresses_postal_code, vec!["01453"]);
+}
+
+#[tokio::test]
+async fn required_organization_name_is_enforced() {
+    let mut json: serde_json::Value = serde_json::from_str(ORGANIZATION_1).unwrap();
+    json.as_object_mut().unwrap().remove("name");
+    let fhir_organization: Organization = serde_json::from_value(json).unwrap();
+
+    let profile = FacilityProfile {
+        required: vec!["Organization.name".to_string()],
+        ..Default::default()
+    };
+    let err = convert_organization(
+        &fhir_organization,
+        &profile,
+        &mut ConversionStats::default(),
+    )
+    .unwrap_err();
+    assert_eq!(err.reason, ConversionErrorReason::MissingRequired);
+    assert_eq!(err.path, "Organization.name");
+}
@@ -0,0 +1,148 @@
+mod utils;
+
+use feeder::{
+    fhir_r4b_shemav1::{BundlePolicy, convert_bundle},
+    profile::{FacilityProfile, FacilityProfiles},
+    rules::{RejectionRule, ScreenedBundle, apply_rules},
+    schemav1::FieldOutcome,
+    stats::ConversionStats,
+};
+use fhir_model::r4b::resources::Bundle;
+use utils::bundle_of;
+
+const PATIENT_1: &str = include_str!("assets/patient_1.json");
+const ENCOUNTER_1: &str = include_str!("assets/encounter_1.json");
+
+fn screen(resources: &[&str], profile: &FacilityProfile) -> ScreenedBundle {
+    let bundle = bundle_of(resources);
+    let fhir_bundle = serde_json::from_str::<Bundle>(&bundle).unwrap();
+    let mut stats = ConversionStats::default();
+    let converted = convert_bundle(&fhir_bundle, profile, &mut stats).unwrap();
+    apply_rules(&fhir_bundle, converted, profile, &mut stats)
+}
+
+/// ENCOUNTER_1 under a new id with `subject` replaced.
+fn encounter_with_subject(id: &str, subject: serde_json::Value) -> String {
+    let mut json: serde_json::Value = serde_json::from_str(ENCOUNTER_1).unwrap();
+    json["id"] = id.into();
+    json["subject"] = subject;
+    json.to_string()
+}
+
+#[tokio::test]
+async fn rules_are_read_from_profiles() {
+    let profiles = FacilityProfiles::from_json(
+        r#"{ "default": { "rules": [
+            { "rule": "subject_not_patient" },
+            { "rule": "max_failed_percent", "percent": 50 }
+        ] } }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        profiles.default.rules,
+        vec![
+            RejectionRule::SubjectNotPatient,
+            RejectionRule::MaxFailedPercent { percent: 50.0 }
+        ]
+    );
+}
+
+#[tokio::test]
+async fn encounter_without_patient_subject_is_rejected() {
+    let no_subject = encounter_with_subject("no-subject", serde_json::Value::Null);
+    let other = encounter_with_subject("other", serde_json::json!({ "display": "somebody" }));
+    let profile = FacilityProfile {
+        bundle_policy: BundlePolicy::BestEffort,
+        rules: vec![RejectionRule::SubjectNotPatient],
+        ..Default::default()
+    };
+
+    let screened = screen(&[PATIENT_1, ENCOUNTER_1, &no_subject], &profile);
+    assert_eq!(screened.resources.len(), 2);
+    assert_eq!(screened.rejected.len(), 1);
+    assert_eq!(screened.rejected[0].reason, "subject_not_patient");
+    assert_eq!(screened.rejected[0].path, "Encounter.subject");
+    assert_eq!(
+        screened.rejected[0].resource_id.as_deref(),
+        Some("no-subject")
+    );
+    assert_eq!(screened.rejected[0].entry, Some(2));
+
+    // Identifier or display only subjects fail conversion, and are rejected
+    // all the same
+    let screened = screen(&[PATIENT_1, &other], &profile);
+    assert_eq!(screened.resources.len(), 1);
+    assert_eq!(screened.rejected[0].reason, "unsupported_value");
+    assert_eq!(screened.rejected[0].resource_id.as_deref(), Some("other"));
+    assert_eq!(screened.rejected[0].entry, Some(1));
+
+    // Entries without an id are still found
+    let mut no_id: serde_json::Value = serde_json::from_str(ENCOUNTER_1).unwrap();
+    no_id.as_object_mut().unwrap().remove("id");
+    let screened = screen(&[PATIENT_1, &no_id.to_string()], &profile);
+    assert_eq!(screened.rejected[0].reason, "missing_id");
+    assert_eq!(screened.rejected[0].resource_id, None);
+    assert_eq!(screened.rejected[0].entry, Some(1));
+}
+
+#[tokio::test]
+async fn rule_rejections_fail_all_or_nothing_bundles() {
+    let no_subject = encounter_with_subject("no-subject", serde_json::Value::Null);
+    let profile = FacilityProfile {
+        rules: vec![RejectionRule::SubjectNotPatient],
+        ..Default::default()
+    };
+
+    let screened = screen(&[PATIENT_1, ENCOUNTER_1, &no_subject], &profile);
+    assert!(screened.resources.is_empty());
+    assert_eq!(screened.rejected.len(), 1);
+    assert!(screened.rejected[0].is_whole_bundle());
+    assert_eq!(screened.rejected[0].reason, "subject_not_patient");
+    assert_eq!(screened.rejected[0].path, "Encounter.subject");
+}
+
+#[tokio::test]
+async fn too_many_failures_reject_the_bundle() {
+    let no_subject = encounter_with_subject("no-subject", serde_json::Value::Null);
+    let profile = FacilityProfile {
+        bundle_policy: BundlePolicy::BestEffort,
+        rules: vec![
+            RejectionRule::SubjectNotPatient,
+            RejectionRule::MaxFailedPercent { percent: 40.0 },
+        ],
+        ..Default::default()
+    };
+
+    let bundle = bundle_of(&[PATIENT_1, &no_subject]);
+    let fhir_bundle = serde_json::from_str::<Bundle>(&bundle).unwrap();
+    let mut stats = ConversionStats::default();
+    let converted = convert_bundle(&fhir_bundle, &profile, &mut stats).unwrap();
+    let screened = apply_rules(&fhir_bundle, converted, &profile, &mut stats);
+    assert!(screened.resources.is_empty());
+    assert_eq!(screened.rejected.len(), 2);
+    assert_eq!(screened.rejected[1].reason, "max_failed_percent");
+    assert_eq!(screened.rejected[1].entry, Some(0));
+    assert_eq!(
+        stats.count("default", "Bundle.entry", FieldOutcome::Rejected),
+        1
+    );
+}
+
+#[tokio::test]
+async fn failures_are_rejected_without_any_rules() {
+    let unresolvable =
+        encounter_with_subject("unresolvable", serde_json::json!({ "display": "somebody" }));
+    let profile = FacilityProfile {
+        bundle_policy: BundlePolicy::BestEffort,
+        ..Default::default()
+    };
+
+    let screened = screen(&[PATIENT_1, ENCOUNTER_1, &unresolvable], &profile);
+    assert_eq!(screened.resources.len(), 2);
+    assert_eq!(screened.rejected.len(), 1);
+    assert_eq!(
+        screened.rejected[0].resource_id.as_deref(),
+        Some("unresolvable")
+    );
+    assert_eq!(screened.rejected[0].entry, Some(2));
+}
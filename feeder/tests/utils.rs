@@ -1,3 +1,6 @@
+// Each test binary only uses some of these
+#![allow(dead_code)]
+
 use clickhouse::Client;
 
 pub fn connect_to_clickhouse_test_container() -> Client {
@@ -10,3 +13,15 @@ pub fn connect_to_clickhouse_test_container() -> Client {
 pub async fn drop_db(client: &Client, db_name: &str) {
     client.query(&format!("DROP DATABASE IF EXISTS {}", db_name)).execute().await.unwrap()
 }
+
+/// Wraps resource json in a collection bundle.
+pub fn bundle_of(resources: &[&str]) -> String {
+    let entries: Vec<String> = resources
+        .iter()
+        .map(|res| format!(r#"{{ "resource": {} }}"#, res))
+        .collect();
+    format!(
+        r#"{{ "resourceType": "Bundle", "type": "collection", "entry": [{}] }}"#,
+        entries.join(",")
+    )
+}